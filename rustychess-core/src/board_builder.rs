@@ -0,0 +1,246 @@
+use crate::chessboard::{CastlingRights, Chessboard2};
+use crate::error::Error;
+use crate::file::File;
+use crate::game::Game;
+use crate::pgn::Position;
+use crate::pieces::{Color, Kind, Piece};
+use crate::variant::{Rules, Standard};
+use std::sync::Arc;
+
+/// Sets up a position piece by piece. `build` checks that the position could occur
+/// in a game before turning it into a `Game`
+#[derive(Debug, Clone)]
+pub struct BoardBuilder {
+    rules: Arc<dyn Rules>,
+    board: Chessboard2,
+    turn: Color,
+    castling: CastlingRights,
+    en_passant: Option<Position>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        BoardBuilder::new()
+    }
+}
+
+impl BoardBuilder {
+    /// Starts from an empty 8x8 board of standard chess with white to move and no
+    /// castling rights
+    pub fn new() -> BoardBuilder {
+        BoardBuilder {
+            rules: Arc::new(Standard),
+            board: Chessboard2::new(),
+            turn: Color::White,
+            castling: CastlingRights::none(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    /// Starts from an empty board with `width` columns and `height` rows
+    pub fn with_size(width: usize, height: usize) -> Result<BoardBuilder, Error> {
+        Ok(BoardBuilder {
+            board: Chessboard2::with_size(width, height)?,
+            ..BoardBuilder::new()
+        })
+    }
+
+    /// Starts from the current position of `game`, keeping its variant
+    pub fn from_game(game: &Game) -> BoardBuilder {
+        BoardBuilder {
+            rules: game.shared_rules(),
+            board: game.board.clone(),
+            turn: game.turn(),
+            castling: game.castling(),
+            en_passant: game.en_passant(),
+            halfmove_clock: game.halfmove_clock(),
+            fullmove_number: game.fullmove_number(),
+        }
+    }
+
+    /// Plays the built game by these rules
+    pub fn rules(mut self, rules: Arc<dyn Rules>) -> BoardBuilder {
+        self.rules = rules;
+        self
+    }
+
+    /// Puts a piece on `pos`, replacing the one standing there
+    pub fn piece(mut self, pos: Position, kind: Kind, color: Color) -> BoardBuilder {
+        self.board.set_(pos, Piece::new(kind, color));
+        self
+    }
+
+    /// Clears the square `pos`
+    pub fn remove(mut self, pos: Position) -> BoardBuilder {
+        self.board.remove(pos);
+        self
+    }
+
+    pub fn side_to_move(mut self, color: Color) -> BoardBuilder {
+        self.turn = color;
+        self
+    }
+
+    pub fn castling(mut self, castling: CastlingRights) -> BoardBuilder {
+        self.castling = castling;
+        self
+    }
+
+    /// Sets the square a pawn skipped with its last move
+    pub fn en_passant(mut self, pos: Option<Position>) -> BoardBuilder {
+        self.en_passant = pos;
+        self
+    }
+
+    pub fn move_numbers(mut self, halfmove_clock: u32, fullmove_number: u32) -> BoardBuilder {
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
+        self
+    }
+
+    /// Checks the position and creates a game from it. Every side needs exactly one
+    /// king unless the rules make it an ordinary piece, pawns stay off the first and
+    /// last row, the side not to move is not in check, castling rights need the king
+    /// and rook at home and an en passant square lies behind a pawn that just moved
+    /// two squares
+    pub fn build(&self) -> Result<Game, Error> {
+        self.check_pieces()?;
+        self.check_castling()?;
+        self.check_en_passant()?;
+
+        let mut game = Game::with_rules(self.rules.clone());
+        let mut board = self.board.clone();
+        // variants with drops start with empty pockets
+        if board.pockets().is_none() {
+            board.set_pockets(game.board.pockets().copied());
+        }
+        game.set_position(
+            board,
+            self.turn,
+            self.castling,
+            self.en_passant,
+            [self.halfmove_clock, self.fullmove_number],
+            [0; 2],
+        );
+        Ok(game)
+    }
+
+    fn check_pieces(&self) -> Result<(), Error> {
+        let last = self.board.last_file();
+        for (pos, piece) in self.board.pieces() {
+            if !self.board.contains(pos) {
+                return Err(invalid(format!("{} stands outside the board", pos.algebraic())));
+            }
+            if piece.kind == Kind::Pawn && (pos.file == File::First || pos.file == last) {
+                return Err(invalid(format!("{} pawn on {}", color_name(piece.color), pos.algebraic())));
+            }
+        }
+
+        // without royal kings any number of kings is fine
+        if self.rules.has_royal_king() {
+            for color in [Color::White, Color::Black] {
+                match self.board.find_pieces(Kind::King, color).len() {
+                    1 => {}
+                    0 => return Err(invalid(format!("{} has no king", color_name(color)))),
+                    n => return Err(invalid(format!("{} has {} kings", color_name(color), n))),
+                }
+            }
+        }
+
+        let waiting = self.turn.switch();
+        if self.rules.is_check(&self.board, waiting) {
+            return Err(invalid(format!(
+                "{} is in check with {} to move",
+                color_name(waiting),
+                color_name(self.turn)
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_castling(&self) -> Result<(), Error> {
+        let sides = [
+            (Color::White, true, self.castling.white_king_side),
+            (Color::White, false, self.castling.white_queen_side),
+            (Color::Black, true, self.castling.black_king_side),
+            (Color::Black, false, self.castling.black_queen_side),
+        ];
+
+        for (color, king_side, _) in sides.iter().filter(|s| s.2) {
+            let home = if *color == Color::White {
+                File::First
+            } else {
+                self.board.last_file()
+            };
+            let (name, rook) = if *king_side {
+                ("king side", self.castling.king_rook)
+            } else {
+                ("queen side", self.castling.queen_rook)
+            };
+            let castles = || format!("{} castling {}", color_name(*color), name);
+
+            let king = match self.board.find_piece(Kind::King, *color) {
+                Ok((pos, _)) if pos.file == home => pos.rank,
+                _ => return Err(invalid(format!("{} needs the king on its first row", castles()))),
+            };
+            let rook_pos = Position::new_(rook, home);
+            let is_rook = matches!(
+                self.board.get_with_pos(&rook_pos),
+                Some(p) if p.kind == Kind::Rook && p.color == *color
+            );
+            if !is_rook || (rook > king) != *king_side {
+                return Err(invalid(format!("{} needs a rook on {}", castles(), rook_pos.algebraic())));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_en_passant(&self) -> Result<(), Error> {
+        let ep = match self.en_passant {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        // the pawn of the side not to move went from `start` over `ep` to `pawn`
+        let (direction, row) = match self.turn {
+            Color::White => (-1, self.board.height().saturating_sub(3)),
+            _ => (1, 2),
+        };
+        let implausible = || {
+            invalid(format!(
+                "en passant square {} does not lie behind a pawn that just moved two squares",
+                ep.algebraic()
+            ))
+        };
+        if !self.board.contains(&ep) || ep.file.to_index() != row {
+            return Err(implausible());
+        }
+
+        let start = ep.offset(0, -direction).ok_or_else(implausible)?;
+        let pawn = ep.offset(0, direction).ok_or_else(implausible)?;
+        let moved = matches!(
+            self.board.get_with_pos(&pawn),
+            Some(p) if p.kind == Kind::Pawn && p.color == self.turn.switch()
+        );
+        let empty = self.board.get_with_pos(&ep).is_none() && self.board.get_with_pos(&start).is_none();
+        if !moved || !empty {
+            return Err(implausible());
+        }
+        Ok(())
+    }
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidBoard { reason }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+        Color::Unknown => "nobody",
+    }
+}
@@ -0,0 +1,390 @@
+use crate::chessboard::{CastlingRights, Chessboard2};
+use crate::file::File;
+use crate::error::Error as ChessError;
+use crate::game::{Game, GameResult, Move};
+use crate::pgn::{PgnGame, Position};
+use crate::pieces::{Color, Kind, Piece};
+use crate::polyglot_random::RANDOM64;
+use crate::rank::Rank;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CASTLING_KEYS: usize = 768;
+const EN_PASSANT_KEYS: usize = 772;
+const TURN_KEY: usize = 780;
+
+/// One 16 byte record of a Polyglot book, stored big endian
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn from_bytes(b: &[u8; 16]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+            raw_move: u16::from_be_bytes([b[8], b[9]]),
+            weight: u16::from_be_bytes([b[10], b[11]]),
+            learn: u32::from_be_bytes([b[12], b[13], b[14], b[15]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut r = [0; 16];
+        r[..8].copy_from_slice(&self.key.to_be_bytes());
+        r[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        r[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        r[12..].copy_from_slice(&self.learn.to_be_bytes());
+        r
+    }
+}
+
+/// An opening book in the Polyglot .bin format
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Ok(Book::from_bytes(&fs::read(path)?))
+    }
+
+    /// Reads the entries of a book file, a truncated last entry is ignored
+    pub fn from_bytes(bytes: &[u8]) -> Book {
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(16)
+            .map(|chunk| {
+                let mut b = [0; 16];
+                b.copy_from_slice(chunk);
+                BookEntry::from_bytes(&b)
+            })
+            .collect();
+
+        // lookups rely on the order the format requires, keep it for sloppy files
+        if entries.windows(2).any(|w| w[0].key > w[1].key) {
+            entries.sort_by_key(|e| e.key);
+        }
+        Book { entries }
+    }
+
+    /// Creates a book from entries in any order
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Book {
+        entries.sort_by_key(|e| (e.key, Reverse(e.weight)));
+        Book { entries }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut file)?;
+        file.flush()
+    }
+
+    pub fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        for entry in self.entries.iter() {
+            writer.write_all(&entry.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns all entries stored for the position with the Polyglot key `key`
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    /// Returns the legal book moves of the position with their weights, best first
+    pub fn moves(&self, game: &Game) -> Vec<(Move, u16)> {
+        let mut r: Vec<(Move, u16)> = self
+            .entries(game.polyglot_key())
+            .iter()
+            .filter_map(|e| decode_move(game, e.raw_move).map(|m| (m, e.weight)))
+            .collect();
+        r.sort_by_key(|(_, weight)| Reverse(*weight));
+        r
+    }
+
+    /// Returns the book move with the highest weight
+    pub fn best_move(&self, game: &Game) -> Option<Move> {
+        self.moves(game).first().map(|(m, _)| *m)
+    }
+
+    /// Picks a book move with a probability proportional to its weight, `seed`
+    /// selects the move so equal seeds give equal choices
+    pub fn weighted_move(&self, game: &Game, seed: u64) -> Option<Move> {
+        let moves = self.moves(game);
+        let total: u64 = moves.iter().map(|(_, w)| *w as u64).sum();
+        if total == 0 {
+            return moves.first().map(|(m, _)| *m);
+        }
+
+        let mut roll = seed % total;
+        for (m, weight) in moves.iter() {
+            if roll < *weight as u64 {
+                return Some(*m);
+            }
+            roll -= *weight as u64;
+        }
+        None
+    }
+
+    /// Picks a weighted book move, seeded from the system time
+    pub fn random_move(&self, game: &Game) -> Option<Move> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        // spread the low bits of the time over the whole range
+        self.weighted_move(game, nanos.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 16)
+    }
+}
+
+/// How a move did in the games a book is built from, seen from the side playing it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveStats {
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    /// The Polyglot weight, two points for a win and one for a draw
+    pub fn score(&self) -> u32 {
+        2 * self.wins + self.draws
+    }
+}
+
+/// Collects the moves of PGN games into a Polyglot book
+#[derive(Debug, Clone)]
+pub struct BookBuilder {
+    /// Moves after this many plies are not recorded
+    pub max_ply: usize,
+    /// Moves played in fewer games are left out
+    pub min_games: u32,
+    /// Only moves of players rated at least this much are recorded,
+    /// games without the Elo tag of the mover then do not count
+    pub min_elo: Option<u32>,
+    stats: HashMap<(u64, u16), MoveStats>,
+    games: usize,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        BookBuilder::new()
+    }
+}
+
+impl BookBuilder {
+    pub fn new() -> BookBuilder {
+        BookBuilder {
+            max_ply: 20,
+            min_games: 1,
+            min_elo: None,
+            stats: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    /// Returns the number of games added so far
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// Replays a game and records its moves, stopping at the first illegal one
+    pub fn add_game(&mut self, pgn: &PgnGame) -> Result<(), ChessError> {
+        let mut game = match pgn.tag("FEN") {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::new(),
+        };
+        let result = GameResult::from_pgn(&pgn.result);
+        let rated = |tag: &str| match self.min_elo {
+            Some(min) => pgn.tag(tag).and_then(|v| v.parse::<u32>().ok()).unwrap_or(0) >= min,
+            None => true,
+        };
+        let rated = [rated("WhiteElo"), rated("BlackElo")];
+        self.games += 1;
+
+        for san in pgn.moves.iter().take(self.max_ply) {
+            let m = game.san_move(&san.san)?;
+            let color = game.turn();
+            if rated[if color == Color::White { 0 } else { 1 }] {
+                let stats = self
+                    .stats
+                    .entry((game.polyglot_key(), encode_move(&m)))
+                    .or_default();
+                stats.games += 1;
+                match result {
+                    Some(GameResult::Draw) => stats.draws += 1,
+                    Some(r) if r == GameResult::win_for(color) => stats.wins += 1,
+                    Some(_) => stats.losses += 1,
+                    None => {}
+                }
+            }
+            game.play(m);
+        }
+        Ok(())
+    }
+
+    /// Returns what was recorded for a move in the position with the key `key`
+    pub fn stats(&self, key: u64, raw_move: u16) -> Option<MoveStats> {
+        self.stats.get(&(key, raw_move)).cloned()
+    }
+
+    /// Creates the book, weights are scaled down per position to fit 16 bits
+    /// and moves that never scored are left out
+    pub fn build(&self) -> Book {
+        let mut positions: HashMap<u64, Vec<(u16, u32)>> = HashMap::new();
+        for ((key, raw_move), stats) in self.stats.iter() {
+            if stats.games >= self.min_games && stats.score() > 0 {
+                positions.entry(*key).or_default().push((*raw_move, stats.score()));
+            }
+        }
+
+        let mut entries = Vec::new();
+        for (key, moves) in positions {
+            let max = moves.iter().map(|(_, score)| *score).max().unwrap_or(0) as u64;
+            for (raw_move, score) in moves {
+                let weight = if max > u16::MAX as u64 {
+                    (score as u64 * u16::MAX as u64 / max).max(1)
+                } else {
+                    score as u64
+                };
+                entries.push(BookEntry {
+                    key,
+                    raw_move,
+                    weight: weight as u16,
+                    learn: 0,
+                });
+            }
+        }
+        Book::from_entries(entries)
+    }
+}
+
+fn kind_index(piece: &Piece) -> usize {
+    let kind = match piece.kind {
+        Kind::Pawn => 0,
+        Kind::Knight => 1,
+        Kind::Bishop => 2,
+        Kind::Rook => 3,
+        Kind::Queen => 4,
+        Kind::King => 5,
+        // not in Polyglot books, see `polyglot_key`
+        Kind::Fairy(_) => 0,
+    };
+    kind * 2 + (piece.color == Color::White) as usize
+}
+
+fn square_index(pos: &Position) -> usize {
+    8 * pos.file.to_index() + pos.rank.to_index()
+}
+
+/// Computes the Polyglot hash of a position
+pub fn polyglot_key(
+    board: &Chessboard2,
+    turn: Color,
+    castling: CastlingRights,
+    en_passant: Option<Position>,
+) -> u64 {
+    let mut key = 0;
+    let hashed = |pos: &Position, p: &Piece| {
+        !matches!(p.kind, Kind::Fairy(_)) && pos.rank.to_index() < 8 && pos.file.to_index() < 8
+    };
+    for (pos, piece) in board.pieces().filter(|(pos, p)| hashed(pos, p)) {
+        key ^= RANDOM64[64 * kind_index(piece) + square_index(pos)];
+    }
+
+    let rights = [
+        castling.white_king_side,
+        castling.white_queen_side,
+        castling.black_king_side,
+        castling.black_queen_side,
+    ];
+    for (i, allowed) in rights.iter().enumerate() {
+        if *allowed {
+            key ^= RANDOM64[CASTLING_KEYS + i];
+        }
+    }
+
+    // the en passant file only counts when a pawn stands ready to capture
+    if let Some(ep) = en_passant {
+        let pawn_file = if turn == Color::White { -1 } else { 1 };
+        let can_capture = [-1, 1].iter().any(|side| {
+            ep.offset(*side, pawn_file)
+                .and_then(|pos| board.get_with_pos(&pos))
+                .is_some_and(|p| p.kind == Kind::Pawn && p.color == turn)
+        });
+        if can_capture {
+            key ^= RANDOM64[EN_PASSANT_KEYS + ep.rank.to_index()];
+        }
+    }
+
+    if turn == Color::White {
+        key ^= RANDOM64[TURN_KEY];
+    }
+    key
+}
+
+fn promotion_index(kind: Option<Kind>) -> u16 {
+    match kind {
+        Some(Kind::Knight) => 1,
+        Some(Kind::Bishop) => 2,
+        Some(Kind::Rook) => 3,
+        Some(Kind::Queen) => 4,
+        _ => 0,
+    }
+}
+
+/// Encodes a move the Polyglot way, castling is written as the king taking its rook
+pub fn encode_move(m: &Move) -> u16 {
+    let to = if m.is_castling {
+        Chessboard2::castling_rook(m).0
+    } else {
+        m.to
+    };
+
+    (to.rank.to_index() as u16)
+        | (to.file.to_index() as u16) << 3
+        | (m.from.rank.to_index() as u16) << 6
+        | (m.from.file.to_index() as u16) << 9
+        | promotion_index(m.promotion) << 12
+}
+
+/// Finds the legal move of `game` a Polyglot move stands for
+pub fn decode_move(game: &Game, raw: u16) -> Option<Move> {
+    let square = |bits: u16| {
+        Position::new_(
+            Rank::from_index((bits & 7) as usize),
+            File::from_index((bits >> 3 & 7) as usize),
+        )
+    };
+    let to = square(raw);
+    let from = square(raw >> 6);
+    let promotion = raw >> 12 & 7;
+
+    game.legal_moves().into_iter().find(|m| {
+        let target = if m.is_castling {
+            Chessboard2::castling_rook(m).0
+        } else {
+            m.to
+        };
+        m.from == from && target == to && promotion_index(m.promotion) == promotion
+    })
+}
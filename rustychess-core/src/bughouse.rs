@@ -0,0 +1,261 @@
+use crate::chessboard::Pockets;
+use crate::clock::{Clock, TimeControl};
+use crate::error::Error;
+use crate::game::{Game, GameResult, Move, Termination};
+use crate::pgn::{write_movetext, write_tags, PgnGame};
+use crate::variant::Bughouse;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+/// Two boards played by two teams of two: white on board A partners black on board B.
+/// Pieces captured on one board go to the partner's pocket on the other board
+#[derive(Debug, Clone)]
+pub struct BughouseMatch {
+    boards: [Game; 2],
+    /// Board of every move, in the order they were played
+    order: Vec<usize>,
+}
+
+impl Default for BughouseMatch {
+    fn default() -> Self {
+        BughouseMatch::new()
+    }
+}
+
+impl BughouseMatch {
+    pub fn new() -> BughouseMatch {
+        BughouseMatch {
+            boards: [Game::with_rules(Arc::new(Bughouse)), Game::with_rules(Arc::new(Bughouse))],
+            order: Vec::new(),
+        }
+    }
+
+    /// Creates a match where both boards get a clock with the given time control
+    pub fn with_clocks(control: TimeControl) -> BughouseMatch {
+        let mut r = BughouseMatch::new();
+        r.set_clock(0, Clock::new(control.clone()));
+        r.set_clock(1, Clock::new(control));
+        r
+    }
+
+    /// Board 0 is board A, 1 is board B
+    pub fn board(&self, board: usize) -> &Game {
+        &self.boards[board]
+    }
+
+    pub fn set_clock(&mut self, board: usize, clock: Clock) {
+        self.boards[board].set_clock(clock);
+    }
+
+    /// Returns the moves of both boards with their board, in the order they were played
+    pub fn moves(&self) -> Vec<(usize, Move)> {
+        let mut played = [0; 2];
+        self.order
+            .iter()
+            .map(|board| {
+                let m = self.boards[*board].moves()[played[*board]];
+                played[*board] += 1;
+                (*board, m)
+            })
+            .collect()
+    }
+
+    /// Plays a legal move on `board` and passes a captured piece on to the partner.
+    /// Returns false without playing the move when the mover ran out of time
+    pub fn play(&mut self, board: usize, m: Move) -> bool {
+        if !self.boards[board].play_timed(m) {
+            return false;
+        }
+        if let Some(c) = m.capture {
+            self.boards[1 - board].update_pocket(c.color, Pockets::kind_of(&c), true);
+        }
+        self.order.push(board);
+        true
+    }
+
+    /// Takes back the last move played on either board
+    pub fn rollback(&mut self) -> Option<(usize, Move)> {
+        let board = self.order.pop()?;
+        let m = self.boards[board].rollback_move()?;
+        if let Some(c) = m.capture {
+            self.boards[1 - board].update_pocket(c.color, Pockets::kind_of(&c), false);
+        }
+        Some((board, m))
+    }
+
+    /// Returns the board that ended the match with the team result and the reason.
+    /// `WhiteWins` is a win for white on board A and black on board B, as in BPGN
+    pub fn outcome(&self) -> Option<(usize, GameResult, Termination)> {
+        self.boards.iter().enumerate().find_map(|(board, game)| {
+            let (result, termination) = game.outcome()?;
+            let result = match (board, result) {
+                (1, GameResult::WhiteWins) => GameResult::BlackWins,
+                (1, GameResult::BlackWins) => GameResult::WhiteWins,
+                (_, result) => result,
+            };
+            Some((board, result, termination))
+        })
+    }
+}
+
+/// A move of a BPGN game, with its board and the comment following it
+#[derive(Debug, Clone, PartialEq)]
+pub struct BpgnMove {
+    pub board: usize,
+    pub san: String,
+    pub comment: Option<String>,
+}
+
+/// A Bughouse match as read from or written to a BPGN file. Moves are numbered
+/// per board and marked with the board and side, eg. "1A. e4 1B. d4 1a. e5"
+#[derive(Debug, Clone)]
+pub struct BpgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<BpgnMove>,
+    pub result: String,
+}
+
+const BPGN_TAG_ROSTER: [&str; 8] = ["Event", "Site", "Date", "WhiteA", "BlackA", "WhiteB", "BlackB", "Result"];
+
+impl Default for BpgnGame {
+    fn default() -> Self {
+        BpgnGame::new()
+    }
+}
+
+impl BpgnGame {
+    /// Creates a game with the player tags of both boards filled with "?"
+    pub fn new() -> BpgnGame {
+        let mut r = BpgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        };
+        for tag in BPGN_TAG_ROSTER.iter() {
+            r.set_tag(tag, if *tag == "Result" { "*" } else { "?" });
+        }
+        r
+    }
+
+    /// Records the moves of both boards in SAN, with the seconds the mover had left
+    /// as comment when the boards have clocks
+    pub fn from_match(bughouse: &BughouseMatch) -> Result<BpgnGame, Error> {
+        let mut r = BpgnGame::new();
+        if let Some(clock) = bughouse.board(0).clock() {
+            r.set_tag("TimeControl", &clock.control().pgn());
+        }
+
+        let mut replay = BughouseMatch::new();
+        let mut played = [0; 2];
+        for (board, m) in bughouse.moves() {
+            let comment = bughouse
+                .board(board)
+                .clock_time(played[board])
+                .map(|t| format!("{:.1}", t.as_secs_f64()));
+            let san = replay.board(board).san(&m);
+            if !replay.play(board, m) {
+                return Err(Error::MoveAfterFlagFall { san });
+            }
+            r.moves.push(BpgnMove { board, san, comment });
+            played[board] += 1;
+        }
+
+        if let Some((_, result, _)) = bughouse.outcome() {
+            r.set_result(result.pgn());
+        }
+        Ok(r)
+    }
+
+    /// Replays the moves on both boards, failing on a move played after the flag fell
+    pub fn to_match(&self) -> Result<BughouseMatch, Error> {
+        let mut r = BughouseMatch::new();
+        for m in self.moves.iter() {
+            let board = m.board.min(1);
+            let played = r.board(board).san_move(&m.san)?;
+            if !r.play(board, played) {
+                return Err(Error::MoveAfterFlagFall { san: m.san.clone() });
+            }
+        }
+        Ok(r)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        self.set_tag("Result", result);
+    }
+
+    /// Parses all games of a BPGN file. Moves without a board marker are skipped
+    pub fn parse_all(text: &str) -> Vec<BpgnGame> {
+        PgnGame::parse_all(text)
+            .into_iter()
+            .map(|pgn| {
+                let mut moves = Vec::new();
+                let mut board = None;
+                for m in pgn.moves {
+                    // the move numbers are gone, "1A." became "A."
+                    let mut san = m.san.as_str();
+                    let mut chars = san.chars();
+                    if let (Some(c @ ('A' | 'a' | 'B' | 'b')), Some('.')) = (chars.next(), chars.next()) {
+                        board = Some(matches!(c, 'B' | 'b') as usize);
+                        san = chars.as_str();
+                        if san.is_empty() {
+                            continue;
+                        }
+                    }
+                    if let Some(board) = board.take() {
+                        moves.push(BpgnMove {
+                            board,
+                            san: san.to_string(),
+                            comment: m.comment,
+                        });
+                    }
+                }
+                BpgnGame {
+                    tags: pgn.tags,
+                    moves,
+                    result: pgn.result,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the game in export format, movetext lines are wrapped at 80 characters
+    pub fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_tags(writer, &self.tags)?;
+
+        // both boards start from the starting position with white to move
+        let mut played = [0; 2];
+        let mut tokens = Vec::new();
+        for m in self.moves.iter() {
+            let board = m.board.min(1);
+            let marker = match (board, played[board] % 2) {
+                (0, 0) => 'A',
+                (0, _) => 'a',
+                (_, 0) => 'B',
+                _ => 'b',
+            };
+            tokens.push(format!("{}{}.", played[board] / 2 + 1, marker));
+            tokens.push(m.san.clone());
+            if let Some(comment) = &m.comment {
+                tokens.push(format!("{{{}}}", comment));
+            }
+            played[board] += 1;
+        }
+        tokens.push(self.result.clone());
+        write_movetext(writer, &tokens)
+    }
+}
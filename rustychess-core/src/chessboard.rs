@@ -1,3 +1,5 @@
+use crate::error::Error as ChessError;
+use crate::fairy::{fairy_piece, StepMode};
 use crate::file::File;
 use crate::game::Move;
 use crate::pgn::Position;
@@ -6,11 +8,14 @@ use crate::pieces::Color;
 use crate::pieces::Kind;
 use crate::pieces::Piece;
 use crate::pieces::PieceMovements;
+use crate::file::{MAX_FILES, NUM_FILES};
 use crate::rank::Rank;
-use crate::rank::ALL_RANKS;
+use crate::rank::{ALL_RANKS, MAX_RANKS, NUM_RANKS};
 use colored::*;
 use hashbrown::HashMap;
 use std::error::Error;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /*trait GetSimple {
     fn get(&self, _: &str, _: i8) -> Option<&Box<Piece>>;
@@ -32,6 +37,11 @@ impl GetEnums for Chessboard2 {
 pub struct Chessboard2 {
     board: HashMap<Position, Box<Piece>>,
     status: BoardStatus,
+    /// Captured pieces in hand, only in variants with drops
+    pockets: Option<Pockets>,
+    /// Number of columns and rows, 8 by 8 unless a variant needs another board
+    width: usize,
+    height: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -43,12 +53,461 @@ pub enum BoardStatus {
     EnPassant,
 }
 
+/// Which sides each color may still castle to, as in the third field of a FEN string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+    /// Columns the castling rooks start on, `H` and `A` except in Chess960.
+    /// Both colors share them like in every Chess960 starting position
+    pub king_rook: Rank,
+    pub queen_rook: Rank,
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+            king_rook: Rank::H,
+            queen_rook: Rank::A,
+        }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+            king_rook: Rank::H,
+            queen_rook: Rank::A,
+        }
+    }
+
+    /// All castling rights of a board set up by `Chessboard2::reset_chess960`
+    pub fn chess960(index: u16) -> CastlingRights {
+        let rooks: Vec<Rank> = ALL_RANKS
+            .iter()
+            .zip(chess960_back_rank(index).iter())
+            .filter(|(_, kind)| **kind == Kind::Rook)
+            .map(|(rank, _)| *rank)
+            .collect();
+        CastlingRights {
+            king_rook: rooks[1],
+            queen_rook: rooks[0],
+            ..CastlingRights::all()
+        }
+    }
+
+    /// Returns whenever either color may still castle
+    pub fn any(&self) -> bool {
+        self.white_king_side || self.white_queen_side || self.black_king_side || self.black_queen_side
+    }
+
+    pub fn king_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_king_side,
+            Color::Black => self.black_king_side,
+            Color::Unknown => false,
+        }
+    }
+
+    pub fn queen_side(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queen_side,
+            Color::Black => self.black_queen_side,
+            Color::Unknown => false,
+        }
+    }
+
+    /// Removes both castling rights of `color`
+    pub fn clear(&mut self, color: Color) {
+        match color {
+            Color::White => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            Color::Black => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+            Color::Unknown => {}
+        }
+    }
+
+    /// Removes the castling right that depends on a rook standing on `pos`
+    pub fn clear_rook(&mut self, pos: &Position) {
+        self.clear_rook_at(pos, File::Eighth);
+    }
+
+    /// Like `clear_rook`, on a board whose last row is `last`
+    pub fn clear_rook_at(&mut self, pos: &Position, last: File) {
+        let rank = pos.rank;
+        if pos.file == File::First {
+            if rank == self.queen_rook {
+                self.white_queen_side = false;
+            } else if rank == self.king_rook {
+                self.white_king_side = false;
+            }
+        } else if pos.file == last {
+            if rank == self.queen_rook {
+                self.black_queen_side = false;
+            } else if rank == self.king_rook {
+                self.black_king_side = false;
+            }
+        }
+    }
+
+    /// Returns whenever the rooks start on other columns than in standard chess
+    pub fn is_chess960(&self) -> bool {
+        self.is_chess960_on(NUM_RANKS)
+    }
+
+    /// Like `is_chess960`, on a board `width` columns wide
+    fn is_chess960_on(&self, width: usize) -> bool {
+        self.king_rook.to_index() != width - 1 || self.queen_rook != Rank::A
+    }
+
+    /// Returns the castling field, written as in Shredder-FEN with the rook
+    /// columns ("HAha") when the rooks did not start on `H` and `A`
+    pub fn fen(&self) -> String {
+        self.fen_on(NUM_RANKS)
+    }
+
+    /// Like `fen`, on a board `width` columns wide: "KQ" stands for the rooks in the corners
+    pub fn fen_on(&self, width: usize) -> String {
+        let letters = if self.is_chess960_on(width) {
+            [self.king_rook.to_str().to_uppercase(), self.queen_rook.to_str().to_uppercase()]
+        } else {
+            ["K".to_string(), "Q".to_string()]
+        };
+
+        let mut r = String::new();
+        if self.white_king_side {
+            r.push_str(&letters[0]);
+        }
+        if self.white_queen_side {
+            r.push_str(&letters[1]);
+        }
+        if self.black_king_side {
+            r.push_str(&letters[0].to_lowercase());
+        }
+        if self.black_queen_side {
+            r.push_str(&letters[1].to_lowercase());
+        }
+        if r.is_empty() {
+            r.push('-');
+        }
+        r
+    }
+
+    /// Parses the castling field of a FEN string, Shredder-FEN and X-FEN included:
+    /// a rook column letter names the rook, "K" and "Q" stand for the outermost rook
+    pub fn from_fen(s: &str, board: &Chessboard2) -> Result<CastlingRights, ChessError> {
+        let mut r = CastlingRights::none();
+        if s == "-" {
+            return Ok(r);
+        }
+
+        for c in s.chars() {
+            let (color, home) = if c.is_ascii_uppercase() {
+                (Color::White, File::First)
+            } else {
+                (Color::Black, board.last_file())
+            };
+            let king = match board.find_piece(Kind::King, color) {
+                Ok((pos, _)) if pos.file == home => pos.rank,
+                _ => Rank::E,
+            };
+            let rook = |rank: &Rank| board.is_piece(&Position::new_(*rank, home), Kind::Rook, color);
+
+            let (king_side, rank) = match c.to_ascii_uppercase() {
+                'K' => (true, board.ranks().rev().find(|r| *r > king && rook(r))),
+                'Q' => (false, board.ranks().find(|r| *r < king && rook(r))),
+                letter if letter.is_ascii_uppercase() && ((letter as u8 - b'A') as usize) < board.width => {
+                    let rank = Rank::from_index((letter as u8 - b'A') as usize);
+                    (rank > king, Some(rank))
+                }
+                _ => return Err(ChessError::InvalidFen { fen: s.to_string() }),
+            };
+
+            match (color, king_side) {
+                (Color::White, true) => r.white_king_side = true,
+                (Color::White, false) => r.white_queen_side = true,
+                (_, true) => r.black_king_side = true,
+                (_, false) => r.black_queen_side = true,
+            }
+            // without a rook the right stays on the standard column and is never used
+            match (king_side, rank) {
+                (true, Some(rank)) => r.king_rook = rank,
+                (false, Some(rank)) => r.queen_rook = rank,
+                _ => {}
+            }
+        }
+        Ok(r)
+    }
+}
+
+/// Pieces kinds that can be held in a pocket, in the order they are written in FEN
+pub const POCKET_KINDS: [Kind; 5] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight, Kind::Pawn];
+
+/// Captured pieces each color may drop back onto the board in Crazyhouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pockets {
+    counts: [[u8; 5]; 2],
+}
+
+impl Pockets {
+    fn index(color: Color, kind: Kind) -> (usize, usize) {
+        let kind = POCKET_KINDS.iter().position(|k| *k == kind).unwrap_or(4);
+        ((color == Color::Black) as usize, kind)
+    }
+
+    pub fn count(&self, color: Color, kind: Kind) -> u8 {
+        let (c, k) = Pockets::index(color, kind);
+        self.counts[c][k]
+    }
+
+    pub fn add(&mut self, color: Color, kind: Kind) {
+        let (c, k) = Pockets::index(color, kind);
+        self.counts[c][k] = self.counts[c][k].saturating_add(1);
+    }
+
+    pub fn remove(&mut self, color: Color, kind: Kind) {
+        let (c, k) = Pockets::index(color, kind);
+        self.counts[c][k] = self.counts[c][k].saturating_sub(1);
+    }
+
+    /// Returns what a captured piece turns into in the pocket, promoted pieces go back as pawns
+    pub fn kind_of(piece: &Piece) -> Kind {
+        if piece.promoted {
+            Kind::Pawn
+        } else {
+            piece.kind
+        }
+    }
+
+    /// Returns the pieces `color` holds, the most valuable first
+    pub fn pieces(&self, color: Color) -> Vec<Kind> {
+        POCKET_KINDS
+            .iter()
+            .flat_map(|kind| std::iter::repeat_n(*kind, self.count(color, *kind) as usize))
+            .collect()
+    }
+
+    /// Returns the pocket extension of a FEN string without the brackets, eg. "QNpp"
+    pub fn fen(&self) -> String {
+        let mut r = String::new();
+        for color in [Color::White, Color::Black] {
+            for kind in self.pieces(color) {
+                r.push_str(&Piece::new(kind, color).fen());
+            }
+        }
+        r
+    }
+
+    /// Parses the pocket letters of a FEN string, eg. "QNpp"
+    pub fn from_fen(s: &str) -> Result<Pockets, ChessError> {
+        let mut r = Pockets::default();
+        for c in s.chars() {
+            let piece = Piece::from_fen(c).map_err(|_| ChessError::InvalidFen { fen: s.to_string() })?;
+            if piece.kind == Kind::King {
+                return Err(ChessError::InvalidFen { fen: s.to_string() });
+            }
+            r.add(piece.color, piece.kind);
+        }
+        Ok(r)
+    }
+}
+
+/// Pieces blown up around the target square of an Atomic capture, besides the
+/// captured and the capturing piece. Kept in the move so it can be taken back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Explosion {
+    /// The pieces on the surrounding squares, in the order of the king's steps
+    removed: [Option<Piece>; 8],
+}
+
+impl Explosion {
+    /// Returns the pieces removed around `center` with their squares
+    pub fn pieces(&self, center: Position) -> impl Iterator<Item = (Position, Piece)> + '_ {
+        KING_OFFSETS
+            .iter()
+            .zip(self.removed.iter())
+            .filter_map(move |(&(r, f), piece)| Some((center.offset(r, f)?, (*piece)?)))
+    }
+}
+
+/// Number of squares a row of a FEN string covers, with multi-digit empty counts
+fn row_width(row: &str) -> usize {
+    let mut r = 0;
+    let mut empty = 0;
+    for c in row.chars() {
+        match c.to_digit(10) {
+            Some(n) => empty = empty * 10 + n as usize,
+            None => {
+                r += empty;
+                empty = 0;
+                if c != '~' {
+                    r += 1;
+                }
+            }
+        }
+    }
+    r + empty
+}
+
+/// Index of the standard starting position among the Chess960 positions
+pub const STANDARD_CHESS960_INDEX: u16 = 518;
+
+/// Returns the pieces of the back rank, from `A` to `H`, of a Chess960 starting
+/// position numbered 0 to 959 as by Scharnagl
+pub fn chess960_back_rank(index: u16) -> [Kind; 8] {
+    let mut r = [None; 8];
+    let mut n = index as usize % 960;
+
+    r[2 * (n % 4) + 1] = Some(Kind::Bishop);
+    n /= 4;
+    r[2 * (n % 4)] = Some(Kind::Bishop);
+    n /= 4;
+
+    // the remaining pieces go to the free squares from left to right
+    let mut place = |nth: usize, kind: Kind| {
+        let free = r.iter().enumerate().filter(|(_, k)| k.is_none()).nth(nth).unwrap().0;
+        r[free] = Some(kind);
+    };
+    place(n % 6, Kind::Queen);
+    n /= 6;
+
+    const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+    let (first, second) = KNIGHTS[n];
+    place(second, Kind::Knight);
+    place(first, Kind::Knight);
+    place(0, Kind::Rook);
+    place(0, Kind::King);
+    place(0, Kind::Rook);
+
+    let mut kinds = [Kind::Rook; 8];
+    for (kind, placed) in kinds.iter_mut().zip(r.iter()) {
+        *kind = placed.unwrap();
+    }
+    kinds
+}
+
+/// Picks one of the 960 starting positions, seeded from the system time
+pub fn random_chess960_index() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default();
+    (nanos.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as u16 % 960
+}
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+const PROMOTION_KINDS: [Kind; 4] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight];
+
 impl Chessboard2 {
     pub fn new() -> Chessboard2 {
         Chessboard2 {
             board: HashMap::new(),
             status: BoardStatus::None,
+            pockets: None,
+            width: NUM_RANKS,
+            height: NUM_FILES,
+        }
+    }
+
+    /// Creates an empty board with `width` columns and `height` rows, at most 16 each
+    pub fn with_size(width: usize, height: usize) -> Result<Chessboard2, ChessError> {
+        if !(1..=MAX_RANKS).contains(&width) || !(1..=MAX_FILES).contains(&height) {
+            return Err(ChessError::InvalidBoardSize { width, height });
         }
+        Ok(Chessboard2 {
+            width,
+            height,
+            ..Chessboard2::new()
+        })
+    }
+
+    /// Number of columns, `A` and up
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows, 1 and up
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns whenever this is the 8x8 board of standard chess
+    pub fn is_standard_size(&self) -> bool {
+        self.width == NUM_RANKS && self.height == NUM_FILES
+    }
+
+    /// The row black starts on and white promotes on
+    pub fn last_file(&self) -> File {
+        File::from_index(self.height - 1)
+    }
+
+    /// Iterates over the columns of the board from `A`
+    pub fn ranks(&self) -> impl DoubleEndedIterator<Item = Rank> {
+        (0..self.width).map(Rank::from_index)
+    }
+
+    /// Iterates over the rows of the board from the first
+    pub fn files(&self) -> impl DoubleEndedIterator<Item = File> {
+        (0..self.height).map(File::from_index)
+    }
+
+    /// Returns whenever `pos` lies on the board
+    pub fn contains(&self, pos: &Position) -> bool {
+        pos.rank.to_index() < self.width && pos.file.to_index() < self.height
+    }
+
+    /// Returns `pos` moved `ranks` to the right and `files` up, or `None` if that leaves the board
+    #[inline]
+    pub fn offset(&self, pos: Position, ranks: i8, files: i8) -> Option<Position> {
+        pos.offset(ranks, files).filter(|p| self.contains(p))
+    }
+
+    /// Returns the pieces in hand, `None` unless pieces can be dropped
+    pub fn pockets(&self) -> Option<&Pockets> {
+        self.pockets.as_ref()
+    }
+
+    /// Enables drops with the given pockets, or disables them with `None`
+    pub fn set_pockets(&mut self, pockets: Option<Pockets>) {
+        self.pockets = pockets;
     }
 
     pub fn set_(&mut self, pos: Position, v: Box<Piece>) {
@@ -116,7 +575,7 @@ impl Chessboard2 {
         let mut rank = Rank::A;
         let mut file = File::First;
         for (i, piece) in board.iter().enumerate() {
-            if i % 8 == 0 && i > 0 {
+            if i % self.width == 0 && i > 0 {
                 file = file.up();
                 rank = Rank::A;
             }
@@ -132,16 +591,19 @@ impl Chessboard2 {
         }
     }
 
+    /// Sets up the standard starting position
     pub fn reset(&mut self) {
+        self.reset_chess960(STANDARD_CHESS960_INDEX);
+    }
+
+    /// Sets up the Chess960 starting position with the given number, see
+    /// `chess960_back_rank`. Pass `random_chess960_index()` for a random one
+    pub fn reset_chess960(&mut self, index: u16) {
+        let back_rank = chess960_back_rank(index);
         let mut v = Vec::new();
-        v.push(Some(Piece::new(Kind::Rook, Color::White)));
-        v.push(Some(Piece::new(Kind::Knight, Color::White)));
-        v.push(Some(Piece::new(Kind::Bishop, Color::White)));
-        v.push(Some(Piece::new(Kind::Queen, Color::White)));
-        v.push(Some(Piece::new(Kind::King, Color::White)));
-        v.push(Some(Piece::new(Kind::Bishop, Color::White)));
-        v.push(Some(Piece::new(Kind::Knight, Color::White)));
-        v.push(Some(Piece::new(Kind::Rook, Color::White)));
+        for kind in back_rank.iter() {
+            v.push(Some(Piece::new(*kind, Color::White)));
+        }
         for _ in 0..8 {
             v.push(Some(Piece::new(Kind::Pawn, Color::White)));
         }
@@ -153,15 +615,14 @@ impl Chessboard2 {
         for _ in 0..8 {
             v.push(Some(Piece::new(Kind::Pawn, Color::Black)));
         }
-        v.push(Some(Piece::new(Kind::Rook, Color::Black)));
-        v.push(Some(Piece::new(Kind::Knight, Color::Black)));
-        v.push(Some(Piece::new(Kind::Bishop, Color::Black)));
-        v.push(Some(Piece::new(Kind::Queen, Color::Black)));
-        v.push(Some(Piece::new(Kind::King, Color::Black)));
-        v.push(Some(Piece::new(Kind::Bishop, Color::Black)));
-        v.push(Some(Piece::new(Kind::Knight, Color::Black)));
-        v.push(Some(Piece::new(Kind::Rook, Color::Black)));
+        for kind in back_rank.iter() {
+            v.push(Some(Piece::new(*kind, Color::Black)));
+        }
 
+        self.board.clear();
+        self.pockets = None;
+        self.width = NUM_RANKS;
+        self.height = NUM_FILES;
         self.set_position(v);
     }
 
@@ -273,13 +734,7 @@ impl Chessboard2 {
                     return Err("Invalid move. Own piece blocking".into());
                 }
 
-                let mut m = Move {
-                    from: *from_pos,
-                    to: *to_pos,
-                    piece: *piece,
-                    capture: None,
-                    is_from_orignal_pos: false,
-                };
+                let mut m = Move::new(*from_pos, *to_pos, *piece);
 
                 let is_enpassant = !(previous_move.is_none())
                     && self.is_enpassant(previous_move.unwrap(), *piece, to_pos);
@@ -459,7 +914,7 @@ impl Chessboard2 {
 
     pub fn can_promote(&self, color: Color, pos: &Position) -> bool {
         if color == Color::White {
-            return pos.file == File::Eighth;
+            return pos.file == self.last_file();
         }
 
         pos.file == File::First
@@ -479,25 +934,629 @@ impl Chessboard2 {
             }
         }
 
-        self.is_field_threatened(color, &king_pos)
+        self.is_square_attacked(&king_pos, color.switch())
+    }
+
+    /// Returns an iterator over all occupied squares
+    pub fn pieces(&self) -> impl Iterator<Item = (&Position, &Box<Piece>)> {
+        self.board.iter()
+    }
+
+    /// Returns whenever any piece of color `by` attacks the square `pos`
+    pub fn is_square_attacked(&self, pos: &Position, by: Color) -> bool {
+        let pawn_file = if by == Color::White { -1 } else { 1 };
+        for rank in [-1, 1] {
+            if let Some(p) = self.offset(*pos, rank, pawn_file) {
+                if self.is_piece(&p, Kind::Pawn, by) {
+                    return true;
+                }
+            }
+        }
+
+        let knight = KNIGHT_OFFSETS.iter().any(|&(r, f)| match self.offset(*pos, r, f) {
+            Some(p) => self.is_piece(&p, Kind::Knight, by),
+            None => false,
+        });
+        let king = KING_OFFSETS.iter().any(|&(r, f)| match self.offset(*pos, r, f) {
+            Some(p) => self.is_piece(&p, Kind::King, by),
+            None => false,
+        });
+        if knight || king {
+            return true;
+        }
+
+        let slider = |directions: &[(i8, i8)], kind: Kind| {
+            directions.iter().any(|&(r, f)| match self.first_piece(pos, r, f) {
+                Some((_, piece)) => piece.color == by && (piece.kind == kind || piece.kind == Kind::Queen),
+                None => false,
+            })
+        };
+
+        slider(&BISHOP_DIRECTIONS, Kind::Bishop)
+            || slider(&ROOK_DIRECTIONS, Kind::Rook)
+            || self
+                .board
+                .iter()
+                .any(|(from, p)| p.color == by && matches!(p.kind, Kind::Fairy(_)) && self.fairy_attacks(*from, p, pos))
+    }
+
+    /// Returns whenever the fairy piece `piece` on `from` could capture on `target`
+    fn fairy_attacks(&self, from: Position, piece: &Piece, target: &Position) -> bool {
+        let fairy = match fairy_piece(piece.kind) {
+            Some(v) => v,
+            None => return false,
+        };
+        let attacks = fairy.steps(piece.color).filter(|s| s.mode != StepMode::Move).any(|step| {
+            let mut current = self.offset(from, step.ranks, step.files);
+            let mut n = 0;
+            while let Some(to) = current {
+                let occupied = self.board.get(&to).is_some();
+                if step.mode == StepMode::Hop {
+                    // a hopper lands on the square right behind the first piece
+                    if occupied {
+                        return self.offset(to, step.ranks, step.files) == Some(*target);
+                    }
+                } else if to == *target {
+                    return true;
+                } else if occupied {
+                    return false;
+                }
+
+                n += 1;
+                if step.range != 0 && n >= step.range {
+                    return false;
+                }
+                current = self.offset(to, step.ranks, step.files);
+            }
+            false
+        });
+        attacks
+    }
+
+    fn is_piece(&self, pos: &Position, kind: Kind, color: Color) -> bool {
+        match self.board.get(pos) {
+            Some(p) => p.kind == kind && p.color == color,
+            None => false,
+        }
+    }
+
+    /// Walks from `pos` in the given direction and returns the first piece found
+    fn first_piece(&self, pos: &Position, ranks: i8, files: i8) -> Option<(Position, &Piece)> {
+        let mut current = self.offset(*pos, ranks, files);
+        while let Some(p) = current {
+            if let Some(piece) = self.board.get(&p) {
+                return Some((p, piece.as_ref()));
+            }
+            current = self.offset(p, ranks, files);
+        }
+        None
+    }
+
+    /// Generates all moves for `color` without checking if they leave the own king in check
+    pub fn pseudo_legal_moves(
+        &self,
+        color: Color,
+        castling: CastlingRights,
+        en_passant: Option<Position>,
+    ) -> Vec<Move> {
+        let mut r = Vec::with_capacity(48);
+        for (from, piece) in self.board.iter().filter(|(_, p)| p.color == color) {
+            match piece.kind {
+                Kind::Pawn => self.pawn_moves(*from, piece, en_passant, &mut r),
+                Kind::Knight => self.step_moves(*from, piece, &KNIGHT_OFFSETS, &mut r),
+                Kind::Bishop => self.slide_moves(*from, piece, &BISHOP_DIRECTIONS, &mut r),
+                Kind::Rook => self.slide_moves(*from, piece, &ROOK_DIRECTIONS, &mut r),
+                Kind::Queen => {
+                    self.slide_moves(*from, piece, &BISHOP_DIRECTIONS, &mut r);
+                    self.slide_moves(*from, piece, &ROOK_DIRECTIONS, &mut r);
+                }
+                Kind::King => {
+                    self.step_moves(*from, piece, &KING_OFFSETS, &mut r);
+                    self.castling_moves(*from, piece, castling, &mut r);
+                }
+                Kind::Fairy(_) => self.fairy_moves(*from, piece, &mut r),
+            }
+        }
+        self.drop_moves(color, &mut r);
+        r
+    }
+
+    /// Generates all legal moves for `color`
+    pub fn legal_moves(
+        &self,
+        color: Color,
+        castling: CastlingRights,
+        en_passant: Option<Position>,
+    ) -> Vec<Move> {
+        let mut board = self.clone();
+        self.pseudo_legal_moves(color, castling, en_passant)
+            .into_iter()
+            .filter(|m| {
+                board.make(m);
+                let legal = !board.is_checked_silent(color);
+                board.unmake(m);
+                legal
+            })
+            .collect()
+    }
+
+    fn is_checked_silent(&self, color: Color) -> bool {
+        match self.find_piece(Kind::King, color) {
+            Ok((pos, _)) => self.is_square_attacked(&pos, color.switch()),
+            Err(_) => false,
+        }
+    }
+
+    fn pawn_moves(
+        &self,
+        from: Position,
+        piece: &Piece,
+        en_passant: Option<Position>,
+        r: &mut Vec<Move>,
+    ) {
+        let (direction, start, last) = match piece.color {
+            Color::White => (1, File::Second, self.last_file()),
+            _ => (-1, File::from_index(self.height - 2), File::First),
+        };
+
+        let push = |m: Move, r: &mut Vec<Move>| {
+            if m.to.file == last {
+                for kind in PROMOTION_KINDS.iter() {
+                    let mut p = m;
+                    p.promotion = Some(*kind);
+                    r.push(p);
+                }
+            } else {
+                r.push(m);
+            }
+        };
+
+        if let Some(to) = self.offset(from, 0, direction) {
+            if self.board.get(&to).is_none() {
+                push(Move::new(from, to, *piece), r);
+
+                if from.file == start {
+                    if let Some(to) = self.offset(to, 0, direction) {
+                        if self.board.get(&to).is_none() {
+                            r.push(Move::new(from, to, *piece));
+                        }
+                    }
+                }
+            }
+        }
+
+        for rank in [-1, 1] {
+            let to = match self.offset(from, rank, direction) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            match self.board.get(&to) {
+                Some(p) if p.color != piece.color => {
+                    let mut m = Move::new(from, to, *piece);
+                    m.capture = Some(**p);
+                    push(m, r);
+                }
+                None if en_passant == Some(to) => {
+                    let captured = Position::new_(to.rank, from.file);
+                    if let Some(p) = self.board.get(&captured) {
+                        let mut m = Move::new(from, to, *piece);
+                        m.capture = Some(**p);
+                        m.is_en_passant = true;
+                        r.push(m);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn step_moves(&self, from: Position, piece: &Piece, offsets: &[(i8, i8)], r: &mut Vec<Move>) {
+        for &(rank, file) in offsets {
+            let to = match self.offset(from, rank, file) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            match self.board.get(&to) {
+                Some(p) if p.color == piece.color => {}
+                target => {
+                    let mut m = Move::new(from, to, *piece);
+                    m.capture = target.map(|p| **p);
+                    r.push(m);
+                }
+            }
+        }
+    }
+
+    fn slide_moves(&self, from: Position, piece: &Piece, directions: &[(i8, i8)], r: &mut Vec<Move>) {
+        for &(rank, file) in directions {
+            let mut current = self.offset(from, rank, file);
+            while let Some(to) = current {
+                match self.board.get(&to) {
+                    Some(p) => {
+                        if p.color != piece.color {
+                            let mut m = Move::new(from, to, *piece);
+                            m.capture = Some(**p);
+                            r.push(m);
+                        }
+                        break;
+                    }
+                    None => r.push(Move::new(from, to, *piece)),
+                }
+                current = self.offset(to, rank, file);
+            }
+        }
+    }
+
+    /// Returns the pieces a capture on `center` blows up: all but pawns on the surrounding squares
+    pub fn explosion(&self, center: Position) -> Explosion {
+        let mut r = Explosion::default();
+        for (&(rank, file), removed) in KING_OFFSETS.iter().zip(r.removed.iter_mut()) {
+            *removed = self
+                .offset(center, rank, file)
+                .and_then(|pos| self.board.get(&pos))
+                .filter(|p| p.kind != Kind::Pawn)
+                .map(|p| **p);
+        }
+        r
+    }
+
+    /// Moves of a fairy piece along the steps of its Betza notation
+    fn fairy_moves(&self, from: Position, piece: &Piece, r: &mut Vec<Move>) {
+        let fairy = match fairy_piece(piece.kind) {
+            Some(v) => v,
+            None => return,
+        };
+
+        for step in fairy.steps(piece.color) {
+            let mut current = self.offset(from, step.ranks, step.files);
+            let mut n = 0;
+            while let Some(to) = current {
+                let target = self.board.get(&to);
+                if step.mode == StepMode::Hop {
+                    // slide to the first piece and land right behind it
+                    if target.is_some() {
+                        if let Some(landing) = self.offset(to, step.ranks, step.files) {
+                            match self.board.get(&landing) {
+                                Some(p) if p.color == piece.color => {}
+                                target => {
+                                    let mut m = Move::new(from, landing, *piece);
+                                    m.capture = target.map(|p| **p);
+                                    r.push(m);
+                                }
+                            }
+                        }
+                        break;
+                    }
+                } else {
+                    match target {
+                        Some(p) => {
+                            if p.color != piece.color && step.mode != StepMode::Move {
+                                let mut m = Move::new(from, to, *piece);
+                                m.capture = Some(**p);
+                                r.push(m);
+                            }
+                            break;
+                        }
+                        None if step.mode != StepMode::Capture => r.push(Move::new(from, to, *piece)),
+                        None => {}
+                    }
+                }
+
+                n += 1;
+                if step.range != 0 && n >= step.range {
+                    break;
+                }
+                current = self.offset(to, step.ranks, step.files);
+            }
+        }
+    }
+
+    /// Drops of the pieces in `color`'s pocket onto empty squares, pawns not on the first or last row
+    fn drop_moves(&self, color: Color, r: &mut Vec<Move>) {
+        let pockets = match self.pockets.as_ref() {
+            Some(v) => v,
+            None => return,
+        };
+
+        let last = self.last_file();
+        for kind in POCKET_KINDS.iter().filter(|k| pockets.count(color, **k) > 0) {
+            for file in self.files() {
+                if *kind == Kind::Pawn && (file == File::First || file == last) {
+                    continue;
+                }
+                for rank in self.ranks() {
+                    let to = Position::new_(rank, file);
+                    if self.board.get(&to).is_none() {
+                        r.push(Move::drop(*Piece::new(*kind, color), to));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Castling moves of a king on its home row. The king ends on the second column from
+    /// the edge (`G` or `C` on the standard board) and the rook next to it, the squares both
+    /// cross must be empty and those the king crosses safe
+    fn castling_moves(&self, from: Position, piece: &Piece, castling: CastlingRights, r: &mut Vec<Move>) {
+        let home = if piece.color == Color::White {
+            File::First
+        } else {
+            self.last_file()
+        };
+        if from.file != home {
+            return;
+        }
+
+        let opponent = piece.color.switch();
+        let king_to = Rank::from_index(self.width - 2);
+        let sides = [
+            (castling.king_side(piece.color), castling.king_rook, king_to, king_to.left()),
+            (castling.queen_side(piece.color), castling.queen_rook, Rank::C, Rank::D),
+        ];
+
+        for (allowed, rook, to, rook_to) in sides.iter() {
+            let rook_from = Position::new_(*rook, home);
+            if !allowed
+                || (*rook > from.rank) != (*to == king_to)
+                || !self.is_piece(&rook_from, Kind::Rook, piece.color)
+            {
+                continue;
+            }
+
+            let span = |a: Rank, b: Rank| {
+                let (low, high) = if a < b { (a, b) } else { (b, a) };
+                (low.to_index()..=high.to_index()).map(Rank::from_index)
+            };
+            let is_blocking = span(from.rank, *to)
+                .chain(span(*rook, *rook_to))
+                .any(|rank| rank != from.rank && rank != *rook && self.board.get(&Position::new_(rank, home)).is_some());
+            if is_blocking {
+                continue;
+            }
+
+            let any_field_threatened =
+                span(from.rank, *to).any(|rank| self.is_square_attacked(&Position::new_(rank, home), opponent));
+            if !any_field_threatened {
+                let mut m = Move::new(from, Position::new_(*to, home), *piece);
+                m.is_castling = true;
+                m.rook = Some(rook_from);
+                r.push(m);
+            }
+        }
+    }
+
+    /// Returns the rook's starting and target square of a castling move
+    pub fn castling_rook(m: &Move) -> (Position, Position) {
+        let file = m.to.file;
+        if m.castles_king_side() {
+            (m.rook.unwrap_or(Position::new_(Rank::H, file)), Position::new_(m.to.rank.left(), file))
+        } else {
+            (m.rook.unwrap_or(Position::new_(Rank::A, file)), Position::new_(m.to.rank.right(), file))
+        }
+    }
+
+    /// Applies a move generated by `pseudo_legal_moves` to the board
+    pub fn make(&mut self, m: &Move) {
+        if m.is_drop {
+            if let Some(pockets) = self.pockets.as_mut() {
+                pockets.remove(m.piece.color, m.piece.kind);
+            }
+            let mut piece = m.piece;
+            piece.number_of_moves = piece.number_of_moves.saturating_add(1);
+            self.board.insert(m.to, Box::new(piece));
+            return;
+        }
+
+        self.board.remove(&m.from);
+        if m.is_en_passant {
+            self.board.remove(&Position::new_(m.to.rank, m.from.file));
+        }
+
+        // the capturing piece goes up with the captured one
+        if let Some(explosion) = m.explosion {
+            self.board.remove(&m.to);
+            for (pos, _) in explosion.pieces(m.to) {
+                self.board.remove(&pos);
+            }
+            return;
+        }
+
+        if m.is_castling {
+            let (rook_from, rook_to) = Chessboard2::castling_rook(m);
+            if let Some(mut rook) = self.board.remove(&rook_from) {
+                rook.number_of_moves = rook.number_of_moves.saturating_add(1);
+                self.board.insert(rook_to, rook);
+            }
+        }
+
+        if let (Some(pockets), Some(c)) = (self.pockets.as_mut(), m.capture) {
+            pockets.add(m.piece.color, Pockets::kind_of(&c));
+        }
+
+        let mut piece = m.piece;
+        piece.number_of_moves = piece.number_of_moves.saturating_add(1);
+        if let Some(kind) = m.promotion {
+            piece.kind = kind;
+            piece.promoted = true;
+        }
+        self.board.insert(m.to, Box::new(piece));
+    }
+
+    /// Reverts a move previously applied with `make`
+    pub fn unmake(&mut self, m: &Move) {
+        if m.is_drop {
+            self.board.remove(&m.to);
+            if let Some(pockets) = self.pockets.as_mut() {
+                pockets.add(m.piece.color, m.piece.kind);
+            }
+            return;
+        }
+
+        if m.is_castling {
+            // in Chess960 the king and the rook may land on each other's starting square
+            let (rook_from, rook_to) = Chessboard2::castling_rook(m);
+            let rook = self.board.remove(&rook_to);
+            self.board.remove(&m.to);
+            if let Some(mut rook) = rook {
+                rook.number_of_moves = rook.number_of_moves.saturating_sub(1);
+                self.board.insert(rook_from, rook);
+            }
+            self.board.insert(m.from, Box::new(m.piece));
+            return;
+        }
+
+        self.board.remove(&m.to);
+        self.board.insert(m.from, Box::new(m.piece));
+        if let Some(explosion) = m.explosion {
+            for (pos, piece) in explosion.pieces(m.to) {
+                self.board.insert(pos, Box::new(piece));
+            }
+        }
+
+        if let Some(c) = m.capture {
+            if let Some(pockets) = self.pockets.as_mut() {
+                pockets.remove(m.piece.color, Pockets::kind_of(&c));
+            }
+            let pos = if m.is_en_passant {
+                Position::new_(m.to.rank, m.from.file)
+            } else {
+                m.to
+            };
+            self.board.insert(pos, Box::new(c));
+        }
+    }
+
+    /// Returns the piece placement field of a FEN string
+    pub fn fen(&self) -> String {
+        let mut r = String::new();
+        for file in self.files().rev() {
+            let mut empty = 0;
+            for rank in self.ranks() {
+                match self.board.get(&Position::new_(rank, file)) {
+                    Some(p) => {
+                        if empty > 0 {
+                            r.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        r.push_str(&p.fen());
+                        if p.promoted && self.pockets.is_some() {
+                            r.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                r.push_str(&empty.to_string());
+            }
+            if file != File::First {
+                r.push('/');
+            }
+        }
+        if let Some(pockets) = self.pockets.as_ref() {
+            r.push_str(&format!("[{}]", pockets.fen()));
+        }
+        r
+    }
+
+    /// Creates a board from the piece placement field of a FEN string. The board is as
+    /// wide as the rows and as high as their number, empty squares may be counted with
+    /// two digits, eg. "rnbqkbnr/pppppppp/10/...". Crazyhouse pockets follow in brackets,
+    /// "RNBQKBNR[Qp]", or on an 8x8 board as a ninth row, "RNBQKBNR/Qp"
+    pub fn from_fen(fen: &str) -> Result<Chessboard2, ChessError> {
+        let invalid = || ChessError::InvalidFen { fen: fen.to_string() };
+        let (placement, pockets) = match fen.find('[') {
+            Some(at) if fen.ends_with(']') => (&fen[..at], Some(&fen[at + 1..fen.len() - 1])),
+            Some(_) => return Err(invalid()),
+            None => (fen, None),
+        };
+        let mut rows: Vec<&str> = placement.split('/').collect();
+        let width = row_width(rows[0]);
+        let pockets = match pockets {
+            Some(v) => Some(v),
+            None if rows.len() == 9 && width == NUM_RANKS && row_width(rows[8]) != width => rows.pop(),
+            None => None,
+        };
+        let mut board = Chessboard2::with_size(width, rows.len()).map_err(|_| invalid())?;
+        if let Some(v) = pockets {
+            board.pockets = Some(Pockets::from_fen(v).map_err(|_| invalid())?);
+        }
+
+        let files: Vec<File> = board.files().rev().collect();
+        for (row, file) in rows.iter().zip(files) {
+            let mut rank = 0;
+            let mut chars = row.chars().peekable();
+            while let Some(c) = chars.next() {
+                if let Some(mut n) = c.to_digit(10) {
+                    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        n = n * 10 + d;
+                        chars.next();
+                    }
+                    rank += n as usize;
+                    continue;
+                }
+                let pos = Position::new_(Rank::from_index(rank.saturating_sub(1)), file);
+                if c == '~' {
+                    // the piece before was promoted
+                    match board.board.get_mut(&pos) {
+                        Some(p) if rank > 0 => p.promoted = true,
+                        _ => return Err(invalid()),
+                    }
+                    continue;
+                }
+                if rank >= width {
+                    return Err(invalid());
+                }
+
+                let piece = Piece::from_fen(c).map_err(|_| invalid())?;
+                board.set_(Position::new_(Rank::from_index(rank), file), Box::new(piece));
+                rank += 1;
+            }
+            if rank != width {
+                return Err(invalid());
+            }
+        }
+
+        Ok(board)
     }
 
     pub fn print(self) {
-        for rank in IntoIterator::into_iter(ALL_RANKS) {
-            print!("  {}", rank);
+        let _ = self.write(&mut std::io::stdout());
+    }
+
+    /// Draws the board with coordinates and the pockets, if any, to `writer`
+    pub fn write(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        let label = self.height.to_string().len();
+        write!(writer, "{}", " ".repeat(label - 1))?;
+        for rank in self.ranks() {
+            write!(writer, "  {}", rank)?;
         }
-        println!();
-        for file in (1..9).rev() {
-            print!("{}", file);
+        writeln!(writer)?;
+        for file in self.files().rev() {
+            write!(writer, "{:>1$}", file.to_i8(), label)?;
 
-            for rank in IntoIterator::into_iter(ALL_RANKS) {
-                let piece = self.get(rank.to_str(), file);
+            for rank in self.ranks() {
+                let piece = self.board.get(&Position::new_(rank, file));
                 match piece {
-                    Some(p) => print!("[{}]", p),
-                    _ => print!("{}", "[ ]".white()),
+                    Some(p) => write!(writer, "[{}]", p)?,
+                    _ => write!(writer, "{}", "[ ]".white())?,
                 }
             }
-            println!();
+            writeln!(writer)?;
+        }
+
+        if let Some(pockets) = self.pockets {
+            for color in [Color::White, Color::Black] {
+                let pieces: Vec<String> = pockets
+                    .pieces(color)
+                    .iter()
+                    .map(|kind| kind.symbol(color).to_string())
+                    .collect();
+                writeln!(writer, "{:?} pocket: {}", color, pieces.join(" "))?;
+            }
         }
+        Ok(())
     }
 }
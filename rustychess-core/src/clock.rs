@@ -0,0 +1,350 @@
+use crate::pieces::Color;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Where a clock reads the time from
+pub trait TimeSource: fmt::Debug + Send + Sync {
+    /// Time elapsed since an arbitrary but fixed point
+    fn now(&self) -> Duration;
+}
+
+/// Reads the monotonic system clock
+#[derive(Debug)]
+pub struct SystemTimeSource {
+    epoch: Instant,
+}
+
+impl Default for SystemTimeSource {
+    fn default() -> Self {
+        SystemTimeSource { epoch: Instant::now() }
+    }
+}
+
+impl TimeSource for SystemTimeSource {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/// A time source that only moves when told to, clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct ManualTimeSource {
+    now: Arc<Mutex<Duration>>,
+}
+
+impl ManualTimeSource {
+    pub fn new() -> ManualTimeSource {
+        ManualTimeSource::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl TimeSource for ManualTimeSource {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Time given back to a player for each move
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bonus {
+    None,
+    /// Added after every move
+    Fischer(Duration),
+    /// Time used is given back after every move, up to this limit
+    Bronstein(Duration),
+    /// The clock only starts counting down once this much time has passed
+    Delay(Duration),
+}
+
+/// A period of a time control
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stage {
+    /// Moves to play in this stage, `None` for the rest of the game
+    pub moves: Option<u32>,
+    pub time: Duration,
+    pub bonus: Bonus,
+}
+
+/// A sequence of stages, the last one is repeated when it has a number of moves
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    pub fn new(stages: Vec<Stage>) -> TimeControl {
+        TimeControl { stages }
+    }
+
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl::with_bonus(time, Bonus::None)
+    }
+
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl::with_bonus(time, Bonus::Fischer(increment))
+    }
+
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::with_bonus(time, Bonus::Bronstein(delay))
+    }
+
+    pub fn simple_delay(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl::with_bonus(time, Bonus::Delay(delay))
+    }
+
+    fn with_bonus(time: Duration, bonus: Bonus) -> TimeControl {
+        TimeControl::new(vec![Stage {
+            moves: None,
+            time,
+            bonus,
+        }])
+    }
+
+    /// Parses stages separated by ':', each written "[moves/]seconds[+increment]", with
+    /// "d" instead of "+" for a simple delay and "b" for a Bronstein delay.
+    /// The FIDE control 40/90+30 (minutes) is "40/5400+30:1800+30"
+    pub fn parse(s: &str) -> Option<TimeControl> {
+        let stages = s.split(':').map(parse_stage).collect::<Option<Vec<Stage>>>()?;
+        if stages.is_empty() {
+            return None;
+        }
+        Some(TimeControl::new(stages))
+    }
+
+    /// Returns the stage played after `index` stages were completed
+    pub fn stage(&self, index: usize) -> &Stage {
+        &self.stages[index.min(self.stages.len() - 1)]
+    }
+
+    /// Returns the value of the PGN TimeControl tag, in the format accepted by `parse`
+    pub fn pgn(&self) -> String {
+        self.stages
+            .iter()
+            .map(|stage| {
+                let mut r = match stage.moves {
+                    Some(moves) => format!("{}/{}", moves, stage.time.as_secs_f64()),
+                    None => format!("{}", stage.time.as_secs_f64()),
+                };
+                match stage.bonus {
+                    Bonus::None => {}
+                    Bonus::Fischer(d) => r.push_str(&format!("+{}", d.as_secs_f64())),
+                    Bonus::Bronstein(d) => r.push_str(&format!("b{}", d.as_secs_f64())),
+                    Bonus::Delay(d) => r.push_str(&format!("d{}", d.as_secs_f64())),
+                }
+                r
+            })
+            .collect::<Vec<String>>()
+            .join(":")
+    }
+}
+
+fn parse_stage(s: &str) -> Option<Stage> {
+    let seconds = |v: &str| v.parse::<f64>().ok().filter(|v| *v >= 0.0).map(Duration::from_secs_f64);
+
+    let (moves, rest) = match s.find('/') {
+        Some(at) => (Some(s[..at].parse::<u32>().ok().filter(|m| *m > 0)?), &s[at + 1..]),
+        None => (None, s),
+    };
+    let (time, bonus) = match rest.find(['+', 'd', 'b']) {
+        Some(at) => {
+            let amount = seconds(&rest[at + 1..])?;
+            let bonus = match &rest[at..at + 1] {
+                "+" => Bonus::Fischer(amount),
+                "b" => Bonus::Bronstein(amount),
+                _ => Bonus::Delay(amount),
+            };
+            (&rest[..at], bonus)
+        }
+        None => (rest, Bonus::None),
+    };
+
+    Some(Stage {
+        moves,
+        time: seconds(time)?,
+        bonus,
+    })
+}
+
+/// A chess clock, only one side runs at a time
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    source: Arc<dyn TimeSource>,
+    remaining: [Duration; 2],
+    stage: [usize; 2],
+    stage_moves: [u32; 2],
+    /// The running side and when it started
+    running: Option<(Color, Duration)>,
+    flagged: Option<Color>,
+}
+
+fn side(color: Color) -> usize {
+    if color == Color::Black {
+        1
+    } else {
+        0
+    }
+}
+
+impl Clock {
+    /// Creates a stopped clock reading the system time
+    pub fn new(control: TimeControl) -> Clock {
+        Clock::with_source(control, Arc::new(SystemTimeSource::default()))
+    }
+
+    pub fn with_source(control: TimeControl, source: Arc<dyn TimeSource>) -> Clock {
+        let time = control.stage(0).time;
+        Clock {
+            control,
+            source,
+            remaining: [time, time],
+            stage: [0, 0],
+            stage_moves: [0, 0],
+            running: None,
+            flagged: None,
+        }
+    }
+
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Starts the clock of `color`, stopping the other one without ending its move
+    pub fn start(&mut self, color: Color) {
+        self.halt();
+        if self.flagged.is_none() {
+            self.running = Some((color, self.source.now()));
+        }
+    }
+
+    /// Stops the running clock without ending the move, eg. to pause the game
+    pub fn halt(&mut self) {
+        if let Some((color, elapsed)) = self.take_running() {
+            self.charge(color, elapsed);
+        }
+    }
+
+    /// Ends the move of the running side: its time is charged and the bonus applied,
+    /// the clock is left stopped. Returns the time left to that side
+    pub fn complete_move(&mut self) -> Option<Duration> {
+        let (color, elapsed) = self.take_running()?;
+        let i = side(color);
+        let stage = *self.control.stage(self.stage[i]);
+        if !self.charge(color, elapsed) {
+            return Some(Duration::from_secs(0));
+        }
+
+        match stage.bonus {
+            Bonus::Fischer(d) => self.remaining[i] += d,
+            Bonus::Bronstein(d) => self.remaining[i] += elapsed.min(d),
+            Bonus::None | Bonus::Delay(_) => {}
+        }
+
+        self.stage_moves[i] += 1;
+        if stage.moves == Some(self.stage_moves[i]) {
+            self.stage[i] += 1;
+            self.stage_moves[i] = 0;
+            self.remaining[i] += self.control.stage(self.stage[i]).time;
+        }
+        Some(self.remaining[i])
+    }
+
+    /// Ends the move of the running side and starts the clock of the other one
+    pub fn press(&mut self) {
+        if let Some((color, _)) = self.running {
+            self.complete_move();
+            self.start(color.switch());
+        }
+    }
+
+    fn take_running(&mut self) -> Option<(Color, Duration)> {
+        let (color, started) = self.running.take()?;
+        Some((color, self.source.now().saturating_sub(started)))
+    }
+
+    /// Deducts the time used by `color` for a move, returns false when its flag fell
+    fn charge(&mut self, color: Color, elapsed: Duration) -> bool {
+        let i = side(color);
+        let used = self.used(i, elapsed);
+        if used >= self.remaining[i] {
+            self.remaining[i] = Duration::from_secs(0);
+            self.flagged = Some(color);
+            return false;
+        }
+        self.remaining[i] -= used;
+        true
+    }
+
+    /// Time actually taken from the clock after `elapsed` on a move
+    fn used(&self, i: usize, elapsed: Duration) -> Duration {
+        match self.control.stage(self.stage[i]).bonus {
+            Bonus::Delay(d) => elapsed.saturating_sub(d),
+            _ => elapsed,
+        }
+    }
+
+    /// Returns the side currently thinking
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    /// Returns the time left to `color`, including the move in progress
+    pub fn remaining(&self, color: Color) -> Duration {
+        let i = side(color);
+        match self.running {
+            Some((c, started)) if c == color => {
+                let elapsed = self.source.now().saturating_sub(started);
+                self.remaining[i].saturating_sub(self.used(i, elapsed))
+            }
+            _ => self.remaining[i],
+        }
+    }
+
+    /// Returns the side whose time ran out, if any
+    pub fn flag(&self) -> Option<Color> {
+        if self.flagged.is_some() {
+            return self.flagged;
+        }
+        match self.running {
+            Some((color, _)) if self.remaining(color) == Duration::from_secs(0) => Some(color),
+            _ => None,
+        }
+    }
+
+    /// Returns the Fischer increment `color` gets for its next move
+    pub fn increment(&self, color: Color) -> Duration {
+        match self.control.stage(self.stage[side(color)]).bonus {
+            Bonus::Fischer(d) => d,
+            _ => Duration::from_secs(0),
+        }
+    }
+
+    /// Returns the number of moves `color` has to play before the next time control
+    pub fn moves_to_go(&self, color: Color) -> Option<u32> {
+        let i = side(color);
+        self.control
+            .stage(self.stage[i])
+            .moves
+            .map(|moves| moves - self.stage_moves[i])
+    }
+}
+
+/// Formats a clock reading as H:MM:SS, as in the PGN [%clk] command
+pub fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Parses a clock reading written H:MM:SS, with optional fractions of a second
+pub fn parse_clock(s: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    for part in s.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok().filter(|v| *v >= 0.0)?;
+    }
+    Some(Duration::from_secs_f64(seconds))
+}
@@ -0,0 +1,623 @@
+use crate::chessboard::{CastlingRights, Chessboard2};
+use crate::engine::piece_value;
+use crate::file::File;
+use crate::game::{Game, Move};
+use crate::pgn::Position;
+use crate::pieces::{Color, Kind, Piece};
+use crate::rank::Rank;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Most pieces, kings included, of the material sets the solver builds tables for
+pub const MAX_ENDGAME_PIECES: usize = 4;
+
+const CACHE_MAGIC: &[u8; 4] = b"RCDT";
+const CACHE_VERSION: u8 = 1;
+
+// values stored for each position: 0 is a draw (or not reached), otherwise the plies
+// to mate plus one. Even plies lose for the side to move, odd plies win
+const DRAW: u8 = 0;
+const INVALID: u8 = 255;
+/// Child count of a position that can reach a draw and never loses
+const NEVER_LOSES: u8 = 255;
+
+/// Distance to mate of a position with best play, from the point of view of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    /// Mates in this many moves
+    Win(u32),
+    /// Gets mated in this many moves, 0 when checkmated
+    Loss(u32),
+    Draw,
+}
+
+impl Dtm {
+    fn from_value(value: u8) -> Dtm {
+        match value {
+            DRAW | INVALID => Dtm::Draw,
+            v if (v - 1) % 2 == 1 => Dtm::Win(v as u32 / 2),
+            v => Dtm::Loss((v as u32 - 1) / 2),
+        }
+    }
+}
+
+impl fmt::Display for Dtm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dtm::Win(n) => write!(f, "mate in {}", n),
+            Dtm::Loss(0) => write!(f, "checkmated"),
+            Dtm::Loss(n) => write!(f, "mated in {}", n),
+            Dtm::Draw => write!(f, "draw"),
+        }
+    }
+}
+
+/// The pieces besides the kings of both sides, eg. KBNK has white bishop and knight
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Material {
+    pub white: Vec<Kind>,
+    pub black: Vec<Kind>,
+}
+
+const KIND_ORDER: [Kind; 5] = [Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight, Kind::Pawn];
+
+impl Material {
+    /// Parses names like "KQK" or "KRKN", the white pieces first
+    pub fn parse(s: &str) -> Option<Material> {
+        let s = s.to_uppercase();
+        let (white, black) = s.strip_prefix('K')?.split_once('K')?;
+        let kinds = |side: &str| {
+            side.chars()
+                .map(|c| match c {
+                    'Q' => Some(Kind::Queen),
+                    'R' => Some(Kind::Rook),
+                    'B' => Some(Kind::Bishop),
+                    'N' => Some(Kind::Knight),
+                    'P' => Some(Kind::Pawn),
+                    _ => None,
+                })
+                .collect::<Option<Vec<Kind>>>()
+        };
+        Some(Material::new(kinds(white)?, kinds(black)?))
+    }
+
+    pub fn new(mut white: Vec<Kind>, mut black: Vec<Kind>) -> Material {
+        let order = |k: &Kind| KIND_ORDER.iter().position(|o| o == k);
+        white.sort_by_key(order);
+        black.sort_by_key(order);
+        Material { white, black }
+    }
+
+    /// Returns the material of the pieces on a board, `None` without both kings
+    /// or with fairy pieces
+    pub fn of(board: &Chessboard2) -> Option<Material> {
+        if !board.is_standard_size() {
+            return None;
+        }
+        let mut kings = 0;
+        let mut white = Vec::new();
+        let mut black = Vec::new();
+        for (_, piece) in board.pieces() {
+            match (piece.kind, piece.color) {
+                (Kind::King, _) => kings += 1,
+                (Kind::Fairy(_), _) => return None,
+                (kind, Color::White) => white.push(kind),
+                (kind, _) => black.push(kind),
+            }
+        }
+        if kings != 2 {
+            return None;
+        }
+        Some(Material::new(white, black))
+    }
+
+    /// Number of pieces, kings included
+    pub fn count(&self) -> usize {
+        self.white.len() + self.black.len() + 2
+    }
+
+    /// Returns the same material with the colors swapped
+    pub fn flipped(&self) -> Material {
+        Material::new(self.black.clone(), self.white.clone())
+    }
+
+    /// Tables are built with the stronger side as white
+    fn is_normalized(&self) -> bool {
+        let value = |side: &[Kind]| side.iter().map(|k| piece_value(*k)).sum::<i32>();
+        let order = |side: &[Kind]| side.iter().map(|k| KIND_ORDER.iter().position(|o| o == k)).collect::<Vec<_>>();
+        (value(&self.white), self.white.len(), Reverse(order(&self.black)))
+            >= (value(&self.black), self.black.len(), Reverse(order(&self.white)))
+    }
+
+    /// Neither side has a piece that could ever mate
+    fn is_dead_draw(&self) -> bool {
+        let minor = |side: &[Kind]| side.is_empty() || side == [Kind::Bishop] || side == [Kind::Knight];
+        (self.white.is_empty() && minor(&self.black)) || (self.black.is_empty() && minor(&self.white))
+    }
+
+    fn has_pawns(&self) -> bool {
+        self.white.contains(&Kind::Pawn) || self.black.contains(&Kind::Pawn)
+    }
+
+    /// The pieces in index order: white king, white pieces, black king, black pieces
+    fn slots(&self) -> Vec<(Kind, Color)> {
+        let mut r = vec![(Kind::King, Color::White)];
+        r.extend(self.white.iter().map(|k| (*k, Color::White)));
+        r.push((Kind::King, Color::Black));
+        r.extend(self.black.iter().map(|k| (*k, Color::Black)));
+        r
+    }
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let side = |kinds: &[Kind]| kinds.iter().map(|k| k.pgn()).collect::<String>();
+        write!(f, "K{}K{}", side(&self.white), side(&self.black))
+    }
+}
+
+/// Answer of the solver for a position
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub dtm: Dtm,
+    /// The fastest mate, the longest defence or a move keeping the draw.
+    /// `None` when there are no legal moves
+    pub best_move: Option<Move>,
+}
+
+/// Distance to mate of every position of a material set, with white to move and black to
+/// move. Positions are indexed by the squares of the pieces in `Material::slots` order
+pub struct EndgameTable {
+    material: Material,
+    values: Vec<u8>,
+}
+
+impl EndgameTable {
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn size(material: &Material) -> usize {
+        2 << (6 * material.count())
+    }
+
+    fn value(&self, squares: &[usize], turn: Color) -> u8 {
+        let idx = canonical(&self.material, squares, turn);
+        self.values[idx]
+    }
+
+    /// Writes the table in the cache format
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let name = self.material.to_string();
+        let mut bytes = Vec::with_capacity(self.values.len() + 16);
+        bytes.extend_from_slice(CACHE_MAGIC);
+        bytes.push(CACHE_VERSION);
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&self.values);
+        fs::write(path, bytes)
+    }
+
+    /// Reads a table written by `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<EndgameTable> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        if bytes.len() < 6 || &bytes[..4] != CACHE_MAGIC || bytes[4] != CACHE_VERSION {
+            return Err(invalid("not an endgame table"));
+        }
+
+        let name_end = 6 + bytes[5] as usize;
+        let material = bytes
+            .get(6..name_end)
+            .and_then(|name| Material::parse(&String::from_utf8_lossy(name)))
+            .filter(|m| m.count() <= MAX_ENDGAME_PIECES)
+            .ok_or_else(|| invalid("unknown material"))?;
+        if bytes.len() - name_end != EndgameTable::size(&material) {
+            return Err(invalid("truncated endgame table"));
+        }
+
+        Ok(EndgameTable {
+            material,
+            values: bytes[name_end..].to_vec(),
+        })
+    }
+}
+
+/// Builds distance to mate tables of up to four pieces by retrograde analysis on demand
+/// and keeps them in memory, and on disk when a cache directory is set
+#[derive(Default)]
+pub struct EndgameSolver {
+    cache_dir: Option<PathBuf>,
+    tables: Mutex<HashMap<Material, Arc<EndgameTable>>>,
+}
+
+impl EndgameSolver {
+    pub fn new() -> EndgameSolver {
+        EndgameSolver::default()
+    }
+
+    /// Tables are read from and written to `dir`, it is created when missing
+    pub fn with_cache_dir<P: AsRef<Path>>(dir: P) -> EndgameSolver {
+        EndgameSolver {
+            cache_dir: Some(dir.as_ref().to_path_buf()),
+            tables: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the table of a material set with at most `MAX_ENDGAME_PIECES` pieces,
+    /// building it the first time. Errors come from the disk cache
+    pub fn table(&self, material: &Material) -> io::Result<Arc<EndgameTable>> {
+        if let Some(table) = self.tables.lock().unwrap().get(material) {
+            return Ok(table.clone());
+        }
+
+        let path = self.cache_dir.as_ref().map(|dir| dir.join(format!("{}.dtm", material)));
+        let cached = path
+            .as_ref()
+            .and_then(|p| EndgameTable::load(p).ok())
+            .filter(|t| t.material == *material);
+        let table = match cached {
+            Some(table) => table,
+            None => {
+                let table = self.build(material);
+                if let Some(path) = path {
+                    fs::create_dir_all(path.parent().unwrap())?;
+                    table.save(path)?;
+                }
+                table
+            }
+        };
+
+        let table = Arc::new(table);
+        self.tables.lock().unwrap().insert(material.clone(), table.clone());
+        Ok(table)
+    }
+
+    /// Returns the result of a game position and the move to play, `None` for more
+    /// than `MAX_ENDGAME_PIECES` pieces or with castling rights or pockets
+    pub fn solve(&self, game: &Game) -> Option<Solution> {
+        if game.castling().any() || game.board.pockets().is_some() {
+            return None;
+        }
+        let dtm = self.probe(&game.board, game.turn())?;
+
+        let mut best: Option<(Move, Dtm)> = None;
+        let mut board = game.board.clone();
+        for m in game.legal_moves() {
+            board.make(&m);
+            let reply = self.probe(&board, game.turn().switch());
+            board.unmake(&m);
+
+            let better = match (reply?, best.map(|(_, d)| d)) {
+                (_, None) => true,
+                // the quickest mate, then the longest defence, draws in between
+                (Dtm::Loss(n), Some(Dtm::Loss(b))) => n < b,
+                (Dtm::Loss(_), _) => true,
+                (Dtm::Draw, Some(Dtm::Win(_))) => true,
+                (Dtm::Win(n), Some(Dtm::Win(b))) => n > b,
+                _ => false,
+            };
+            if better {
+                best = Some((m, reply?));
+            }
+        }
+
+        Some(Solution {
+            dtm,
+            best_move: best.map(|(m, _)| m),
+        })
+    }
+
+    /// Returns the distance to mate of a position without castling and en passant rights
+    pub fn probe(&self, board: &Chessboard2, turn: Color) -> Option<Dtm> {
+        self.value(board, turn).map(Dtm::from_value)
+    }
+
+    fn value(&self, board: &Chessboard2, turn: Color) -> Option<u8> {
+        let material = Material::of(board)?;
+        if material.count() > MAX_ENDGAME_PIECES {
+            return None;
+        }
+        if material.is_dead_draw() {
+            return Some(DRAW);
+        }
+
+        // the colors are swapped and the board turned around for the weaker side
+        let flip = !material.is_normalized();
+        let material = if flip { material.flipped() } else { material };
+        let table = self.table(&material).ok()?;
+
+        let mut squares = Vec::with_capacity(MAX_ENDGAME_PIECES);
+        for (kind, color) in material.slots() {
+            let color = if flip { color.switch() } else { color };
+            let mut found: Vec<usize> = board
+                .pieces()
+                .filter(|(_, p)| p.kind == kind && p.color == color)
+                .map(|(pos, _)| square(pos) ^ if flip { 56 } else { 0 })
+                .filter(|sq| !squares.contains(sq))
+                .collect();
+            found.sort_unstable();
+            squares.push(*found.first()?);
+        }
+        let turn = if flip { turn.switch() } else { turn };
+        Some(table.value(&squares, turn))
+    }
+
+    /// Retrograde analysis: mates are found first, then positions are resolved by
+    /// increasing distance walking moves backwards. Moves leaving the material set
+    /// (captures and promotions) are looked up in the smaller tables
+    fn build(&self, material: &Material) -> EndgameTable {
+        let slots = material.slots();
+        let size = EndgameTable::size(material);
+        let mut values = vec![DRAW; size];
+        // distinct children inside the table not known to be lost
+        let mut counts = vec![0u8; size];
+        // longest mate reached by leaving the table
+        let mut exit_plies = vec![0u8; size];
+        let mut done = vec![false; size];
+        let mut buckets: Vec<Vec<u32>> = Vec::new();
+        let push = |buckets: &mut Vec<Vec<u32>>, plies: usize, idx: usize| {
+            if buckets.len() <= plies {
+                buckets.resize(plies + 1, Vec::new());
+            }
+            buckets[plies].push(idx as u32);
+        };
+
+        for idx in 0..size {
+            let (squares, turn) = decode(material, idx);
+            if canonical(material, &squares, turn) != idx {
+                continue;
+            }
+            if !is_valid(&slots, &squares, turn) {
+                values[idx] = INVALID;
+                continue;
+            }
+
+            let mut board = board_of(&slots, &squares);
+            let moves = legal_moves(&mut board, &slots, &squares, turn);
+            if moves.is_empty() {
+                if is_check(&board, &slots, &squares, turn) {
+                    values[idx] = 1;
+                    push(&mut buckets, 0, idx);
+                } else {
+                    done[idx] = true;
+                }
+                continue;
+            }
+
+            let mut children = Vec::with_capacity(moves.len());
+            let mut never_loses = false;
+            let mut exit_win: Option<u8> = None;
+            for m in moves.iter() {
+                if m.capture.is_some() || m.promotion.is_some() {
+                    board.make(m);
+                    let value = self.value(&board, turn.switch()).unwrap_or(DRAW);
+                    board.unmake(m);
+
+                    match value {
+                        DRAW | INVALID => never_loses = true,
+                        v if (v - 1) % 2 == 0 => exit_win = Some(exit_win.unwrap_or(u8::MAX).min(v)),
+                        v => exit_plies[idx] = exit_plies[idx].max(v - 1),
+                    }
+                    continue;
+                }
+
+                let mut child = squares.clone();
+                let moved = squares.iter().position(|sq| *sq == square(&m.from)).unwrap();
+                child[moved] = square(&m.to);
+                children.push(canonical(material, &child, turn.switch()));
+            }
+            children.sort_unstable();
+            children.dedup();
+            counts[idx] = if never_loses { NEVER_LOSES } else { children.len() as u8 };
+
+            if let Some(v) = exit_win {
+                // the child loses in v - 1 plies
+                values[idx] = v + 1;
+                push(&mut buckets, v as usize, idx);
+            } else if counts[idx] == 0 {
+                let plies = exit_plies[idx] as usize + 1;
+                values[idx] = plies as u8 + 1;
+                push(&mut buckets, plies, idx);
+            }
+        }
+
+        let mut plies = 0;
+        while plies < buckets.len() {
+            let bucket = std::mem::take(&mut buckets[plies]);
+            for idx in bucket {
+                let idx = idx as usize;
+                if done[idx] || values[idx] as usize != plies + 1 {
+                    continue;
+                }
+                done[idx] = true;
+
+                let (squares, turn) = decode(material, idx);
+                for parent in parents(material, &slots, &squares, turn) {
+                    if done[parent] || values[parent] == INVALID {
+                        continue;
+                    }
+                    if plies % 2 == 0 {
+                        // a move into a lost position wins
+                        if values[parent] == DRAW || values[parent] as usize > plies + 2 {
+                            values[parent] = plies as u8 + 2;
+                            push(&mut buckets, plies + 1, parent);
+                        }
+                    } else if values[parent] == DRAW && counts[parent] != NEVER_LOSES {
+                        // lost once every move lets the opponent win
+                        counts[parent] -= 1;
+                        if counts[parent] == 0 {
+                            let lost = plies.max(exit_plies[parent] as usize) + 1;
+                            values[parent] = lost as u8 + 1;
+                            push(&mut buckets, lost, parent);
+                        }
+                    }
+                }
+            }
+            plies += 1;
+        }
+
+        EndgameTable {
+            material: material.clone(),
+            values,
+        }
+    }
+}
+
+/// Squares numbered a1 = 0, b1 = 1 .. h8 = 63
+fn square(pos: &Position) -> usize {
+    8 * pos.file.to_index() + pos.rank.to_index()
+}
+
+fn position(sq: usize) -> Position {
+    Position::new_(Rank::from_index(sq & 7), File::from_index(sq >> 3))
+}
+
+fn decode(material: &Material, idx: usize) -> (Vec<usize>, Color) {
+    let turn = if idx & 1 == 1 { Color::Black } else { Color::White };
+    let n = material.count();
+    let squares = (0..n).map(|i| (idx >> (1 + 6 * (n - 1 - i))) & 63).collect();
+    (squares, turn)
+}
+
+fn encode(squares: &[usize], turn: Color) -> usize {
+    let idx = squares.iter().fold(0, |idx, sq| idx << 6 | sq);
+    idx << 1 | (turn == Color::Black) as usize
+}
+
+/// Index of the position among its mirror images with the lowest index, the one
+/// that holds the value. Pawns only allow mirroring the files
+fn canonical(material: &Material, squares: &[usize], turn: Color) -> usize {
+    let slots = material.slots();
+    let symmetries = if material.has_pawns() { 2 } else { 8 };
+    let mut best = usize::MAX;
+    let mut t = squares.to_vec();
+    for symmetry in 0..symmetries {
+        for (i, sq) in squares.iter().enumerate() {
+            let (mut rank, mut file) = (sq >> 3, sq & 7);
+            if symmetry & 1 != 0 {
+                file = 7 - file;
+            }
+            if symmetry & 2 != 0 {
+                rank = 7 - rank;
+            }
+            if symmetry & 4 != 0 {
+                std::mem::swap(&mut rank, &mut file);
+            }
+            t[i] = rank * 8 + file;
+        }
+        // identical pieces are interchangeable
+        for i in 1..t.len() {
+            let mut j = i;
+            while j > 0 && slots[j] == slots[j - 1] && t[j] < t[j - 1] {
+                t.swap(j, j - 1);
+                j -= 1;
+            }
+        }
+        best = best.min(encode(&t, turn));
+    }
+    best
+}
+
+fn board_of(slots: &[(Kind, Color)], squares: &[usize]) -> Chessboard2 {
+    let mut board = Chessboard2::new();
+    for ((kind, color), sq) in slots.iter().zip(squares.iter()) {
+        board.set_(position(*sq), Piece::new(*kind, *color));
+    }
+    board
+}
+
+fn king_square(slots: &[(Kind, Color)], squares: &[usize], color: Color) -> usize {
+    let i = slots.iter().position(|s| *s == (Kind::King, color)).unwrap();
+    squares[i]
+}
+
+fn is_check(board: &Chessboard2, slots: &[(Kind, Color)], squares: &[usize], color: Color) -> bool {
+    board.is_square_attacked(&position(king_square(slots, squares, color)), color.switch())
+}
+
+/// Pieces on different squares, no pawns on the first and last rank, kings apart
+/// and the side that just moved not in check
+fn is_valid(slots: &[(Kind, Color)], squares: &[usize], turn: Color) -> bool {
+    for (i, sq) in squares.iter().enumerate() {
+        if squares[..i].contains(sq) {
+            return false;
+        }
+        if slots[i].0 == Kind::Pawn && (sq >> 3 == 0 || sq >> 3 == 7) {
+            return false;
+        }
+    }
+    let (white, black) = (king_square(slots, squares, Color::White), king_square(slots, squares, Color::Black));
+    let distance = ((white >> 3) as i32 - (black >> 3) as i32)
+        .abs()
+        .max(((white & 7) as i32 - (black & 7) as i32).abs());
+    if distance <= 1 {
+        return false;
+    }
+    !is_check(&board_of(slots, squares), slots, squares, turn.switch())
+}
+
+/// Legal moves with the project's generator, checked on the board itself as
+/// `Chessboard2::legal_moves` copies the board for every position
+fn legal_moves(board: &mut Chessboard2, slots: &[(Kind, Color)], squares: &[usize], turn: Color) -> Vec<Move> {
+    let king = position(king_square(slots, squares, turn));
+    board
+        .pseudo_legal_moves(turn, CastlingRights::none(), None)
+        .into_iter()
+        .filter(|m| {
+            board.make(m);
+            let king = if m.piece.kind == Kind::King { m.to } else { king };
+            let legal = !board.is_square_attacked(&king, turn.switch());
+            board.unmake(m);
+            legal
+        })
+        .collect()
+}
+
+/// Positions inside the table that reach this one with a move, without duplicates.
+/// Pieces move back the way they move forward, only pawns are handled apart
+fn parents(material: &Material, slots: &[(Kind, Color)], squares: &[usize], turn: Color) -> Vec<usize> {
+    let mover = turn.switch();
+    let board = board_of(slots, squares);
+    let mut r = Vec::new();
+    let mut add = |from: usize, to: usize| {
+        let mut parent = squares.to_vec();
+        let moved = squares.iter().position(|sq| *sq == from).unwrap();
+        parent[moved] = to;
+        r.push(canonical(material, &parent, mover));
+    };
+
+    for m in board.pseudo_legal_moves(mover, CastlingRights::none(), None) {
+        if m.capture.is_none() && m.piece.kind != Kind::Pawn && !m.is_castling {
+            add(square(&m.from), square(&m.to));
+        }
+    }
+
+    let back: i32 = if mover == Color::White { -8 } else { 8 };
+    let start_rank = if mover == Color::White { 1 } else { 6 };
+    for (i, sq) in squares.iter().enumerate() {
+        if slots[i] != (Kind::Pawn, mover) {
+            continue;
+        }
+        let one = (*sq as i32 + back) as usize;
+        if squares.contains(&one) {
+            continue;
+        }
+        if one >> 3 != 0 && one >> 3 != 7 {
+            add(*sq, one);
+        }
+        let two = (one as i32 + back) as usize;
+        if two >> 3 == start_rank && !squares.contains(&two) {
+            add(*sq, two);
+        }
+    }
+
+    r.sort_unstable();
+    r.dedup();
+    r
+}
@@ -0,0 +1,572 @@
+use crate::fairy::fairy_piece;
+use crate::game::Game;
+use crate::game::GameResult;
+use crate::game::Move;
+use crate::pieces::Color;
+use crate::pieces::Kind;
+use crate::syzygy::{Tablebase, Wdl};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Score of a checkmate at the root, mates further away score lower
+pub const MATE: i32 = 30000;
+
+/// Deepest iteration the search will start
+pub const MAX_DEPTH: u32 = 64;
+
+/// Score of a tablebase win at the root, below any mate score
+pub const TB_WIN: i32 = MATE - 1000;
+
+/// Evaluation of a position from the point of view of the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Centipawns
+    Cp(i32),
+    /// Mate in the given number of moves, negative if the side to move gets mated
+    Mate(i32),
+}
+
+impl Score {
+    pub fn from_value(value: i32) -> Score {
+        if value > MATE - MAX_DEPTH as i32 * 2 {
+            Score::Mate((MATE - value + 1) / 2)
+        } else if value < -MATE + MAX_DEPTH as i32 * 2 {
+            Score::Mate(-(MATE + value) / 2)
+        } else {
+            Score::Cp(value)
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Score::Cp(v) => write!(f, "cp {}", v),
+            Score::Mate(v) => write!(f, "mate {}", v),
+        }
+    }
+}
+
+/// When to stop searching, all limits are optional
+#[derive(Debug, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> SearchLimits {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..SearchLimits::default()
+        }
+    }
+
+    /// Returns how long the side `color` may think for this move
+    pub fn time_for(&self, color: Color, overhead: Duration) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(movetime.checked_sub(overhead).unwrap_or_default());
+        }
+
+        let (time, inc) = match color {
+            Color::Black => (self.btime, self.binc),
+            _ => (self.wtime, self.winc),
+        };
+        let time = time?;
+        let inc = inc.unwrap_or_default();
+        let moves_left = self.movestogo.unwrap_or(30).max(1);
+
+        let budget = time / moves_left + inc * 3 / 4;
+        let max = time.checked_sub(overhead).unwrap_or_default();
+        Some(budget.min(max))
+    }
+}
+
+/// Progress report sent after each completed iteration
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+impl SearchInfo {
+    /// Nodes per second
+    pub fn nps(&self) -> u64 {
+        let millis = self.time.as_millis() as u64;
+        if millis == 0 {
+            return 0;
+        }
+        self.nodes * 1000 / millis
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+    pub score: Score,
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+pub struct Engine {
+    stop: Arc<AtomicBool>,
+    pub move_overhead: Duration,
+    nodes: u64,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    aborted: bool,
+    pv: Vec<Move>,
+    tablebase: Option<Arc<Tablebase>>,
+    /// Root moves the tablebases allow, all moves when empty
+    root_moves: Vec<Move>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+impl Engine {
+    pub fn new() -> Engine {
+        Engine {
+            stop: Arc::new(AtomicBool::new(false)),
+            move_overhead: Duration::from_millis(10),
+            nodes: 0,
+            node_limit: None,
+            deadline: None,
+            aborted: false,
+            pv: Vec::new(),
+            tablebase: None,
+            root_moves: Vec::new(),
+        }
+    }
+
+    /// Sets the endgame tablebases probed during the search
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn tablebase(&self) -> Option<&Arc<Tablebase>> {
+        self.tablebase.as_ref()
+    }
+
+    /// Returns the flag that aborts a running search when set,
+    /// it has to be cleared again before the next search
+    pub fn stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Searches the current position of `game` for the best move,
+    /// `on_info` is called after every completed iteration
+    pub fn search<F>(&mut self, game: &Game, limits: &SearchLimits, mut on_info: F) -> SearchResult
+    where
+        F: FnMut(&SearchInfo),
+    {
+        let start = Instant::now();
+        let mut game = game.clone();
+        let time = limits.time_for(game.turn(), self.move_overhead);
+
+        self.nodes = 0;
+        self.node_limit = limits.nodes;
+        self.deadline = time.map(|t| start + t);
+        self.aborted = false;
+        self.pv.clear();
+        self.root_moves = self.tablebase_root_moves(&game);
+
+        let mut result = SearchResult {
+            best_move: None,
+            ponder: None,
+            score: Score::Cp(0),
+            depth: 0,
+            nodes: 0,
+        };
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let value = self.negamax(&mut game, depth, 0, -MATE - 1, MATE + 1, &mut pv);
+            if self.aborted && depth > 1 {
+                break;
+            }
+
+            self.pv = pv;
+            result.best_move = self.pv.first().copied();
+            result.ponder = self.pv.get(1).copied();
+            result.score = Score::from_value(value);
+            result.depth = depth;
+            result.nodes = self.nodes;
+
+            on_info(&SearchInfo {
+                depth,
+                score: result.score,
+                nodes: self.nodes,
+                time: start.elapsed(),
+                pv: self.pv.clone(),
+            });
+
+            if self.aborted || result.best_move.is_none() {
+                break;
+            }
+            if let Score::Mate(_) = result.score {
+                if !limits.infinite {
+                    break;
+                }
+            }
+            // another iteration would not finish in time
+            if let Some(t) = time {
+                if start.elapsed() > t / 2 {
+                    break;
+                }
+            }
+        }
+
+        if result.best_move.is_none() {
+            result.best_move = game.legal_moves().first().copied();
+        }
+        result.nodes = self.nodes;
+        result
+    }
+
+    /// Keeps the root moves the tablebases rate best: the fastest wins, the draws or the
+    /// longest losses. The search then chooses among them
+    fn tablebase_root_moves(&self, game: &Game) -> Vec<Move> {
+        let moves = match self.tablebase.as_ref().and_then(|tb| tb.probe_moves(game)) {
+            Some(moves) => moves,
+            None => return Vec::new(),
+        };
+        let best = match moves.first() {
+            Some(t) => (t.wdl, t.dtz),
+            None => return Vec::new(),
+        };
+        moves
+            .into_iter()
+            .filter(|t| (t.wdl, t.dtz) == best)
+            .map(|t| t.m)
+            .collect()
+    }
+
+    fn probe_wdl(&self, game: &Game) -> Option<Wdl> {
+        let tb = self.tablebase.as_ref()?;
+        if game.castling().any() || game.board.pockets().is_some() {
+            return None;
+        }
+        tb.probe_wdl(&game.board, game.turn(), game.en_passant())
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.aborted {
+            return true;
+        }
+
+        if self.nodes & 511 == 0 {
+            let out_of_time = match self.deadline {
+                Some(d) => Instant::now() >= d,
+                None => false,
+            };
+            let out_of_nodes = match self.node_limit {
+                Some(n) => self.nodes >= n,
+                None => false,
+            };
+            if out_of_time || out_of_nodes || self.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+        }
+        self.aborted
+    }
+
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        if ply > 0 && self.should_stop() {
+            return 0;
+        }
+
+        if ply > 0 && game.halfmove_clock() >= 100 {
+            return 0;
+        }
+
+        let mut moves = game.legal_moves();
+        // at the root a move is still wanted as long as there is one
+        if ply > 0 || moves.is_empty() {
+            if let Some((result, _)) = game.rules().result(game, &moves) {
+                return match result {
+                    GameResult::Draw => 0,
+                    r if r == GameResult::win_for(game.turn()) => MATE - ply as i32,
+                    _ => -MATE + ply as i32,
+                };
+            }
+        }
+
+        // probed when a capture or pawn move enters the tables, the 50 move counter is then 0
+        if ply > 0 && game.halfmove_clock() == 0 {
+            if let Some(wdl) = self.probe_wdl(game) {
+                return match wdl {
+                    Wdl::Win => TB_WIN - ply as i32,
+                    Wdl::Loss => -TB_WIN + ply as i32,
+                    _ => 0,
+                };
+            }
+        }
+        if ply == 0 && !self.root_moves.is_empty() {
+            let allowed = &self.root_moves;
+            moves.retain(|m| allowed.iter().any(|a| a.same(m)));
+        }
+
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+
+        let pv_move = self.pv.get(ply as usize).copied();
+        order_moves(&mut moves, pv_move);
+
+        for m in moves {
+            game.play(m);
+            let mut child_pv = Vec::new();
+            let value = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha, &mut child_pv);
+            game.rollback_move();
+
+            if self.aborted {
+                return 0;
+            }
+
+            if value > alpha {
+                alpha = value;
+                pv.clear();
+                pv.push(m);
+                pv.append(&mut child_pv);
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        alpha
+    }
+
+    fn quiescence(&mut self, game: &mut Game, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let stand_pat = evaluate(game);
+        if stand_pat >= beta || ply >= MAX_DEPTH * 2 {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        let mut moves: Vec<Move> = game
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.capture.is_some() || m.promotion == Some(Kind::Queen))
+            .collect();
+        order_moves(&mut moves, None);
+
+        for m in moves {
+            game.play(m);
+            let value = -self.quiescence(game, ply + 1, -beta, -alpha);
+            game.rollback_move();
+
+            if self.aborted {
+                return 0;
+            }
+            if value > alpha {
+                alpha = value;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        alpha
+    }
+}
+
+/// Sorts the principal variation move first, then captures by most valuable
+/// victim / least valuable attacker and promotions
+fn order_moves(moves: &mut [Move], pv_move: Option<Move>) {
+    moves.sort_by_cached_key(|m| {
+        if let Some(pv) = pv_move {
+            if pv.same(m) {
+                return i32::MIN;
+            }
+        }
+
+        let mut key = 0;
+        if let Some(c) = m.capture {
+            key -= 10 * piece_value(c.kind) - piece_value(m.piece.kind) / 10;
+        }
+        if let Some(p) = m.promotion {
+            key -= piece_value(p);
+        }
+        key
+    });
+}
+
+pub fn piece_value(kind: Kind) -> i32 {
+    match kind {
+        Kind::Pawn => 100,
+        Kind::Knight => 320,
+        Kind::Bishop => 330,
+        Kind::Rook => 500,
+        Kind::Queen => 900,
+        Kind::King => 0,
+        Kind::Fairy(_) => fairy_piece(kind).map_or(0, |p| p.value),
+    }
+}
+
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5,  5, 10, 25, 25, 10,  5,  5,
+    10, 10, 20, 30, 30, 20, 10, 10,
+    50, 50, 50, 50, 50, 50, 50, 50,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+   -20,-10,-10,-10,-10,-10,-10,-20,
+   -10,  5,  0,  0,  0,  0,  5,-10,
+   -10, 10, 10, 10, 10, 10, 10,-10,
+   -10,  0, 10, 10, 10, 10,  0,-10,
+   -10,  5,  5, 10, 10,  5,  5,-10,
+   -10,  0,  5, 10, 10,  5,  0,-10,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  5,  5,  0,  0,  0,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     5, 10, 10, 10, 10, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    20, 30, 10,  0,  0, 10, 30, 20,
+    20, 20,  0,  0,  0,  0, 20, 20,
+   -10,-20,-20,-20,-20,-20,-20,-10,
+   -20,-30,-30,-40,-40,-30,-30,-20,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+];
+
+/// Static evaluation in centipawns from the point of view of the side to move
+pub fn evaluate(game: &Game) -> i32 {
+    let mut score = 0;
+    let tables = game.board.is_standard_size();
+    for (pos, piece) in game.board.pieces() {
+        // tables are written from white's side with the first file first
+        let file = match piece.color {
+            Color::Black => 7 - pos.file.to_index().min(7),
+            _ => pos.file.to_index(),
+        };
+        let square = file * 8 + pos.rank.to_index();
+
+        let bonus = match piece.kind {
+            // the tables only fit the standard board
+            _ if !tables => 0,
+            Kind::Pawn => PAWN_TABLE[square],
+            Kind::Knight => KNIGHT_TABLE[square],
+            Kind::Bishop => BISHOP_TABLE[square],
+            Kind::Rook => ROOK_TABLE[square],
+            Kind::Queen => 0,
+            Kind::King => KING_TABLE[square],
+            Kind::Fairy(_) => 0,
+        };
+        let value = piece_value(piece.kind) + bonus;
+
+        if piece.color == game.turn() {
+            score += value;
+        } else {
+            score -= value;
+        }
+    }
+
+    if let Some(pockets) = game.board.pockets() {
+        for kind in pockets.pieces(game.turn()) {
+            score += piece_value(kind);
+        }
+        for kind in pockets.pieces(game.turn().switch()) {
+            score -= piece_value(kind);
+        }
+    }
+    score
+}
+
+/// Counts the leaf nodes of the legal move tree, used to verify the move generator
+pub fn perft(game: &mut Game, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = game.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for m in moves {
+        game.play(m);
+        nodes += perft(game, depth - 1);
+        game.rollback_move();
+    }
+    nodes
+}
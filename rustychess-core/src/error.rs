@@ -8,10 +8,8 @@ pub enum Error {
     InvalidFen { fen: String },
 
     /// The board created from BoardBuilder was found to be invalid
-    #[fail(
-        display = "The board specified did not pass sanity checks.  Are you sure the kings exist and the side to move cannot capture the opposing king?"
-    )]
-    InvalidBoard,
+    #[fail(display = "The board specified did not pass sanity checks: {}", reason)]
+    InvalidBoard { reason: String },
 
     /// An attempt was made to create a square from an invalid string
     #[fail(display = "The string specified does not contain a valid algebraic notation square")]
@@ -36,4 +34,24 @@ pub enum Error {
     /// Unknown piece
     #[fail(display = "Unknown piece")]
     InvalidPiece,
+
+    /// A piece movement that is not valid Betza notation
+    #[fail(display = "Invalid Betza notation: {}", betza)]
+    InvalidBetza { betza: String },
+
+    /// A fairy piece that can't be registered
+    #[fail(display = "Invalid fairy piece: {}", reason)]
+    InvalidFairyPiece { reason: String },
+
+    /// A variant name that no `Rules` implementation answers to
+    #[fail(display = "Unknown variant: {}", name)]
+    UnknownVariant { name: String },
+
+    /// A move played after the flag of the mover fell
+    #[fail(display = "Move played after the flag fell: {}", san)]
+    MoveAfterFlagFall { san: String },
+
+    /// A board wider or higher than 16 squares, or without any
+    #[fail(display = "Invalid board size: {}x{}", width, height)]
+    InvalidBoardSize { width: usize, height: usize },
 }
@@ -0,0 +1,296 @@
+use crate::error::Error;
+use crate::pieces::{Color, Kind};
+use std::sync::{LazyLock, RwLock};
+
+pub const ARCHBISHOP: Kind = Kind::Fairy(0);
+
+pub const CHANCELLOR: Kind = Kind::Fairy(1);
+
+pub const AMAZON: Kind = Kind::Fairy(2);
+
+pub const CAMEL: Kind = Kind::Fairy(3);
+
+pub const GRASSHOPPER: Kind = Kind::Fairy(4);
+
+/// What a step may do on the square it reaches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepMode {
+    /// Move to an empty square or capture
+    Any,
+    /// Only move to an empty square, Betza `m`
+    Move,
+    /// Only capture, Betza `c`
+    Capture,
+    /// Jump over the first piece in the line and land right behind it, Betza `g`
+    Hop,
+}
+
+/// One direction a piece moves in, written from white's side: `files` goes up the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub ranks: i8,
+    pub files: i8,
+    /// How many times the step repeats, 1 for a leaper and 0 for an unlimited rider
+    pub range: u8,
+    pub mode: StepMode,
+}
+
+/// A piece beyond the standard six, moving as its Betza notation says
+#[derive(Debug, Clone)]
+pub struct FairyPiece {
+    pub name: String,
+    /// Letter in FEN and SAN, uppercase
+    pub letter: char,
+    pub betza: String,
+    /// Worth in centipawns for the engine
+    pub value: i32,
+    steps: Vec<Step>,
+    pgn: String,
+    symbols: [String; 2],
+}
+
+impl FairyPiece {
+    /// Creates a piece from its Betza notation, eg. "BN" for the Archbishop.
+    /// The value is estimated from the mobility on an empty board
+    pub fn new(name: &str, letter: char, betza: &str) -> Result<FairyPiece, Error> {
+        let invalid = |reason: &str| Error::InvalidFairyPiece {
+            reason: format!("{}: {}", name, reason),
+        };
+        let letter = letter.to_ascii_uppercase();
+        if !letter.is_ascii_alphabetic() || "PNBRQK".contains(letter) {
+            return Err(invalid("the letter must be free and alphabetic"));
+        }
+
+        let steps = parse_betza(betza)?;
+        let mut r = FairyPiece {
+            name: name.to_string(),
+            letter,
+            betza: betza.to_string(),
+            value: 0,
+            steps,
+            pgn: letter.to_string(),
+            symbols: [letter.to_string(), letter.to_ascii_lowercase().to_string()],
+        };
+        r.value = 40 * r.mobility() / 64;
+        Ok(r)
+    }
+
+    pub fn with_value(mut self, value: i32) -> FairyPiece {
+        self.value = value;
+        self
+    }
+
+    /// Changes the symbols the board printout uses, the letters by default
+    pub fn with_symbols(mut self, white: &str, black: &str) -> FairyPiece {
+        self.symbols = [white.to_string(), black.to_string()];
+        self
+    }
+
+    /// Returns the steps of a piece of `color`, turned around for black
+    pub fn steps(&self, color: Color) -> impl Iterator<Item = Step> + '_ {
+        let sign = if color == Color::Black { -1 } else { 1 };
+        self.steps.iter().map(move |s| Step {
+            ranks: s.ranks * sign,
+            files: s.files * sign,
+            ..*s
+        })
+    }
+
+    pub fn pgn(&self) -> &str {
+        &self.pgn
+    }
+
+    pub fn symbol(&self, color: Color) -> &str {
+        &self.symbols[(color == Color::Black) as usize]
+    }
+
+    /// Number of moves summed over all squares of an empty 8x8 board
+    fn mobility(&self) -> i32 {
+        let mut r = 0;
+        for rank in 0..8i8 {
+            for file in 0..8i8 {
+                for step in self.steps.iter().filter(|s| s.mode != StepMode::Capture) {
+                    let range = if step.range == 0 { 7 } else { step.range as i8 };
+                    for n in 1..=range {
+                        let (x, y) = (rank + n * step.ranks, file + n * step.files);
+                        if !(0..8).contains(&x) || !(0..8).contains(&y) {
+                            break;
+                        }
+                        r += 1;
+                    }
+                }
+            }
+        }
+        r
+    }
+}
+
+/// Registered pieces are never dropped, so `Kind` can hand out their names and
+/// symbols. Each one is leaked once when it is registered
+static FAIRY_PIECES: LazyLock<RwLock<Vec<&'static FairyPiece>>> = LazyLock::new(|| {
+    let builtin = [
+        ("Archbishop", 'A', "BN", 850),
+        ("Chancellor", 'C', "RN", 900),
+        ("Amazon", 'M', "QN", 1200),
+        ("Camel", 'L', "C", 250),
+        ("Grasshopper", 'G', "gQ", 200),
+    ];
+    let pieces = builtin
+        .iter()
+        .map(|(name, letter, betza, value)| {
+            &*Box::leak(Box::new(FairyPiece::new(name, *letter, betza).unwrap().with_value(*value)))
+        })
+        .collect();
+    RwLock::new(pieces)
+});
+
+/// Makes a piece known to FEN, SAN and the move generator and returns its kind
+pub fn register_fairy_piece(piece: FairyPiece) -> Result<Kind, Error> {
+    let mut pieces = FAIRY_PIECES.write().unwrap();
+    if pieces.iter().any(|p| p.letter == piece.letter) {
+        return Err(Error::InvalidFairyPiece {
+            reason: format!("{}: the letter {} is taken", piece.name, piece.letter),
+        });
+    }
+    if pieces.len() > u8::MAX as usize {
+        return Err(Error::InvalidFairyPiece {
+            reason: format!("{}: too many pieces", piece.name),
+        });
+    }
+    pieces.push(Box::leak(Box::new(piece)));
+    Ok(Kind::Fairy((pieces.len() - 1) as u8))
+}
+
+/// Returns the definition of a fairy piece, `None` for the standard kinds
+pub fn fairy_piece(kind: Kind) -> Option<&'static FairyPiece> {
+    match kind {
+        Kind::Fairy(i) => FAIRY_PIECES.read().unwrap().get(i as usize).cloned(),
+        _ => None,
+    }
+}
+
+/// Finds the fairy piece with the given letter, in either case
+pub fn fairy_kind(letter: char) -> Option<Kind> {
+    let letter = letter.to_ascii_uppercase();
+    FAIRY_PIECES
+        .read()
+        .unwrap()
+        .iter()
+        .position(|p| p.letter == letter)
+        .map(|i| Kind::Fairy(i as u8))
+}
+
+/// Parses a piece's moves in Betza notation: the atoms W, F, D, N, A, H, C, Z, G and
+/// the compounds K, Q, R, B. A doubled atom or a number rides, eg. "NN" or "R2".
+/// Lowercase prefixes limit the atom: m moves, c captures, g hops, and f, b, l, r,
+/// v, s and pairs such as "fl" choose the directions
+pub fn parse_betza(betza: &str) -> Result<Vec<Step>, Error> {
+    let invalid = || Error::InvalidBetza { betza: betza.to_string() };
+    let chars: Vec<char> = betza.chars().collect();
+    let mut r = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && chars[i].is_ascii_lowercase() {
+            i += 1;
+        }
+        let prefix: String = chars[start..i].iter().collect();
+        let atom = *chars.get(i).ok_or_else(invalid)?;
+        i += 1;
+
+        let (atoms, mut range): (&[(i8, i8)], u8) = match atom {
+            'W' => (&[(1, 0)], 1),
+            'F' => (&[(1, 1)], 1),
+            'D' => (&[(2, 0)], 1),
+            'N' => (&[(2, 1)], 1),
+            'A' => (&[(2, 2)], 1),
+            'H' => (&[(3, 0)], 1),
+            'C' => (&[(3, 1)], 1),
+            'Z' => (&[(3, 2)], 1),
+            'G' => (&[(3, 3)], 1),
+            'K' => (&[(1, 0), (1, 1)], 1),
+            'R' => (&[(1, 0)], 0),
+            'B' => (&[(1, 1)], 0),
+            'Q' => (&[(1, 0), (1, 1)], 0),
+            _ => return Err(invalid()),
+        };
+        if chars.get(i) == Some(&atom) {
+            range = 0;
+            i += 1;
+        } else if chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+            let digits: String = chars[i..].iter().take_while(|c| c.is_ascii_digit()).collect();
+            i += digits.len();
+            range = digits.parse().map_err(|_| invalid())?;
+        }
+
+        let mut mode = StepMode::Any;
+        let mut directions = Vec::new();
+        let prefix: Vec<char> = prefix.chars().collect();
+        let mut j = 0;
+        while j < prefix.len() {
+            match prefix[j] {
+                'm' => mode = StepMode::Move,
+                'c' => mode = StepMode::Capture,
+                'g' => mode = StepMode::Hop,
+                c @ ('f' | 'b') if matches!(prefix.get(j + 1), Some('l') | Some('r')) => {
+                    directions.push(format!("{}{}", c, prefix[j + 1]));
+                    j += 1;
+                }
+                c @ ('f' | 'b' | 'l' | 'r' | 'v' | 's') => directions.push(c.to_string()),
+                _ => return Err(invalid()),
+            }
+            j += 1;
+        }
+
+        for (a, b) in atoms {
+            for (ranks, files) in symmetries(*a, *b) {
+                let allowed = directions.is_empty() || directions.iter().any(|d| direction(d, ranks, files));
+                let step = Step {
+                    ranks,
+                    files,
+                    range,
+                    mode,
+                };
+                if allowed && !r.contains(&step) {
+                    r.push(step);
+                }
+            }
+        }
+    }
+
+    if r.is_empty() {
+        return Err(invalid());
+    }
+    Ok(r)
+}
+
+/// The up to eight directions of a leap of `a` and `b` squares
+fn symmetries(a: i8, b: i8) -> Vec<(i8, i8)> {
+    let mut r = Vec::with_capacity(8);
+    for (x, y) in [(a, b), (b, a)] {
+        for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let v = (x * sx, y * sy);
+            if !r.contains(&v) {
+                r.push(v);
+            }
+        }
+    }
+    r
+}
+
+fn direction(d: &str, ranks: i8, files: i8) -> bool {
+    match d {
+        "f" => files > 0,
+        "b" => files < 0,
+        "l" => ranks < 0,
+        "r" => ranks > 0,
+        "v" => files.abs() > ranks.abs(),
+        "s" => ranks.abs() > files.abs(),
+        "fl" => files > 0 && ranks < 0,
+        "fr" => files > 0 && ranks > 0,
+        "bl" => files < 0 && ranks < 0,
+        "br" => files < 0 && ranks > 0,
+        _ => false,
+    }
+}
@@ -14,12 +14,23 @@ pub enum File {
     Sixth,
     Seventh,
     Eighth,
+    Ninth,
+    Tenth,
+    Eleventh,
+    Twelfth,
+    Thirteenth,
+    Fourteenth,
+    Fifteenth,
+    Sixteenth,
 }
 
-/// How many ranks are there?
+/// How many files are there on the standard board?
 pub const NUM_FILES: usize = 8;
 
-/// Enumerate all ranks
+/// Files on the highest board
+pub const MAX_FILES: usize = 16;
+
+/// Enumerate the files of the standard board
 pub const ALL_FILES: [File; NUM_FILES] = [
     File::First,
     File::Second,
@@ -32,26 +43,26 @@ pub const ALL_FILES: [File; NUM_FILES] = [
 ];
 
 impl File {
-    /// Convert a `usize` into a `File` (the inverse of to_index).  If the number is > 7, wrap
+    /// Convert a `usize` into a `File` (the inverse of to_index).  If the number is > 15, wrap
     /// around.
     #[inline]
     pub fn from_index(i: usize) -> File {
-        unsafe { transmute((i as u8) & 7) }
+        unsafe { transmute((i as u8) & 15) }
     }
 
-    /// Go one rank down.  If impossible, wrap around.
+    /// Go one file down.  If impossible, stay.
     #[inline]
     pub fn down(&self) -> File {
-        File::from_index(self.to_index().wrapping_sub(1))
+        File::from_index(self.to_index().saturating_sub(1))
     }
 
-    /// Go one file up.  If impossible, wrap around.
+    /// Go one file up.  If impossible, stay.
     #[inline]
     pub fn up(&self) -> File {
-        File::from_index(self.to_index() + 1)
+        File::from_index((self.to_index() + 1).min(MAX_FILES - 1))
     }
 
-    /// Convert this `File` into a `usize` between 0 and 7 (inclusive).
+    /// Convert this `File` into a `usize` between 0 and 15 (inclusive).
     #[inline]
     pub fn to_index(&self) -> usize {
         *self as usize
@@ -59,28 +70,12 @@ impl File {
 
     #[inline]
     pub fn to_i8(&self) -> i8 {
-        match self {
-            &File::First => 1,
-            &File::Second => 2,
-            &File::Third => 3,
-            &File::Fourth => 4,
-            &File::Fifth => 5,
-            &File::Sixth => 6,
-            &File::Seventh => 7,
-            &File::Eighth => 8,
-        }
+        self.to_index() as i8 + 1
     }
 
     pub fn from_i8(s: i8) -> Result<Self, Error> {
         match s {
-            1 => Ok(File::First),
-            2 => Ok(File::Second),
-            3 => Ok(File::Third),
-            4 => Ok(File::Fourth),
-            5 => Ok(File::Fifth),
-            6 => Ok(File::Sixth),
-            7 => Ok(File::Seventh),
-            8 => Ok(File::Eighth),
+            1..=16 => Ok(File::from_index(s as usize - 1)),
             _ => Err(Error::InvalidFile),
         }
     }
@@ -115,16 +110,10 @@ impl FromStr for File {
         if s.len() < 1 {
             return Err(Error::InvalidFile);
         }
-        match s.chars().next().unwrap() {
-            '1' => Ok(File::First),
-            '2' => Ok(File::Second),
-            '3' => Ok(File::Third),
-            '4' => Ok(File::Fourth),
-            '5' => Ok(File::Fifth),
-            '6' => Ok(File::Sixth),
-            '7' => Ok(File::Seventh),
-            '8' => Ok(File::Eighth),
-            _ => Err(Error::InvalidFile),
+        let digits: String = s.chars().take_while(|c| c.is_ascii_digit()).collect();
+        match digits.parse::<i8>() {
+            Ok(v) => File::from_i8(v),
+            Err(_) => Err(Error::InvalidFile),
         }
     }
 }
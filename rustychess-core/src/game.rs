@@ -1,16 +1,28 @@
+use crate::book;
 use crate::chessboard::BoardStatus;
+use crate::chessboard::CastlingRights;
 use crate::chessboard::Chessboard2;
+use crate::chessboard::Explosion;
+use crate::chessboard::Pockets;
+use crate::clock::Clock;
+use crate::error::Error as ChessError;
+use crate::fairy::fairy_kind;
 use crate::pgn::Position;
 use crate::pgn::PGN;
 use crate::pieces::Color;
 use crate::pieces::Kind;
 use crate::pieces::Piece;
+use crate::rank::Rank;
+use crate::variant::{Rules, Standard};
 use chrono::Local;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::error::Error;
 use std::fmt::{self, Debug};
 use std::io::{Result as ioResult, Write};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 use crate::pieces::PieceMovements;
 
 #[derive(Copy, Clone, Debug)]
@@ -20,6 +32,15 @@ pub struct Move {
     pub piece: Piece,
     pub capture: Option<Piece>,
     pub is_from_orignal_pos: bool,
+    pub promotion: Option<Kind>,
+    pub is_castling: bool,
+    /// Square the rook starts from when castling
+    pub rook: Option<Position>,
+    pub is_en_passant: bool,
+    /// A piece from the pocket put on `to`, `from` is the same square
+    pub is_drop: bool,
+    /// The pieces an Atomic capture blows up
+    pub explosion: Option<Explosion>,
 }
 
 impl fmt::Display for Move {
@@ -46,7 +67,73 @@ impl fmt::Display for Move {
 }
 
 impl Move {
+    pub fn new(from: Position, to: Position, piece: Piece) -> Move {
+        Move {
+            from,
+            to,
+            piece,
+            capture: None,
+            is_from_orignal_pos: false,
+            promotion: None,
+            is_castling: false,
+            rook: None,
+            is_en_passant: false,
+            is_drop: false,
+            explosion: None,
+        }
+    }
+
+    /// Creates a Crazyhouse drop of `piece` onto `to`
+    pub fn drop(piece: Piece, to: Position) -> Move {
+        let mut m = Move::new(to, to, piece);
+        m.is_drop = true;
+        m
+    }
+
+    /// Returns the move in UCI (long algebraic) notation, eg. "e2e4", "e7e8q" or "N@f3"
+    pub fn uci(&self) -> String {
+        if self.is_drop {
+            return format!("{}@{}", self.piece.kind.pgn(), self.to.algebraic());
+        }
+        let promotion = match self.promotion {
+            Some(kind) => kind.pgn().to_lowercase(),
+            None => String::new(),
+        };
+
+        format!("{}{}{}", self.from.algebraic(), self.to.algebraic(), promotion)
+    }
+
+    /// Returns the move in UCI notation for Chess960, where castling is written
+    /// as the king taking its own rook, eg. "e1h1"
+    pub fn uci_chess960(&self) -> String {
+        match self.rook {
+            Some(rook) if self.is_castling => format!("{}{}", self.from.algebraic(), rook.algebraic()),
+            _ => self.uci(),
+        }
+    }
+
+    /// Returns whenever a castling move goes to the king's side, where the rook is
+    /// right of the king
+    pub fn castles_king_side(&self) -> bool {
+        match self.rook {
+            Some(rook) => rook.rank > self.from.rank,
+            None => self.to.rank > self.from.rank,
+        }
+    }
+
+    /// Returns whenever two moves describe the same action on the board
+    pub fn same(&self, other: &Move) -> bool {
+        self.from == other.from
+            && self.to == other.to
+            && self.promotion == other.promotion
+            && self.is_drop == other.is_drop
+            && (!self.is_drop || self.piece.kind == other.piece.kind)
+    }
+
     fn pgn(&self) -> String {
+        if self.is_drop {
+            return format!("{}@{} ", self.piece.kind.pgn(), self.to.algebraic());
+        }
         let kind_str = if self.piece.kind != Kind::Pawn {
             self.piece.kind.pgn()
         } else {
@@ -75,14 +162,116 @@ impl Move {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    pub fn win_for(color: Color) -> GameResult {
+        if color == Color::Black {
+            GameResult::BlackWins
+        } else {
+            GameResult::WhiteWins
+        }
+    }
+
+    /// Returns the result as written in PGN, eg. "1-0"
+    pub fn pgn(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+
+    pub fn from_pgn(s: &str) -> Option<GameResult> {
+        match s {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+}
+
+/// Why a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    InsufficientMaterial,
+    TimeForfeit,
+    IllegalMove,
+    Adjudication,
+    Resignation,
+    /// Both players agreed to a draw
+    Agreement,
+    /// A rule of the variant played, eg. "king of the hill"
+    Variant(&'static str),
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::FiftyMoveRule => "fifty move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "illegal move",
+            Termination::Adjudication => "adjudication",
+            Termination::Resignation => "resignation",
+            Termination::Agreement => "agreement",
+            Termination::Variant(rule) => rule,
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn square_color(pos: &Position) -> bool {
+    (pos.rank.to_index() + pos.file.to_index()).is_multiple_of(2)
+}
+
+/// The parts of a position that cannot be recovered when a move is taken back
+#[derive(Copy, Clone, Debug)]
+struct State {
+    castling: CastlingRights,
+    en_passant: Option<Position>,
+    halfmove_clock: u32,
+    checks: [u32; 2],
+}
+
 #[derive(Clone, Debug)]
 pub struct Game {
     pub board: Chessboard2,
     turn: Color,
     moves: VecDeque<Move>,
     metadata: HashMap<String, String>,
+    castling: CastlingRights,
+    en_passant: Option<Position>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    states: Vec<State>,
+    pending_promotion: Option<Move>,
+    clock: Option<Clock>,
+    /// Time left to the mover after each move, when played with a clock
+    clock_times: Vec<Option<Duration>>,
+    chess960: bool,
+    rules: Arc<dyn Rules>,
+    /// Checks given by white and black
+    checks: [u32; 2],
+    /// The result of a game ended by resignation or agreement
+    declared: Option<(GameResult, Termination)>,
 }
 
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 impl Game {
     pub fn new() -> Game {
         let mut r = Game {
@@ -90,6 +279,18 @@ impl Game {
             turn: Color::White,
             moves: VecDeque::with_capacity(90),
             metadata: HashMap::new(),
+            castling: CastlingRights::all(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            states: Vec::with_capacity(90),
+            pending_promotion: None,
+            clock: None,
+            clock_times: Vec::new(),
+            chess960: false,
+            rules: Arc::new(Standard),
+            checks: [0; 2],
+            declared: None,
         };
         r.board.reset();
         r.insert_metadata(
@@ -99,11 +300,243 @@ impl Game {
         r
     }
 
+    /// Creates a Chess960 game from the starting position with the given number,
+    /// see `chessboard::chess960_back_rank`
+    pub fn new_chess960(index: u16) -> Game {
+        let mut r = Game::new();
+        r.board.reset_chess960(index);
+        r.castling = CastlingRights::chess960(index);
+        r.chess960 = true;
+        r
+    }
+
+    /// Creates a game of a variant from its starting position
+    pub fn with_rules(rules: Arc<dyn Rules>) -> Game {
+        let mut r = Game::new();
+        if rules.starting_fen() != STARTING_FEN {
+            r.set_fen(rules.starting_fen()).unwrap();
+        }
+        r.rules = rules;
+        r
+    }
+
+    /// Creates a game starting from the position described by a FEN string
+    pub fn from_fen(fen: &str) -> Result<Game, ChessError> {
+        let mut r = Game::new();
+        r.set_fen(fen)?;
+        Ok(r)
+    }
+
+    /// Replaces the current position with the one described by a FEN string,
+    /// the move history is cleared
+    pub fn set_fen(&mut self, fen: &str) -> Result<(), ChessError> {
+        let invalid = || ChessError::InvalidFen { fen: fen.to_string() };
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err(invalid());
+        }
+
+        let mut board = Chessboard2::from_fen(fields[0])?;
+        if self.rules.has_drops() && board.pockets().is_none() {
+            board.set_pockets(Some(Pockets::default()));
+        }
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(invalid()),
+        };
+        let castling = CastlingRights::from_fen(fields[2], &board).map_err(|_| invalid())?;
+        let en_passant = match fields[3] {
+            "-" => None,
+            v => Some(Position::from_algebraic(v).map_err(|_| invalid())?),
+        };
+        let halfmove_clock = match fields.get(4) {
+            Some(v) => v.parse::<u32>().map_err(|_| invalid())?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(v) => v.parse::<u32>().map_err(|_| invalid())?,
+            None => 1,
+        };
+        // checks given in Three-check, eg. "+1+0"
+        let checks = match fields.get(6) {
+            Some(v) => {
+                let counts: Vec<&str> = v.split('+').collect();
+                match counts.as_slice() {
+                    ["", white, black] => [
+                        white.parse::<u32>().map_err(|_| invalid())?,
+                        black.parse::<u32>().map_err(|_| invalid())?,
+                    ],
+                    _ => return Err(invalid()),
+                }
+            }
+            None => [0; 2],
+        };
+
+        self.set_position(board, turn, castling, en_passant, [halfmove_clock, fullmove_number], checks);
+        Ok(())
+    }
+
+    /// Replaces the current position without any checks, the move history is cleared.
+    /// `move_numbers` are the halfmove clock and the fullmove number
+    pub(crate) fn set_position(
+        &mut self,
+        board: Chessboard2,
+        turn: Color,
+        castling: CastlingRights,
+        en_passant: Option<Position>,
+        move_numbers: [u32; 2],
+        checks: [u32; 2],
+    ) {
+        // kings castling from elsewhere than the e-file only happen in Chess960
+        let moved_king = [Color::White, Color::Black].iter().any(|color| {
+            let castles = castling.king_side(*color) || castling.queen_side(*color);
+            castles && board.find_piece(Kind::King, *color).is_ok_and(|(pos, _)| pos.rank != Rank::E)
+        });
+        self.chess960 |= board.is_standard_size() && (castling.is_chess960() || moved_king);
+
+        self.board = board;
+        self.turn = turn;
+        self.castling = castling;
+        self.en_passant = en_passant;
+        self.halfmove_clock = move_numbers[0];
+        self.fullmove_number = move_numbers[1].max(1);
+        self.checks = checks;
+        self.moves.clear();
+        self.states.clear();
+        self.pending_promotion = None;
+        self.clock_times.clear();
+        self.declared = None;
+    }
+
+    /// Returns the current position as a FEN string
+    pub fn fen(&self) -> String {
+        let en_passant = match self.en_passant {
+            Some(pos) => pos.algebraic(),
+            None => "-".to_string(),
+        };
+
+        let mut r = format!(
+            "{} {} {} {} {} {}",
+            self.board.fen(),
+            if self.turn == Color::White { "w" } else { "b" },
+            self.castling.fen_on(self.board.width()),
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        );
+        if self.rules.check_limit().is_some() {
+            r.push_str(&format!(" +{}+{}", self.checks[0], self.checks[1]));
+        }
+        r
+    }
+
+    /// Returns whenever castling follows the Chess960 rules in the notation:
+    /// UCI moves are written king takes rook and PGN gets the Variant tag
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    /// Returns the rules the game is played by
+    pub fn rules(&self) -> &dyn Rules {
+        self.rules.as_ref()
+    }
+
+    /// The rules as shared with other games, eg. to start one of the same variant
+    pub fn shared_rules(&self) -> Arc<dyn Rules> {
+        self.rules.clone()
+    }
+
+    /// Changes the rules, the position stays as it is
+    pub fn set_rules(&mut self, rules: Arc<dyn Rules>) {
+        self.rules = rules;
+    }
+
+    /// Returns how many checks `color` gave, counted since the FEN the game started from
+    pub fn checks_given(&self, color: Color) -> u32 {
+        self.checks[(color == Color::Black) as usize]
+    }
+
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn castling(&self) -> CastlingRights {
+        self.castling
+    }
+
+    pub fn en_passant(&self) -> Option<Position> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Returns the Polyglot opening book key of the current position
+    pub fn polyglot_key(&self) -> u64 {
+        book::polyglot_key(&self.board, self.turn, self.castling, self.en_passant)
+    }
+
+    /// Returns the position before the first move as a FEN string
+    pub fn starting_fen(&self) -> String {
+        let mut game = self.clone();
+        while game.rollback_move().is_some() {}
+        game.fen()
+    }
+
+    /// Returns the moves played so far
+    pub fn moves(&self) -> &VecDeque<Move> {
+        &self.moves
+    }
+
+    /// Plays the rest of the game with a clock, the side to move starts thinking now
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        clock.start(self.turn);
+        self.clock = Some(clock);
+    }
+
+    pub fn clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    pub fn clock_mut(&mut self) -> Option<&mut Clock> {
+        self.clock.as_mut()
+    }
+
+    /// Returns the time the mover had left after the move with index `ply`
+    pub fn clock_time(&self, ply: usize) -> Option<Duration> {
+        self.clock_times.get(ply).cloned().flatten()
+    }
+
     pub fn reset(&mut self) {
         self.board.reset();
         self.metadata.clear();
         self.turn = Color::White;
         self.moves.clear();
+        self.castling = CastlingRights::all();
+        self.en_passant = None;
+        self.halfmove_clock = 0;
+        self.fullmove_number = 1;
+        self.chess960 = false;
+        self.checks = [0; 2];
+        self.states.clear();
+        self.pending_promotion = None;
+        self.clock = None;
+        self.clock_times.clear();
+        self.declared = None;
+        if self.rules.starting_fen() != STARTING_FEN {
+            let fen = self.rules.starting_fen();
+            self.set_fen(fen).unwrap();
+        }
 
         self.insert_metadata(
             "created_at".to_string(),
@@ -115,6 +548,10 @@ impl Game {
         self.metadata.insert(key, value);
     }
 
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
     pub fn print_metadata(self) {
         for (key, value) in self.metadata {
             println!("{}: {}", key, value);
@@ -129,9 +566,22 @@ impl Game {
         Ok(())
     }
 
+    /// Returns the move `move_` left waiting for the promotion piece
+    pub fn pending_promotion(&self) -> Option<Move> {
+        self.pending_promotion
+    }
+
+    /// Finishes a move that was left waiting for the promotion piece,
+    /// `pos` is either the square the pawn moves from or to
     pub fn promote(&mut self, pos: &Position, kind: Kind) {
-        self.board.promote(pos, kind, self.turn);
-        self.turn = self.turn.switch();
+        match self.pending_promotion {
+            Some(mut m) if m.from == *pos || m.to == *pos => {
+                m.promotion = Some(kind);
+                self.pending_promotion = None;
+                self.play_timed(m);
+            }
+            _ => {}
+        }
     }
 
     pub fn number_of_moves(self) -> usize {
@@ -154,19 +604,413 @@ impl Game {
         }
     }
 
-    pub fn rollback_move(&mut self) {
-        let m = self.moves.pop_back();
-        println!("{:#?}", m);
-        match m {
-            Some(mm) => {
-                self.board.set_(mm.from, Box::new(mm.piece));
-                match mm.capture {
-                    Some(c) => self.board.set_(mm.to, Box::new(c)),
-                    None => {}
+    /// Ends the game without a move, eg. by `Termination::Resignation`. Taking
+    /// back a move lets the game go on
+    pub fn declare_result(&mut self, result: GameResult, termination: Termination) {
+        self.declared = Some((result, termination));
+    }
+
+    /// Takes back the last move and returns it
+    pub fn rollback_move(&mut self) -> Option<Move> {
+        let m = self.moves.pop_back()?;
+        self.declared = None;
+        if let Some(c) = m.capture.filter(|_| !self.rules.captures_to_pocket()) {
+            self.update_pocket(m.piece.color, Pockets::kind_of(&c), true);
+        }
+        self.board.unmake(&m);
+        self.turn = self.turn.switch();
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+
+        if let Some(state) = self.states.pop() {
+            self.castling = state.castling;
+            self.en_passant = state.en_passant;
+            self.halfmove_clock = state.halfmove_clock;
+            self.checks = state.checks;
+        }
+        self.clock_times.truncate(self.moves.len());
+        Some(m)
+    }
+
+    /// Adds a piece to or removes one from the pocket of `color`, if the board has pockets
+    pub fn update_pocket(&mut self, color: Color, kind: Kind, add: bool) {
+        if let Some(mut pockets) = self.board.pockets().copied() {
+            if add {
+                pockets.add(color, kind);
+            } else {
+                pockets.remove(color, kind);
+            }
+            self.board.set_pockets(Some(pockets));
+        }
+    }
+
+    /// Returns all legal moves of the side to move under the rules of the game
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.rules.legal_moves(self)
+    }
+
+    /// Plays a move like `play` and presses the clock, if the game has one.
+    /// Returns false without playing the move when the mover ran out of time
+    pub fn play_timed(&mut self, m: Move) -> bool {
+        let left = match self.clock.as_mut() {
+            Some(clock) => {
+                let left = clock.complete_move();
+                if clock.flag().is_some() {
+                    return false;
+                }
+                left
+            }
+            None => None,
+        };
+
+        self.play(m);
+        self.clock_times.resize(self.moves.len() - 1, None);
+        self.clock_times.push(left);
+
+        let over = self.outcome().is_some();
+        if let Some(clock) = self.clock.as_mut() {
+            if !over {
+                clock.start(self.turn);
+            }
+        }
+        true
+    }
+
+    /// Plays a move generated by `legal_moves` without validating it
+    pub fn play(&mut self, mut m: Move) {
+        self.states.push(State {
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            checks: self.checks,
+        });
+
+        self.board.make(&m);
+        m.is_from_orignal_pos = m.piece.number_of_moves == 0;
+        if let Some(c) = m.capture.filter(|_| !self.rules.captures_to_pocket()) {
+            self.update_pocket(m.piece.color, Pockets::kind_of(&c), false);
+        }
+
+        if m.piece.kind == Kind::King {
+            self.castling.clear(m.piece.color);
+        }
+        let last = self.board.last_file();
+        self.castling.clear_rook_at(&m.from, last);
+        self.castling.clear_rook_at(&m.to, last);
+        if let Some(explosion) = m.explosion {
+            for (pos, piece) in explosion.pieces(m.to) {
+                if piece.kind == Kind::King {
+                    self.castling.clear(piece.color);
+                }
+                self.castling.clear_rook_at(&pos, last);
+            }
+        }
+
+        self.en_passant = None;
+        if m.piece.kind == Kind::Pawn && m.from.file.sub(m.to.file) == 2 {
+            self.en_passant = m.from.offset(0, if m.piece.color == Color::White { 1 } else { -1 });
+        }
+
+        if m.piece.kind == Kind::Pawn || m.capture.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.turn = self.turn.switch();
+        if self.is_check() {
+            self.checks[(m.piece.color == Color::Black) as usize] += 1;
+        }
+        self.moves.push_back(m);
+    }
+
+    /// Returns whenever the side to move is in check
+    pub fn is_check(&self) -> bool {
+        self.rules.is_check(&self.board, self.turn)
+    }
+
+    /// Returns `Checkmate` or `Stalemate` if the side to move has no legal moves
+    pub fn status(&self) -> BoardStatus {
+        if !self.legal_moves().is_empty() {
+            return BoardStatus::None;
+        }
+
+        if self.is_check() {
+            BoardStatus::Checkmate
+        } else {
+            BoardStatus::Stalemate
+        }
+    }
+
+    /// Returns the move in standard algebraic notation, eg. "Nf3", "exd5", "e8=Q+", "O-O" or "N@f3"
+    pub fn san(&self, m: &Move) -> String {
+        let mut r = String::new();
+        if m.is_drop {
+            r.push_str(m.piece.kind.pgn());
+            r.push('@');
+            r.push_str(&m.to.algebraic());
+        } else if m.is_castling {
+            r.push_str(if m.castles_king_side() { "O-O" } else { "O-O-O" });
+        } else {
+            if m.piece.kind == Kind::Pawn {
+                if m.capture.is_some() {
+                    r.push_str(m.from.rank.to_str());
                 }
+            } else {
+                r.push_str(m.piece.kind.pgn());
+
+                let others: Vec<Move> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|o| o.piece.kind == m.piece.kind && o.to == m.to && o.from != m.from)
+                    .collect();
+                if !others.is_empty() {
+                    let same_rank = others.iter().any(|o| o.from.rank == m.from.rank);
+                    let same_file = others.iter().any(|o| o.from.file == m.from.file);
+                    if !same_rank {
+                        r.push_str(m.from.rank.to_str());
+                    } else if !same_file {
+                        r.push_str(&m.from.file.to_i8().to_string());
+                    } else {
+                        r.push_str(&m.from.algebraic());
+                    }
+                }
+            }
+
+            if m.capture.is_some() {
+                r.push('x');
+            }
+            r.push_str(&m.to.algebraic());
+            if let Some(kind) = m.promotion {
+                r.push('=');
+                r.push_str(kind.pgn());
             }
-            None => {}
         }
+
+        let mut after = self.clone();
+        after.play(*m);
+        if after.status() == BoardStatus::Checkmate {
+            r.push('#');
+        } else if after.is_check() {
+            r.push('+');
+        }
+        r
+    }
+
+    /// Finds the legal move matching a move in standard algebraic notation, eg. "Nbd7" or "exd8=Q+"
+    pub fn san_move(&self, notation: &str) -> Result<Move, ChessError> {
+        let san = notation.trim().trim_end_matches(|c| "+#!?".contains(c));
+        let legal = self.legal_moves();
+
+        let castling = match san {
+            "O-O" | "0-0" => Some(true),
+            "O-O-O" | "0-0-0" => Some(false),
+            _ => None,
+        };
+        if let Some(king_side) = castling {
+            return legal
+                .into_iter()
+                .find(|m| m.is_castling && m.castles_king_side() == king_side)
+                .ok_or(ChessError::InvalidSanMove);
+        }
+
+        if let Some(at) = san.find('@') {
+            return self.drop_move(&san[..at], &san[at + 1..]).ok_or(ChessError::InvalidSanMove);
+        }
+
+        let (san, promotion) = match san.find('=') {
+            Some(at) => (&san[..at], Some(&san[at + 1..])),
+            None => match san.char_indices().last() {
+                Some((at, c)) if ("QRBN".contains(c) || (c.is_ascii_uppercase() && fairy_kind(c).is_some())) && at > 0 => {
+                    (&san[..at], Some(&san[at..]))
+                }
+                _ => (san, None),
+            },
+        };
+        let promotion = match promotion {
+            Some(p) => Some(
+                Piece::from_str(p)
+                    .map_err(|_| ChessError::InvalidSanMove)?
+                    .kind,
+            ),
+            None => None,
+        };
+
+        let (kind, rest) = match san.chars().next() {
+            Some(c) if "KQRBN".contains(c) || (c.is_ascii_uppercase() && fairy_kind(c).is_some()) => (
+                Piece::from_str(&c.to_string())
+                    .map_err(|_| ChessError::InvalidSanMove)?
+                    .kind,
+                &san[1..],
+            ),
+            _ => (Kind::Pawn, san),
+        };
+
+        let rest: String = rest.chars().filter(|c| *c != 'x' && *c != '-').collect();
+        if !rest.is_ascii() {
+            return Err(ChessError::InvalidSanMove);
+        }
+        // the target square is the last letter with its row number, eg. "d7" or "j10"
+        let at = rest.rfind(|c: char| c.is_ascii_alphabetic()).ok_or(ChessError::InvalidSanMove)?;
+        let to = Position::from_algebraic(&rest[at..]).map_err(|_| ChessError::InvalidSanMove)?;
+        let (rank, file) = rest[..at].split_at(rest[..at].find(|c: char| c.is_ascii_digit()).unwrap_or(at));
+        if rank.len() > 1 || (!file.is_empty() && file.parse::<i8>().is_err()) {
+            return Err(ChessError::InvalidSanMove);
+        }
+
+        let candidates: Vec<Move> = legal
+            .into_iter()
+            .filter(|m| {
+                m.piece.kind == kind && m.to == to && m.promotion == promotion && !m.is_castling && !m.is_drop
+            })
+            .filter(|m| {
+                (rank.is_empty() || m.from.rank.to_str() == rank)
+                    && (file.is_empty() || m.from.file.to_i8().to_string() == file)
+            })
+            .collect();
+
+        match candidates.as_slice() {
+            [m] => Ok(*m),
+            _ => Err(ChessError::InvalidSanMove),
+        }
+    }
+
+    /// Finds the legal drop of the piece with letter `kind` ("N", empty for a pawn) onto `square`
+    fn drop_move(&self, kind: &str, square: &str) -> Option<Move> {
+        let kind = match kind {
+            "" => Kind::Pawn,
+            k => Piece::from_str(&k.to_ascii_uppercase()).ok()?.kind,
+        };
+        let to = Position::from_algebraic(square).ok()?;
+        self.legal_moves()
+            .into_iter()
+            .find(|m| m.is_drop && m.piece.kind == kind && m.to == to)
+    }
+
+    /// Returns how often the current position occurred before, only positions since
+    /// the last capture or pawn move are compared
+    pub fn repetitions(&self) -> usize {
+        let key = |g: &Game| {
+            let fen = g.fen();
+            fen.split_whitespace().take(4).collect::<Vec<&str>>().join(" ")
+        };
+
+        let current = key(self);
+        let mut game = self.clone();
+        let mut r = 0;
+        for _ in 0..self.halfmove_clock.min(self.moves.len() as u32) {
+            game.rollback_move();
+            if key(&game) == current {
+                r += 1;
+            }
+        }
+        r
+    }
+
+    /// Returns whenever `color` still has enough material to possibly checkmate
+    pub fn can_mate(&self, color: Color) -> bool {
+        let own: Vec<Kind> = self
+            .board
+            .pieces()
+            .filter(|(_, p)| p.color == color && p.kind != Kind::King)
+            .map(|(_, p)| p.kind)
+            .collect();
+        let opponent: Vec<(Position, Kind)> = self
+            .board
+            .pieces()
+            .filter(|(_, p)| p.color != color && p.kind != Kind::King)
+            .map(|(pos, p)| (*pos, p.kind))
+            .collect();
+
+        if own
+            .iter()
+            .any(|k| matches!(k, Kind::Pawn | Kind::Rook | Kind::Queen | Kind::Fairy(_)))
+        {
+            return true;
+        }
+
+        let knights = own.iter().filter(|k| **k == Kind::Knight).count();
+        let bishops = own.iter().filter(|k| **k == Kind::Bishop).count();
+        match (knights, bishops) {
+            (0, 0) => false,
+            // a lone knight only mates if the opponent blocks its own king
+            (1, 0) => opponent.iter().any(|(_, k)| *k != Kind::Queen),
+            (0, _) => {
+                let own_colors: Vec<bool> = self
+                    .board
+                    .pieces()
+                    .filter(|(_, p)| p.color == color && p.kind == Kind::Bishop)
+                    .map(|(pos, _)| square_color(pos))
+                    .collect();
+                let same_colored = own_colors.iter().all(|c| *c == own_colors[0]);
+                !same_colored
+                    || opponent.iter().any(|(pos, k)| {
+                        *k == Kind::Pawn
+                            || *k == Kind::Knight
+                            || (*k == Kind::Bishop && square_color(pos) != own_colors[0])
+                    })
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns whenever neither side can checkmate anymore
+    pub fn is_insufficient_material(&self) -> bool {
+        !self.can_mate(Color::White) && !self.can_mate(Color::Black)
+    }
+
+    /// Returns the result if the game ended by the rules
+    pub fn outcome(&self) -> Option<(GameResult, Termination)> {
+        if self.declared.is_some() {
+            return self.declared;
+        }
+        if let Some(color) = self.clock.as_ref().and_then(Clock::flag) {
+            let result = if self.can_mate(color.switch()) {
+                GameResult::win_for(color.switch())
+            } else {
+                GameResult::Draw
+            };
+            return Some((result, Termination::TimeForfeit));
+        }
+
+        let moves = self.legal_moves();
+        if let Some(r) = self.rules.result(self, &moves) {
+            return Some(r);
+        }
+
+        if self.halfmove_clock >= 100 {
+            Some((GameResult::Draw, Termination::FiftyMoveRule))
+        } else if self.repetitions() >= 2 {
+            Some((GameResult::Draw, Termination::ThreefoldRepetition))
+        } else {
+            None
+        }
+    }
+
+    /// Finds the legal move matching a move in UCI notation, eg. "e2e4", "e7e8q" or "N@f3".
+    /// In Chess960 games castling is written as the king taking its own rook
+    pub fn uci_move(&self, notation: &str) -> Result<Move, ChessError> {
+        let notation = notation.trim();
+        if let Some(at) = notation.find('@') {
+            return self
+                .drop_move(&notation[..at], &notation[at + 1..])
+                .ok_or(ChessError::InvalidUciMove);
+        }
+        let (from, to, promotion) = parse_coordinates(notation).ok_or(ChessError::InvalidUciMove)?;
+        self.legal_moves()
+            .into_iter()
+            .find(|m| {
+                let target = match m.rook {
+                    Some(rook) if self.chess960 => rook,
+                    _ => m.to,
+                };
+                m.from == from && target == to && m.promotion == promotion
+            })
+            .ok_or(ChessError::InvalidUciMove)
     }
 
     pub fn count_moves(&self, piece: &Piece) -> usize {
@@ -196,77 +1040,56 @@ impl Game {
         }
     }
 
+    /// Plays a move given as "e2e4", "Qh4e1", "e7e8q" or the drop "N@f3"
+    ///
+    /// Returns `BoardStatus::Promote` when a pawn reaches the last file without
+    /// a promotion piece, the move is then finished by `promote`
     pub fn move_(&mut self, pgn: &str) -> Result<BoardStatus, Box<dyn Error>> {
-        let prev_move = self.moves.back();
-        let can_castle = self.can_castle(self.turn);
-        let result = self.board.move_(prev_move, pgn, self.turn, can_castle);
-        match result {
-            Ok(r) => {
-                self.add_move(r.0);
-                let is_checked = self.board.is_checked(self.turn);
-                if !is_checked {
-                    match r.1 {
-                        BoardStatus::Promote => {
-                            return Ok(r.1);
-                        }
-                        _ => {}
-                    }
-
-                    self.turn = self.turn.switch();
-                } else {
-                    self.rollback_move();
-                    println!("Invalid move. Move leads to check");
-                }
+        if pgn.contains('@') {
+            let m = self.san_move(pgn).map_err(|e| e.to_string())?;
+            if !self.play_timed(m) {
+                return Err(format!("{:?} lost on time", self.turn).into());
             }
-            Err(e) => println!("{}", e),
+            return Ok(self.status());
         }
-        println!("{:?}", self.turn);
-        Ok(BoardStatus::None)
-    }
 
-    fn can_castle(&self, color: Color) -> bool {
-        if self.board.is_checked(color) {
-            return false;
-        }
-
-        let (a_rook_pos, h_rook_pos, king_pos) = match color {
-            Color::White => (
-                Position::new("A", 1),
-                Position::new("H", 1),
-                Position::new("E", 1),
-            ),
-            Color::Black => (
-                Position::new("A", 8),
-                Position::new("H", 8),
-                Position::new("E", 8),
-            ),
-            _ => panic!("Error! Not a chess color"),
+        let (from, to, promotion) = match parse_coordinates(pgn) {
+            Some(v) => v,
+            None => return Err("Invalid move".into()),
         };
 
-        let has_moved = [
-            (&Piece::new(Kind::King, color), king_pos),
-            (&Piece::new(Kind::Rook, color), a_rook_pos),
-            (&Piece::new(Kind::Rook, color), h_rook_pos),
-        ]
-        .iter()
-        .any(|&(piece, pos)| self.find_move(piece, Some(&pos), None).is_some());
-
-        if has_moved {
-            return false;
-        }
-
-        let short_side: Vec<Position> = king_pos.shortest_path(a_rook_pos);
-        let long_side: Vec<Position> = king_pos.shortest_path(h_rook_pos);
-
-        let c: Vec<Position> = short_side.iter().chain(&long_side).copied().collect();
+        let candidates: Vec<Move> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|m| m.from == from && m.to == to)
+            .collect();
 
-        let any_field_threatened = c
-            .iter()
-            .any(|&field| self.clone().board.is_field_threatened(color, &field));
+        let m = match candidates.first() {
+            Some(m) => *m,
+            None => {
+                if self.board.get_with_pos(&from).is_none() {
+                    return Err("Invalid move. Field empty!".into());
+                }
+                return Err("Invalid move".into());
+            }
+        };
 
-        let is_blocking = c.iter().any(|pos| self.board.get_with_pos(pos).is_some());
+        let m = if m.promotion.is_some() {
+            match candidates.into_iter().find(|m| m.promotion == promotion) {
+                Some(m) => m,
+                None => {
+                    self.pending_promotion = Some(m);
+                    return Ok(BoardStatus::Promote);
+                }
+            }
+        } else {
+            m
+        };
 
-        any_field_threatened && !is_blocking
+        if !self.play_timed(m) {
+            return Err(format!("{:?} lost on time", self.turn).into());
+        }
+        Ok(self.status())
     }
 
     pub fn save(&self, writer: &mut dyn Write) -> ioResult<()> {
@@ -331,3 +1154,39 @@ impl Game {
     }
 
 }
+
+/// Splits a move in coordinate notation into its squares and promotion piece,
+/// the piece letter in front ("Qh4e1") and a "=" before the promotion are optional
+fn parse_coordinates(notation: &str) -> Option<(Position, Position, Option<Kind>)> {
+    let notation = notation.trim();
+    let notation = match notation.chars().next() {
+        Some(c) if c.is_ascii_uppercase() => &notation[1..],
+        _ => notation,
+    };
+    if !notation.is_ascii() {
+        return None;
+    }
+
+    let (from, notation) = split_square(notation)?;
+    let (to, notation) = split_square(notation)?;
+    let promotion = match notation.trim_start_matches('=') {
+        "" => None,
+        p if p.len() == 1 => match p.to_ascii_lowercase().as_str() {
+            "q" => Some(Kind::Queen),
+            "r" => Some(Kind::Rook),
+            "b" => Some(Kind::Bishop),
+            "n" => Some(Kind::Knight),
+            p => Some(fairy_kind(p.chars().next()?)?),
+        },
+        _ => return None,
+    };
+
+    Some((from, to, promotion))
+}
+
+/// Splits the square at the start of `s`, a letter and one or two digits, from the rest
+fn split_square(s: &str) -> Option<(Position, &str)> {
+    let digits = s.get(1..)?.chars().take_while(|c| c.is_ascii_digit()).count();
+    let (square, rest) = s.split_at(1 + digits.min(2));
+    Some((Position::from_algebraic(square).ok()?, rest))
+}
@@ -25,6 +25,41 @@ pub use crate::game::*;
 pub mod my_reader;
 pub use crate::my_reader::*;
 
+pub mod engine;
+pub use crate::engine::*;
+
+pub mod uci_client;
+pub use crate::uci_client::*;
+
+pub mod clock;
+pub use crate::clock::*;
+
+pub mod tournament;
+pub use crate::tournament::*;
+
+pub mod book;
+pub use crate::book::*;
+
+pub mod syzygy;
+pub use crate::syzygy::*;
+
+pub mod endgame;
+pub use crate::endgame::*;
+
+pub mod variant;
+pub use crate::variant::*;
+
+pub mod fairy;
+pub use crate::fairy::*;
+
+pub mod board_builder;
+pub use crate::board_builder::*;
+
+pub mod bughouse;
+pub use crate::bughouse::*;
+
+mod polyglot_random;
+
 #[cfg(test)]
 mod tests {
     #[test]
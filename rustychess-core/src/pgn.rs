@@ -1,9 +1,15 @@
-use crate::file::File;
+use crate::clock::format_clock;
+use crate::error::Error;
+use crate::file::{File, MAX_FILES};
+use crate::game::Game;
 use crate::my_reader;
 use crate::pieces::Piece;
-use crate::rank::Rank;
+use crate::rank::{Rank, MAX_RANKS};
+use crate::variant::{variant_by_name, Rules, Standard};
 use std::fmt;
+use std::io::{self, Write};
 use std::str::FromStr;
+use std::sync::Arc;
 
 quick_error! {
     #[derive(Debug)]
@@ -65,6 +71,45 @@ impl Position {
             rank: rank,
         }
     }
+
+    /// Parses a square in algebraic notation, eg. "e4"
+    pub fn from_algebraic(s: &str) -> Result<Position, Error> {
+        let mut chars = s.chars();
+        match chars.next() {
+            Some(r) if r.is_ascii_alphabetic() && (2..=3).contains(&s.len()) => {
+                let f = chars.as_str();
+                if !f.chars().all(|c| c.is_ascii_digit()) || f.starts_with('0') {
+                    return Err(Error::InvalidSquare);
+                }
+                Ok(Position {
+                    rank: Rank::from_str(&r.to_string()).map_err(|_| Error::InvalidSquare)?,
+                    file: File::from_str(f).map_err(|_| Error::InvalidSquare)?,
+                })
+            }
+            _ => Err(Error::InvalidSquare),
+        }
+    }
+
+    /// Returns the square in algebraic notation, eg. "e4"
+    pub fn algebraic(&self) -> String {
+        format!("{}{}", self.rank.to_str(), self.file.to_i8())
+    }
+
+    /// Returns the position moved `ranks` to the right and `files` up, or `None` if that
+    /// would leave the largest board. `Chessboard2::offset` keeps to the board's own size
+    pub fn offset(self, ranks: i8, files: i8) -> Option<Position> {
+        let rank = self.rank.to_index() as i8 + ranks;
+        let file = self.file.to_index() as i8 + files;
+        if rank < 0 || rank >= MAX_RANKS as i8 || file < 0 || file >= MAX_FILES as i8 {
+            return None;
+        }
+
+        Some(Position {
+            rank: Rank::from_index(rank as usize),
+            file: File::from_index(file as usize),
+        })
+    }
+
     pub fn diagonals_squares(self, limit: i8) -> Vec<Position> {
         let mut r = Vec::new();
         let mut positions = vec![self.clone(), self.clone(), self.clone(), self.clone()];
@@ -414,3 +459,300 @@ impl PGN {
         Ok(r)
     }
 }
+
+/// A move of a PGN game together with the comment following it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub san: String,
+    pub comment: Option<String>,
+}
+
+/// A game as read from or written to a PGN file
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        PgnGame::new()
+    }
+}
+
+impl PgnGame {
+    /// Creates a game with the seven tag roster filled with "?"
+    pub fn new() -> PgnGame {
+        let mut r = PgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        };
+        for tag in SEVEN_TAG_ROSTER.iter() {
+            r.set_tag(tag, if *tag == "Result" { "*" } else { "?" });
+        }
+        r
+    }
+
+    /// Records the moves of `game` in SAN, adding the SetUp and FEN tags if it did
+    /// not start from the starting position, and the Variant tag for other rules
+    pub fn from_game(game: &Game) -> PgnGame {
+        let mut r = PgnGame::new();
+        let start = game.starting_fen();
+        if game.is_chess960() {
+            r.set_tag("Variant", "Chess960");
+        } else if game.rules().name() != Standard.name() {
+            r.set_tag("Variant", game.rules().name());
+        }
+        if start != game.rules().starting_fen() || game.is_chess960() {
+            r.set_tag("SetUp", "1");
+            r.set_tag("FEN", &start);
+        }
+
+        if let Some(clock) = game.clock() {
+            r.set_tag("TimeControl", &clock.control().pgn());
+        }
+
+        let mut replay = game.clone();
+        while replay.rollback_move().is_some() {}
+        for (i, m) in game.moves().iter().enumerate() {
+            r.moves.push(PgnMove {
+                san: replay.san(m),
+                comment: game.clock_time(i).map(|t| format!("[%clk {}]", format_clock(t))),
+            });
+            replay.play(*m);
+        }
+
+        if let Some((result, _)) = game.outcome() {
+            r.set_result(result.pgn());
+        }
+        r
+    }
+
+    /// Replays the moves on the position given by the FEN tag or the starting
+    /// position of the variant named by the Variant tag
+    pub fn to_game(&self) -> Result<Game, Error> {
+        let rules = match self.tag("Variant") {
+            Some(name) => variant_by_name(name).ok_or_else(|| Error::UnknownVariant { name: name.to_string() })?,
+            None => Arc::new(Standard),
+        };
+        let mut game = Game::with_rules(rules);
+        if let Some(fen) = self.tag("FEN") {
+            game.set_fen(fen)?;
+        }
+        if self.is_chess960() {
+            game.set_chess960(true);
+        }
+
+        for m in self.moves.iter() {
+            let _move = game.san_move(&m.san)?;
+            game.play(_move);
+        }
+        Ok(game)
+    }
+
+    /// Returns whenever the Variant tag names Chess960 (also called Fischerandom)
+    pub fn is_chess960(&self) -> bool {
+        match self.tag("Variant") {
+            Some(v) => {
+                let v = v.to_lowercase().replace(' ', "");
+                v == "chess960" || v == "fischerandom" || v == "fischerrandom"
+            }
+            None => false,
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        self.set_tag("Result", result);
+    }
+
+    /// Parses all games of a PGN file, variations and NAGs are skipped
+    pub fn parse_all(text: &str) -> Vec<PgnGame> {
+        let mut games = Vec::new();
+        let mut game = PgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        };
+        let mut started = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '[' => {
+                    if !game.moves.is_empty() {
+                        games.push(game);
+                        game = PgnGame {
+                            tags: Vec::new(),
+                            moves: Vec::new(),
+                            result: "*".to_string(),
+                        };
+                    }
+                    // brackets inside the quoted value belong to it, eg. Crazyhouse pockets in a FEN
+                    let mut tag = String::new();
+                    let mut quoted = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            '"' => quoted = !quoted,
+                            ']' if !quoted => break,
+                            _ => {}
+                        }
+                        tag.push(c);
+                    }
+                    let mut parts = tag.trim().splitn(2, char::is_whitespace);
+                    if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                        game.set_tag(name, value.trim().trim_matches('"'));
+                    }
+                    started = true;
+                }
+                '{' => {
+                    let comment: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    if let Some(m) = game.moves.last_mut() {
+                        let comment = comment.trim().to_string();
+                        m.comment = Some(match m.comment.take() {
+                            Some(c) => format!("{} {}", c, comment),
+                            None => comment,
+                        });
+                    }
+                }
+                ';' => {
+                    chars.by_ref().take_while(|c| *c != '\n').for_each(drop);
+                }
+                '(' => {
+                    let mut depth = 1;
+                    for c in chars.by_ref() {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut token = c.to_string();
+                    while let Some(next) = chars.peek() {
+                        if next.is_whitespace() || "{}()[];".contains(*next) {
+                            break;
+                        }
+                        token.push(*next);
+                        chars.next();
+                    }
+
+                    match token.as_str() {
+                        "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                            game.result = token.clone();
+                            if game.tag("Result").is_none() {
+                                game.set_tag("Result", &token);
+                            }
+                            games.push(game);
+                            game = PgnGame {
+                                tags: Vec::new(),
+                                moves: Vec::new(),
+                                result: "*".to_string(),
+                            };
+                            started = false;
+                        }
+                        t if t.starts_with('$') => {}
+                        t => {
+                            // strip move numbers such as "12." and "12..."
+                            let san = t.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                            if !san.is_empty() {
+                                game.moves.push(PgnMove {
+                                    san: san.to_string(),
+                                    comment: None,
+                                });
+                                started = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if started {
+            games.push(game);
+        }
+        games
+    }
+
+    /// Writes the game in export format, movetext lines are wrapped at 80 characters
+    pub fn write(&self, writer: &mut dyn Write) -> io::Result<()> {
+        write_tags(writer, &self.tags)?;
+
+        let black_starts = match self.tag("FEN") {
+            Some(fen) => fen.split_whitespace().nth(1) == Some("b"),
+            None => false,
+        };
+        let first_number = match self.tag("FEN") {
+            Some(fen) => fen
+                .split_whitespace()
+                .nth(5)
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(1),
+            None => 1,
+        };
+
+        let mut tokens = Vec::new();
+        for (i, m) in self.moves.iter().enumerate() {
+            let ply = i + black_starts as usize;
+            if ply.is_multiple_of(2) {
+                tokens.push(format!("{}.", first_number + ply / 2));
+            } else if i == 0 {
+                tokens.push(format!("{}...", first_number + ply / 2));
+            }
+            tokens.push(m.san.clone());
+            if let Some(comment) = &m.comment {
+                tokens.push(format!("{{{}}}", comment));
+            }
+        }
+        tokens.push(self.result.clone());
+        write_movetext(writer, &tokens)
+    }
+}
+
+/// Writes the tag pairs followed by an empty line
+pub(crate) fn write_tags(writer: &mut dyn Write, tags: &[(String, String)]) -> io::Result<()> {
+    for (name, value) in tags.iter() {
+        writeln!(writer, "[{} \"{}\"]", name, value.replace('\\', "\\\\").replace('"', "\\\""))?;
+    }
+    writeln!(writer)
+}
+
+/// Writes movetext tokens separated by spaces in lines of at most 80 characters
+pub(crate) fn write_movetext(writer: &mut dyn Write, tokens: &[String]) -> io::Result<()> {
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.len() + 1 > 80 {
+            writeln!(writer, "{}", line)?;
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(token);
+    }
+    writeln!(writer, "{}", line)?;
+    writeln!(writer)
+}
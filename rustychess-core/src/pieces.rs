@@ -1,4 +1,5 @@
 use crate::error::Error;
+use crate::fairy::{fairy_kind, fairy_piece};
 use crate::file::File;
 use crate::file::ALL_FILES;
 use crate::pgn::Position;
@@ -21,6 +22,8 @@ pub enum Kind {
     Rook,
     Queen,
     King,
+    /// A piece registered in `fairy`, numbered in the order of registration
+    Fairy(u8),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -49,6 +52,7 @@ impl<'a> Kind {
             Kind::Rook => "R",
             Kind::Queen => "Q",
             Kind::King => "K",
+            Kind::Fairy(_) => fairy_piece(*self).map_or("?", |p| p.pgn()),
         }
     }
 
@@ -96,13 +100,17 @@ impl<'a> Kind {
                     "♚"
                 }
             }
+            Kind::Fairy(_) => fairy_piece(*self).map_or("?", |p| p.symbol(c)),
         }
     }
 }
 
 impl fmt::Display for Kind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match fairy_piece(*self) {
+            Some(p) => write!(f, "{}", p.name),
+            None => write!(f, "{:?}", self),
+        }
         // or, alternatively:
         // fmt::Debug::fmt(self, f)
     }
@@ -113,11 +121,33 @@ pub struct Piece {
     pub kind: Kind,
     pub color: Color,
     pub number_of_moves: i8,
+    /// Whenever the piece is a promoted pawn, it goes back to the pocket as a pawn in Crazyhouse
+    pub promoted: bool,
 }
 
 impl Piece {
     pub fn new(k: Kind, c: Color) -> Box<Piece> {
-        Box::new(Piece { kind: k, color: c, number_of_moves: 0 })
+        Box::new(Piece { kind: k, color: c, number_of_moves: 0, promoted: false })
+    }
+
+    /// Returns the FEN letter of the piece, uppercase for white and lowercase for black
+    pub fn fen(&self) -> String {
+        if self.color == Color::Black {
+            self.kind.pgn().to_lowercase()
+        } else {
+            self.kind.pgn().to_string()
+        }
+    }
+
+    /// Creates a piece from a FEN letter, eg. 'N' for a white knight and 'n' for a black knight
+    pub fn from_fen(c: char) -> Result<Piece, Error> {
+        let mut piece = Piece::from_str(&c.to_ascii_uppercase().to_string())?;
+        piece.color = if c.is_ascii_uppercase() {
+            Color::White
+        } else {
+            Color::Black
+        };
+        Ok(piece)
     }
 }
 
@@ -143,7 +173,10 @@ impl FromStr for Piece {
             'R' => Ok(*Piece::new(Kind::Rook, Color::Unknown)),
             'Q' => Ok(*Piece::new(Kind::Queen, Color::Unknown)),
             'K' => Ok(*Piece::new(Kind::King, Color::Unknown)),
-            _ => Err(Error::InvalidPiece),
+            c => match fairy_kind(c) {
+                Some(kind) if c.is_ascii_uppercase() => Ok(*Piece::new(kind, Color::Unknown)),
+                _ => Err(Error::InvalidPiece),
+            },
         }
     }
 }
@@ -365,6 +398,25 @@ impl PieceMovements for Piece {
             Kind::King => {
                 r.append(&mut position.squares_around(1));
             }
+            Kind::Fairy(_) => {
+                let fairy = match fairy_piece(self.kind) {
+                    Some(v) => v,
+                    None => return r,
+                };
+                for step in fairy.steps(self.color) {
+                    let range = if step.range == 0 { 7 } else { step.range as i8 };
+                    let mut current = position;
+                    for _ in 0..range {
+                        match current.offset(step.ranks, step.files) {
+                            Some(p) => {
+                                r.push(p);
+                                current = p;
+                            }
+                            None => break,
+                        }
+                    }
+                }
+            }
         }
         r
     }
@@ -14,12 +14,23 @@ pub enum Rank {
     F,
     G,
     H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
 }
 
-/// How many ranks are there?
+/// How many ranks are there on the standard board?
 pub const NUM_RANKS: usize = 8;
 
-/// Enumerate all ranks
+/// Ranks on the widest board
+pub const MAX_RANKS: usize = 16;
+
+/// Enumerate the ranks of the standard board
 pub const ALL_RANKS: [Rank; NUM_RANKS] = [
     Rank::A,
     Rank::B,
@@ -32,25 +43,25 @@ pub const ALL_RANKS: [Rank; NUM_RANKS] = [
 ];
 
 impl Rank {
-    /// Convert a `usize` into a `Rank` (the inverse of to_index).  If i > 7, wrap around.
+    /// Convert a `usize` into a `Rank` (the inverse of to_index).  If i > 15, wrap around.
     #[inline]
     pub fn from_index(i: usize) -> Rank {
-        unsafe { transmute((i as u8) & 7) }
+        unsafe { transmute((i as u8) & 15) }
     }
 
-    /// Go one rank to the left.  If impossible, wrap around.
+    /// Go one rank to the left.  If impossible, stay.
     #[inline]
     pub fn left(&self) -> Rank {
-        Rank::from_index(self.to_index().wrapping_sub(1))
+        Rank::from_index(self.to_index().saturating_sub(1))
     }
 
-    /// Go one rank to the right.  If impossible, wrap around.
+    /// Go one rank to the right.  If impossible, stay.
     #[inline]
     pub fn right(&self) -> Rank {
-        Rank::from_index(self.to_index() + 1)
+        Rank::from_index((self.to_index() + 1).min(MAX_RANKS - 1))
     }
 
-    /// Convert this `Rank` into a `usize` from 0 to 7 inclusive.
+    /// Convert this `Rank` into a `usize` from 0 to 15 inclusive.
     #[inline]
     pub fn to_index(&self) -> usize {
         *self as usize
@@ -67,6 +78,14 @@ impl Rank {
             Rank::F => "f",
             Rank::G => "g",
             Rank::H => "h",
+            Rank::I => "i",
+            Rank::J => "j",
+            Rank::K => "k",
+            Rank::L => "l",
+            Rank::M => "m",
+            Rank::N => "n",
+            Rank::O => "o",
+            Rank::P => "p",
         }
     }
 
@@ -101,14 +120,7 @@ impl FromStr for Rank {
             return Err(Error::InvalidRank);
         }
         match s.to_lowercase().chars().next().unwrap() {
-            'a' => Ok(Rank::A),
-            'b' => Ok(Rank::B),
-            'c' => Ok(Rank::C),
-            'd' => Ok(Rank::D),
-            'e' => Ok(Rank::E),
-            'f' => Ok(Rank::F),
-            'g' => Ok(Rank::G),
-            'h' => Ok(Rank::H),
+            c @ 'a'..='p' => Ok(Rank::from_index((c as u8 - b'a') as usize)),
             _ => Err(Error::InvalidRank),
         }
     }
@@ -0,0 +1,1221 @@
+use crate::chessboard::{CastlingRights, Chessboard2};
+use crate::game::{Game, Move};
+use crate::pgn::Position;
+use crate::pieces::{Color, Kind, Piece};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+/// Most pieces, kings included, a Syzygy table can hold
+pub const MAX_PIECES: usize = 7;
+
+// flags stored in front of the compressed data of each subtable
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// Result of a position with best play, from the point of view of the side to move.
+/// Cursed wins and blessed losses are draws by the fifty move rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_i32(v: i32) -> Wdl {
+        match v {
+            i32::MIN..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn signum(self) -> i32 {
+        (self as i32).signum()
+    }
+}
+
+impl std::ops::Neg for Wdl {
+    type Output = Wdl;
+
+    fn neg(self) -> Wdl {
+        Wdl::from_i32(-(self as i32))
+    }
+}
+
+impl fmt::Display for Wdl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Wdl::Loss => "loss",
+            Wdl::BlessedLoss => "blessed loss",
+            Wdl::Draw => "draw",
+            Wdl::CursedWin => "cursed win",
+            Wdl::Win => "win",
+        };
+        f.pad(s)
+    }
+}
+
+/// A legal move rated by the tablebases
+#[derive(Debug, Clone)]
+pub struct TbMove {
+    pub m: Move,
+    /// Result for the side playing the move, the fifty move rule counted
+    pub wdl: Wdl,
+    /// Plies to the next capture or pawn move of the line, from before the move.
+    /// Positive when winning, `None` without the DTZ table
+    pub dtz: Option<i32>,
+}
+
+/// Tables read so far by name and DTZ flag, `None` for unreadable files
+type TableCache = HashMap<(String, bool), Option<Arc<Table>>>;
+
+/// A set of Syzygy tablebase files, tables are read from disk the first time they are probed.
+///
+/// Positions where castling is still allowed are not part of the tables
+pub struct Tablebase {
+    wdl_files: HashMap<String, PathBuf>,
+    dtz_files: HashMap<String, PathBuf>,
+    tables: Mutex<TableCache>,
+    max_pieces: usize,
+}
+
+impl Default for Tablebase {
+    fn default() -> Self {
+        Tablebase::new()
+    }
+}
+
+impl fmt::Debug for Tablebase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tablebase")
+            .field("wdl", &self.wdl_files.len())
+            .field("dtz", &self.dtz_files.len())
+            .field("max_pieces", &self.max_pieces)
+            .finish()
+    }
+}
+
+impl Tablebase {
+    pub fn new() -> Tablebase {
+        Tablebase {
+            wdl_files: HashMap::new(),
+            dtz_files: HashMap::new(),
+            tables: Mutex::new(HashMap::new()),
+            max_pieces: 0,
+        }
+    }
+
+    /// Opens the directories of a path list, separated like the PATH variable
+    /// (':' or ';' on Windows) as in the SyzygyPath option of UCI engines
+    pub fn open(paths: &str) -> io::Result<Tablebase> {
+        let mut tb = Tablebase::new();
+        for dir in std::env::split_paths(paths).filter(|p| !p.as_os_str().is_empty()) {
+            tb.add_directory(dir)?;
+        }
+        Ok(tb)
+    }
+
+    /// Adds the .rtbw and .rtbz files of a directory and returns how many were found,
+    /// files with a wrong header are an error
+    pub fn add_directory<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<usize> {
+        let mut found = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let (stem, ext) = match (path.file_stem(), path.extension()) {
+                (Some(stem), Some(ext)) => (stem.to_string_lossy().into_owned(), ext.to_string_lossy().into_owned()),
+                _ => continue,
+            };
+            let dtz = match ext.as_str() {
+                "rtbw" => false,
+                "rtbz" => true,
+                _ => continue,
+            };
+            let pieces = match material_pieces(&stem) {
+                Some(n) => n,
+                None => continue,
+            };
+
+            let mut magic = [0; 4];
+            fs::File::open(&path)?.read_exact(&mut magic)?;
+            if magic != if dtz { DTZ_MAGIC } else { WDL_MAGIC } {
+                return Err(invalid(&format!("{}: not a Syzygy table", path.display())));
+            }
+
+            self.max_pieces = self.max_pieces.max(pieces);
+            if dtz {
+                self.dtz_files.insert(stem, path);
+            } else {
+                self.wdl_files.insert(stem, path);
+            }
+            found += 1;
+        }
+        Ok(found)
+    }
+
+    /// Most pieces of the available tables, 0 without any
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Number of WDL and DTZ files
+    pub fn len(&self) -> usize {
+        self.wdl_files.len() + self.dtz_files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the win/draw/loss of a position without castling rights.
+    /// `None` when it is not covered by the available tables
+    pub fn probe_wdl(&self, board: &Chessboard2, turn: Color, en_passant: Option<Position>) -> Option<Wdl> {
+        let mut pos = TbPosition::new(board, turn, en_passant);
+        if pos.count() > self.max_pieces.max(2) {
+            return None;
+        }
+        self.search(&mut pos, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Returns the distance to zeroing of a position without castling rights: the plies
+    /// to the next capture or pawn move with best play, negative when losing and beyond
+    /// 100 for cursed wins and blessed losses. 0 for draws.
+    ///
+    /// Tables may store moves instead of plies, values can then be one ply too high
+    pub fn probe_dtz(&self, board: &Chessboard2, turn: Color, en_passant: Option<Position>) -> Option<i32> {
+        let mut pos = TbPosition::new(board, turn, en_passant);
+        if pos.count() > self.max_pieces.max(2) {
+            return None;
+        }
+        self.dtz(&mut pos)
+    }
+
+    /// Rates all legal moves of a game, the best first.
+    /// `None` when the position is not in the tables
+    pub fn probe_moves(&self, game: &Game) -> Option<Vec<TbMove>> {
+        if game.castling().any() || game.board.pockets().is_some() {
+            return None;
+        }
+        let mut pos = TbPosition::new(&game.board, game.turn(), game.en_passant());
+        if pos.count() > self.max_pieces.max(2) {
+            return None;
+        }
+
+        // without DTZ tables the moves are only told apart by their result
+        let with_dtz = self.dtz(&mut pos.clone()).is_some();
+
+        let mut r = Vec::new();
+        for m in pos.moves() {
+            let previous = pos.make(&m);
+            let rated = self.rate_move(&mut pos, &m, with_dtz);
+            pos.unmake(&m, previous);
+
+            let (wdl, dtz) = rated?;
+            r.push(TbMove { m, wdl, dtz });
+        }
+
+        let halfmove_clock = game.halfmove_clock() as i32;
+        for t in r.iter_mut() {
+            t.wdl = match t.dtz {
+                Some(dtz) if dtz > 0 && dtz + halfmove_clock > 100 => Wdl::CursedWin,
+                Some(dtz) if dtz < 0 && -dtz + halfmove_clock > 100 => Wdl::BlessedLoss,
+                _ => t.wdl,
+            };
+        }
+        r.sort_by_key(|t| Reverse((t.wdl, dtz_rank(t.dtz))));
+        Some(r)
+    }
+
+    /// Result and DTZ from before the move, `pos` is the position after it
+    fn rate_move(&self, pos: &mut TbPosition, m: &Move, with_dtz: bool) -> Option<(Wdl, Option<i32>)> {
+        let wdl = -self.search(pos, false)?.0;
+        if !with_dtz {
+            return Some((wdl, None));
+        }
+
+        let mut dtz = if is_zeroing(m) {
+            dtz_before_zeroing(wdl)
+        } else {
+            let dtz = -self.dtz(pos)?;
+            dtz + dtz.signum()
+        };
+        // a mate is one ply from zeroing
+        if dtz == 2 && pos.is_check() && pos.moves().is_empty() {
+            dtz = 1;
+        }
+        Some((wdl, Some(dtz)))
+    }
+
+    /// Probes the WDL table and the captures (and pawn moves with `zeroing`) of a position,
+    /// the tables may store any value where a capture is at least as good.
+    /// Also returns whenever the best move is one of those searched
+    fn search(&self, pos: &mut TbPosition, zeroing: bool) -> Option<(Wdl, bool)> {
+        let moves = pos.moves();
+        let total = moves.len();
+        let mut searched = 0;
+        let mut best = Wdl::Loss;
+
+        for m in moves.iter() {
+            if m.capture.is_none() && (!zeroing || m.piece.kind != Kind::Pawn) {
+                continue;
+            }
+            searched += 1;
+
+            let previous = pos.make(m);
+            let value = self.search(pos, false);
+            pos.unmake(m, previous);
+            let value = -value?.0;
+
+            if value > best {
+                best = value;
+                if value == Wdl::Win {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // the table does not know about en passant, it is not needed when all moves were searched
+        let all_searched = searched > 0 && searched == total;
+        let value = if all_searched {
+            best
+        } else {
+            match self.probe_table(pos, None)? {
+                Probed::Value(v) => Wdl::from_i32(v),
+                Probed::OtherSide => return None,
+            }
+        };
+
+        if best >= value {
+            return Some((best, best > Wdl::Draw || all_searched));
+        }
+        Some((value, false))
+    }
+
+    fn dtz(&self, pos: &mut TbPosition) -> Option<i32> {
+        let (wdl, zeroing_best) = self.search(pos, true)?;
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+        if zeroing_best {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        if let Probed::Value(dtz) = self.probe_table(pos, Some(wdl))? {
+            let cursed = if wdl == Wdl::CursedWin || wdl == Wdl::BlessedLoss { 100 } else { 0 };
+            return Some((dtz + cursed) * wdl.signum());
+        }
+
+        // the table only stores the other side to move, look one ply ahead
+        let mut min_dtz = i32::MAX;
+        for m in pos.moves() {
+            let previous = pos.make(&m);
+            let dtz = if is_zeroing(&m) {
+                self.search(pos, false).map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.dtz(pos).map(|dtz| -dtz)
+            };
+            let mate = dtz == Some(1) && pos.is_check() && pos.moves().is_empty();
+            pos.unmake(&m, previous);
+
+            let mut dtz = dtz?;
+            if mate {
+                min_dtz = 1;
+            }
+            if !is_zeroing(&m) {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Looks up the position in the WDL table, or the DTZ table given the result
+    fn probe_table(&self, pos: &TbPosition, wdl: Option<Wdl>) -> Option<Probed> {
+        if pos.count() == 2 {
+            return Some(Probed::Value(0));
+        }
+
+        let dtz = wdl.is_some();
+        let (white, black) = pos.material();
+        let (table, white_first) = match self.table(&format!("{}v{}", white, black), dtz) {
+            Some(t) => (t, true),
+            None => (self.table(&format!("{}v{}", black, white), dtz)?, false),
+        };
+        table.probe(pos, white_first, wdl.unwrap_or(Wdl::Draw))
+    }
+
+    fn table(&self, name: &str, dtz: bool) -> Option<Arc<Table>> {
+        let files = if dtz { &self.dtz_files } else { &self.wdl_files };
+        let path = files.get(name)?;
+
+        let mut tables = self.tables.lock().unwrap();
+        tables
+            .entry((name.to_string(), dtz))
+            .or_insert_with(|| {
+                // a broken file is reported as missing
+                fs::read(path)
+                    .and_then(|bytes| Table::new(name, bytes, dtz))
+                    .ok()
+                    .map(Arc::new)
+            })
+            .clone()
+    }
+}
+
+/// A value read from a table
+enum Probed {
+    Value(i32),
+    /// The DTZ table only stores the other side to move
+    OtherSide,
+}
+
+fn is_zeroing(m: &Move) -> bool {
+    m.capture.is_some() || m.piece.kind == Kind::Pawn
+}
+
+/// DTZ of a position whose best move is a capture or pawn move
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+        Wdl::Draw => 0,
+    }
+}
+
+/// Orders moves of the same result: quick wins and slow losses first
+fn dtz_rank(dtz: Option<i32>) -> i32 {
+    match dtz {
+        Some(dtz) if dtz > 0 => 1000 - dtz,
+        Some(dtz) if dtz < 0 => -1000 - dtz,
+        _ => 0,
+    }
+}
+
+/// Returns the number of pieces of a table name such as "KRPvKR"
+fn material_pieces(name: &str) -> Option<usize> {
+    let (white, black) = name.split_once('v')?;
+    let valid = |side: &str| side.starts_with('K') && side[1..].chars().all(|c| "QRBNP".contains(c));
+    if !valid(white) || !valid(black) || name.len() - 1 > MAX_PIECES {
+        return None;
+    }
+    Some(name.len() - 1)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// The position being probed, kept apart from `Game` to make and unmake moves cheaply
+#[derive(Clone)]
+struct TbPosition {
+    board: Chessboard2,
+    turn: Color,
+    en_passant: Option<Position>,
+}
+
+impl TbPosition {
+    fn new(board: &Chessboard2, turn: Color, en_passant: Option<Position>) -> TbPosition {
+        TbPosition {
+            board: board.clone(),
+            turn,
+            en_passant,
+        }
+    }
+
+    /// Number of pieces, more than any table holds when fairy pieces are on the board
+    fn count(&self) -> usize {
+        if !self.board.is_standard_size() || self.board.pieces().any(|(_, p)| matches!(p.kind, Kind::Fairy(_))) {
+            return usize::MAX;
+        }
+        self.board.pieces().count()
+    }
+
+    fn moves(&self) -> Vec<Move> {
+        self.board.legal_moves(self.turn, CastlingRights::none(), self.en_passant)
+    }
+
+    fn is_check(&self) -> bool {
+        match self.board.find_piece(Kind::King, self.turn) {
+            Ok((pos, _)) => self.board.is_square_attacked(&pos, self.turn.switch()),
+            Err(_) => false,
+        }
+    }
+
+    /// Plays a move and returns the previous en passant square
+    fn make(&mut self, m: &Move) -> Option<Position> {
+        let previous = self.en_passant;
+        self.board.make(m);
+        self.en_passant = None;
+        if m.piece.kind == Kind::Pawn && m.from.file.sub(m.to.file) == 2 {
+            self.en_passant = m.from.offset(0, if m.piece.color == Color::White { 1 } else { -1 });
+        }
+        self.turn = self.turn.switch();
+        previous
+    }
+
+    fn unmake(&mut self, m: &Move, en_passant: Option<Position>) {
+        self.board.unmake(m);
+        self.en_passant = en_passant;
+        self.turn = self.turn.switch();
+    }
+
+    /// Pieces of white and black as written in table names, eg. ("KRP", "KR")
+    fn material(&self) -> (String, String) {
+        let side = |color: Color| {
+            let mut r = String::new();
+            for kind in [Kind::King, Kind::Queen, Kind::Rook, Kind::Bishop, Kind::Knight, Kind::Pawn].iter() {
+                let n = self.board.pieces().filter(|(_, p)| p.kind == *kind && p.color == color).count();
+                r.push_str(&kind.pgn().repeat(n));
+            }
+            r
+        };
+        (side(Color::White), side(Color::Black))
+    }
+
+    /// Squares numbered a1 = 0, b1 = 1 .. h8 = 63 with the table piece codes
+    fn squares(&self) -> Vec<(usize, u8)> {
+        let mut r: Vec<(usize, u8)> = self
+            .board
+            .pieces()
+            .map(|(pos, p)| (8 * pos.file.to_index() + pos.rank.to_index(), piece_code(p)))
+            .collect();
+        r.sort_unstable();
+        r
+    }
+}
+
+/// Piece encoding of the table files, black pieces have the 8 bit set
+fn piece_code(piece: &Piece) -> u8 {
+    let kind = match piece.kind {
+        Kind::Pawn => 1,
+        Kind::Knight => 2,
+        Kind::Bishop => 3,
+        Kind::Rook => 4,
+        Kind::Queen => 5,
+        Kind::King => 6,
+        Kind::Fairy(_) => 0,
+    };
+    if piece.color == Color::Black {
+        kind | 8
+    } else {
+        kind
+    }
+}
+
+fn rank_of(sq: usize) -> usize {
+    sq >> 3
+}
+
+fn file_of(sq: usize) -> usize {
+    sq & 7
+}
+
+/// Distance above the a1-h8 diagonal, negative below it
+fn off_diagonal(sq: usize) -> i32 {
+    rank_of(sq) as i32 - file_of(sq) as i32
+}
+
+/// Lookup tables of the position index encoding
+struct Indices {
+    /// Pawn squares a2-h7 numbered so that the leading pawn has the highest value
+    map_pawns: [usize; 64],
+    /// The b1-h1-h7 triangle below the diagonal as 0..27
+    map_b1h1h7: [usize; 64],
+    /// The a1-d1-d4 triangle as 0..9, the diagonal squares last
+    map_a1d1d4: [usize; 64],
+    /// The 462 placements of two kings with the first one in the a1-d1-d4 triangle
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; MAX_PIECES],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+fn indices() -> &'static Indices {
+    static INDICES: OnceLock<Indices> = OnceLock::new();
+    INDICES.get_or_init(Indices::new)
+}
+
+impl Indices {
+    fn new() -> Indices {
+        let mut t = Indices {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_diagonal(sq) < 0 {
+                t.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        let d4 = 27;
+        let mut diagonal = Vec::new();
+        code = 0;
+        for sq in 0..=d4 {
+            if off_diagonal(sq) < 0 && file_of(sq) <= 3 {
+                t.map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_diagonal(sq) == 0 && file_of(sq) <= 3 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            t.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        let b1 = 1;
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..=d4 {
+                if t.map_a1d1d4[s1] != idx || (idx == 0 && s1 != b1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    let distance = (rank_of(s1) as i32 - rank_of(s2) as i32)
+                        .abs()
+                        .max((file_of(s1) as i32 - file_of(s2) as i32).abs());
+                    if distance <= 1 || (off_diagonal(s1) == 0 && off_diagonal(s2) > 0) {
+                        continue;
+                    }
+                    if off_diagonal(s1) == 0 && off_diagonal(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        t.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            t.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        t.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                let with = if k > 0 { t.binomial[k - 1][n - 1] } else { 0 };
+                let without = if k < n { t.binomial[k][n - 1] } else { 0 };
+                t.binomial[k][n] = with + without;
+            }
+        }
+
+        let mut available = 47;
+        for lead in 1..=5 {
+            for file in 0..4 {
+                let mut idx = 0;
+                for rank in 1..=6 {
+                    let sq = rank * 8 + file;
+                    if lead == 1 {
+                        t.map_pawns[sq] = available;
+                        t.map_pawns[sq ^ 7] = available.saturating_sub(1);
+                        available = available.saturating_sub(2);
+                    }
+                    t.lead_pawn_idx[lead][sq] = idx;
+                    idx += t.binomial[lead - 1][t.map_pawns[sq]];
+                }
+                t.lead_pawns_size[lead][file] = idx;
+            }
+        }
+
+        t
+    }
+}
+
+/// Decoding state of one compressed subtable: a side to move, and a leading pawn
+/// file for tables with pawns
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    pieces: [u8; MAX_PIECES],
+    group_len: [usize; MAX_PIECES + 1],
+    group_idx: [u64; MAX_PIECES + 1],
+    block_size: u64,
+    span: u64,
+    sparse_index_size: u64,
+    num_blocks: u64,
+    block_length_size: u64,
+    /// Shortest Huffman code, or the value of a single value table
+    min_sym_len: u8,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    // offsets into the file
+    lowest_sym: usize,
+    btree: usize,
+    sparse_index: usize,
+    block_length: usize,
+    data: usize,
+    map_idx: [usize; 4],
+}
+
+/// A WDL or DTZ file read into memory
+struct Table {
+    bytes: Vec<u8>,
+    dtz: bool,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    /// Pawns of the leading color and of the other one
+    pawn_count: [usize; 2],
+    symmetric: bool,
+    /// Subtables by side to move and leading pawn file, DTZ and symmetric tables have one side
+    items: Vec<Vec<PairsData>>,
+    map: usize,
+}
+
+impl Table {
+    fn new(name: &str, bytes: Vec<u8>, dtz: bool) -> io::Result<Table> {
+        let (white, black) = name.split_once('v').ok_or_else(|| invalid(name))?;
+        let count = |side: &str, c: char| side.chars().filter(|x| *x == c).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let unique = |side: &str| "QRBNP".chars().any(|c| count(side, c) == 1);
+        // the side with fewer pawns leads, it compresses better
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        let mut t = Table {
+            bytes,
+            dtz,
+            piece_count: white.len() + black.len(),
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: unique(white) || unique(black),
+            pawn_count: if white_leads {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            symmetric: white == black,
+            items: Vec::new(),
+            map: 0,
+        };
+
+        let magic = if dtz { DTZ_MAGIC } else { WDL_MAGIC };
+        if t.bytes.len() < 5 || t.bytes[..4] != magic {
+            return Err(invalid(&format!("{}: not a Syzygy table", name)));
+        }
+        if (t.bytes[4] & 2 != 0) != t.has_pawns {
+            return Err(invalid(&format!("{}: the header does not match the name", name)));
+        }
+        t.init();
+
+        let end = t
+            .items
+            .iter()
+            .flatten()
+            .filter(|d| d.num_blocks > 0)
+            .map(|d| d.data as u64 + d.num_blocks * d.block_size)
+            .max();
+        if end.unwrap_or(0) > t.bytes.len() as u64 {
+            return Err(invalid(&format!("{}: truncated file", name)));
+        }
+        Ok(t)
+    }
+
+    fn init(&mut self) {
+        let sides = if !self.dtz && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+
+        self.items = vec![vec![PairsData::default(); files]; sides];
+        let mut at = 5;
+        for f in 0..files {
+            let second = if both_pawns { self.u8_at(at + 1) } else { 0xff };
+            let order = [
+                [(self.u8_at(at) & 0xf) as usize, (second & 0xf) as usize],
+                [(self.u8_at(at) >> 4) as usize, (second >> 4) as usize],
+            ];
+            at += 1 + both_pawns as usize;
+
+            for k in 0..self.piece_count {
+                let b = self.u8_at(at);
+                for i in 0..sides {
+                    self.items[i][f].pieces[k] = if i == 1 { b >> 4 } else { b & 0xf };
+                }
+                at += 1;
+            }
+            for (i, order) in order.iter().enumerate().take(sides) {
+                let mut d = std::mem::take(&mut self.items[i][f]);
+                self.set_groups(&mut d, order, f);
+                self.items[i][f] = d;
+            }
+        }
+        at += at & 1;
+
+        for f in 0..files {
+            for i in 0..sides {
+                let mut d = std::mem::take(&mut self.items[i][f]);
+                at = self.set_sizes(&mut d, at);
+                self.items[i][f] = d;
+            }
+        }
+
+        if self.dtz {
+            self.map = at;
+            for f in 0..files {
+                let flags = self.items[0][f].flags;
+                if flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+                for i in 0..4 {
+                    if flags & FLAG_WIDE != 0 {
+                        at += at & 1;
+                        self.items[0][f].map_idx[i] = (at - self.map) / 2 + 1;
+                        at += 2 * self.u16_le(at) as usize + 2;
+                    } else {
+                        self.items[0][f].map_idx[i] = at - self.map + 1;
+                        at += self.u8_at(at) as usize + 1;
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for f in 0..files {
+            for i in 0..sides {
+                self.items[i][f].sparse_index = at;
+                at += self.items[i][f].sparse_index_size as usize * 6;
+            }
+        }
+        for f in 0..files {
+            for i in 0..sides {
+                self.items[i][f].block_length = at;
+                at += self.items[i][f].block_length_size as usize * 2;
+            }
+        }
+        for f in 0..files {
+            for i in 0..sides {
+                at = (at + 0x3f) & !0x3f;
+                self.items[i][f].data = at;
+                at += (self.items[i][f].num_blocks * self.items[i][f].block_size) as usize;
+            }
+        }
+    }
+
+    /// Splits the pieces into groups of the same kind and computes the factor of each
+    /// group in the index, the groups are weighted in the order the file asks for
+    fn set_groups(&self, d: &mut PairsData, order: &[usize; 2], file: usize) {
+        let t = indices();
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len[0] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] != d.pieces[i - 1] {
+                n += 1;
+                d.group_len[n] = 1;
+            } else {
+                d.group_len[n] += 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        let both_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if both_pawns { 2 } else { 1 };
+        let mut free = 64 - d.group_len[0] - if both_pawns { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+
+        let mut k = 0;
+        while (next < n || k == order[0] || k == order[1]) && k < 16 {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    t.lead_pawns_size[d.group_len[0].min(5)][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= t.binomial[d.group_len[1].min(MAX_PIECES - 1)][48 - d.group_len[0]];
+            } else if next < n {
+                d.group_idx[next] = idx;
+                idx *= t.binomial[d.group_len[next].min(MAX_PIECES - 1)][free];
+                free = free.saturating_sub(d.group_len[next]);
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    /// Reads the block layout and the Huffman code of a subtable, returns the offset after it
+    fn set_sizes(&self, d: &mut PairsData, mut at: usize) -> usize {
+        d.flags = self.u8_at(at);
+        at += 1;
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            d.min_sym_len = self.u8_at(at);
+            return at + 1;
+        }
+
+        let end = d.group_len.iter().position(|len| *len == 0).unwrap_or(MAX_PIECES);
+        let size = d.group_idx[end];
+
+        d.block_size = 1u64.checked_shl(self.u8_at(at) as u32).unwrap_or(0);
+        d.span = 1u64.checked_shl(self.u8_at(at + 1) as u32).unwrap_or(0).max(1);
+        d.sparse_index_size = size.div_ceil(d.span);
+        let padding = self.u8_at(at + 2) as u64;
+        d.num_blocks = self.u32_le(at + 3) as u64;
+        d.block_length_size = d.num_blocks + padding;
+        let max_sym_len = self.u8_at(at + 7);
+        d.min_sym_len = self.u8_at(at + 8);
+        at += 9;
+        d.lowest_sym = at;
+
+        // canonical Huffman codes: longer codes have lower values, base64[len] is the
+        // lowest code of each length left aligned in 64 bits
+        let lengths = max_sym_len.saturating_sub(d.min_sym_len) as usize + 1;
+        d.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev() {
+            let lowest = self.u16_le(d.lowest_sym + 2 * i) as u64;
+            let next = self.u16_le(d.lowest_sym + 2 * (i + 1)) as u64;
+            d.base64[i] = (d.base64[i + 1] + lowest).wrapping_sub(next) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            let shift = 64u32.saturating_sub(i as u32 + d.min_sym_len as u32);
+            *base = base.checked_shl(shift).unwrap_or(0);
+        }
+        at += lengths * 2;
+
+        let symbols = self.u16_le(at) as usize;
+        at += 2;
+        d.btree = at;
+        d.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                d.symlen[sym] = self.set_symlen(d, sym, &mut visited);
+            }
+        }
+        at + symbols * 3 + (symbols & 1)
+    }
+
+    /// Number of values a symbol expands to, minus one
+    fn set_symlen(&self, d: &mut PairsData, sym: usize, visited: &mut [bool]) -> u8 {
+        visited[sym] = true;
+        let right = self.right(d, sym);
+        if right == 0xfff {
+            return 0;
+        }
+        let left = self.left(d, sym);
+        if left >= visited.len() || right >= visited.len() {
+            return 0;
+        }
+        if !visited[left] {
+            d.symlen[left] = self.set_symlen(d, left, visited);
+        }
+        if !visited[right] {
+            d.symlen[right] = self.set_symlen(d, right, visited);
+        }
+        d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1)
+    }
+
+    fn left(&self, d: &PairsData, sym: usize) -> usize {
+        let at = d.btree + 3 * sym;
+        ((self.u8_at(at + 1) as usize & 0xf) << 8) | self.u8_at(at) as usize
+    }
+
+    fn right(&self, d: &PairsData, sym: usize) -> usize {
+        let at = d.btree + 3 * sym;
+        ((self.u8_at(at + 2) as usize) << 4) | (self.u8_at(at + 1) as usize >> 4)
+    }
+
+    /// Returns the value stored at `idx` of a subtable
+    fn decompress(&self, d: &PairsData, idx: u64) -> i32 {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return d.min_sym_len as i32;
+        }
+        let symlen = |sym: usize| d.symlen.get(sym).copied().unwrap_or(0) as i64;
+        let block_length = |block: u32| self.u16_le(d.block_length + 2 * block as usize) as i64;
+
+        // the sparse index points near the block holding the value, walk from there
+        let k = (idx / d.span) as usize;
+        let mut block = self.u32_le(d.sparse_index + 6 * k);
+        let mut offset = self.u16_le(d.sparse_index + 6 * k + 4) as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+        while offset < 0 {
+            block = block.wrapping_sub(1);
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block = block.wrapping_add(1);
+        }
+
+        let mut at = d.data + (block as u64 * d.block_size) as usize;
+        let mut buf = self.u64_be(at);
+        at += 8;
+        let mut buf_size = 64;
+        let min = d.min_sym_len as u32;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf < d.base64[len] {
+                len += 1;
+            }
+            let shift = 64u32.saturating_sub(len as u32 + min);
+            sym = ((buf - d.base64[len]).checked_shr(shift).unwrap_or(0) as usize)
+                .wrapping_add(self.u16_le(d.lowest_sym + 2 * len) as usize);
+            if offset < symlen(sym) + 1 {
+                break;
+            }
+
+            offset -= symlen(sym) + 1;
+            let len = len as u32 + min;
+            buf = buf.checked_shl(len).unwrap_or(0);
+            buf_size -= len as i32;
+            if buf_size <= 32 {
+                buf_size += 32;
+                buf |= (self.u32_be(at) as u64) << (64 - buf_size);
+                at += 4;
+            }
+        }
+
+        // symbols stand for pairs of symbols, the values of both sides are adjacent
+        while symlen(sym) != 0 {
+            let left = self.left(d, sym);
+            if offset < symlen(left) + 1 {
+                sym = left;
+            } else {
+                offset -= symlen(left) + 1;
+                sym = self.right(d, sym);
+            }
+        }
+        self.left(d, sym) as i32
+    }
+
+    /// Looks up a position, `white_first` when white has the pieces written first in the
+    /// table name. `wdl` is the result of the position for DTZ tables
+    fn probe(&self, pos: &TbPosition, white_first: bool, wdl: Wdl) -> Option<Probed> {
+        let t = indices();
+
+        // tables store the stronger side as white and symmetric tables only white to move,
+        // the colors and the board are flipped otherwise
+        let flip = !white_first || (self.symmetric && pos.turn == Color::Black);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = flip as usize ^ (pos.turn == Color::Black) as usize;
+
+        let all = pos.squares();
+        if all.len() != self.piece_count {
+            return None;
+        }
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns = 0;
+        let mut file = 0;
+
+        // tables with pawns are split by the file of the leading pawn, the one
+        // nearest to the edge and then to the first rank
+        let lead_code = self.items[0][0].pieces[0] ^ flip_color;
+        if self.has_pawns {
+            for (sq, _) in all.iter().filter(|(_, code)| *code == lead_code) {
+                squares[size] = sq ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns = size;
+            let lead = (0..lead_pawns).fold(0, |best, i| {
+                if t.map_pawns[squares[i]] > t.map_pawns[squares[best]] {
+                    i
+                } else {
+                    best
+                }
+            });
+            squares.swap(0, lead);
+            file = file_of(squares[0]).min(7 - file_of(squares[0]));
+        }
+
+        let d = &self.items[stm % self.items.len()][file];
+        // symmetric tables without pawns are the same for both sides
+        let both_sides = self.symmetric && !self.has_pawns;
+        if self.dtz && !both_sides && (d.flags & FLAG_STM) as usize != stm {
+            return Some(Probed::OtherSide);
+        }
+
+        for (sq, code) in all.iter().filter(|(_, code)| !self.has_pawns || *code != lead_code) {
+            squares[size] = sq ^ flip_squares;
+            pieces[size] = code ^ flip_color;
+            size += 1;
+        }
+
+        // the pieces are ordered as in the table
+        for i in lead_pawns..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|j| d.pieces[i] == pieces[*j]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // mirror the leading piece to the a-d files
+        if file_of(squares[0]) > 3 {
+            for sq in squares[..size].iter_mut() {
+                *sq ^= 7;
+            }
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = t.lead_pawn_idx[lead_pawns][squares[0]];
+            squares[1..lead_pawns].sort_by_key(|sq| t.map_pawns[*sq]);
+            for (i, sq) in squares[..lead_pawns].iter().enumerate().skip(1) {
+                idx += t.binomial[i][t.map_pawns[*sq]];
+            }
+        } else {
+            // and to the first four ranks, then below the a1-h8 diagonal
+            if rank_of(squares[0]) > 3 {
+                for sq in squares[..size].iter_mut() {
+                    *sq ^= 56;
+                }
+            }
+            for i in 0..d.group_len[0] {
+                if off_diagonal(squares[i]) == 0 {
+                    continue;
+                }
+                if off_diagonal(squares[i]) > 0 {
+                    for sq in squares[i..size].iter_mut() {
+                        *sq = ((*sq >> 3) | (*sq << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            idx = if self.has_unique_pieces {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = (s1 > s0) as usize;
+                let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+                let r = if off_diagonal(s0) != 0 {
+                    (t.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                } else if off_diagonal(s1) != 0 {
+                    (6 * 63 + rank_of(s0) * 28 + t.map_b1h1h7[s1]) * 62 + s2 - adjust2
+                } else if off_diagonal(s2) != 0 {
+                    6 * 63 * 62 + 4 * 28 * 62 + rank_of(s0) * 7 * 28 + (rank_of(s1) - adjust1) * 28 + t.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank_of(s0) * 7 * 6
+                        + (rank_of(s1) - adjust1) * 6
+                        + (rank_of(s2) - adjust2)
+                };
+                r as u64
+            } else {
+                t.map_kk[t.map_a1d1d4[squares[0]]][squares[1]] as u64
+            };
+        }
+        idx *= d.group_idx[0];
+
+        // the other groups as combinations of squares not taken by the groups before them
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while next <= MAX_PIECES && d.group_len[next] != 0 {
+            let len = d.group_len[next];
+            if start + len > size {
+                return None;
+            }
+            squares[start..start + len].sort_unstable();
+            let mut n = 0;
+            for i in 0..len {
+                let sq = squares[start + i];
+                let adjust = squares[..start].iter().filter(|s| sq > **s).count();
+                let sq = (sq - adjust).saturating_sub(if remaining_pawns { 8 } else { 0 });
+                n += t.binomial[(i + 1).min(MAX_PIECES - 1)][sq];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        let value = self.decompress(d, idx);
+        if !self.dtz {
+            return Some(Probed::Value(value - 2));
+        }
+        Some(Probed::Value(self.map_dtz(file, value, wdl)))
+    }
+
+    /// Turns a stored DTZ value into plies
+    fn map_dtz(&self, file: usize, mut value: i32, wdl: Wdl) -> i32 {
+        let d = &self.items[0][file];
+        if d.flags & FLAG_MAPPED != 0 {
+            let map = d.map_idx[match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::CursedWin => 2,
+                _ => 0,
+            }];
+            value = if d.flags & FLAG_WIDE != 0 {
+                self.u16_le(self.map + 2 * (map + value as usize)) as i32
+            } else {
+                self.u8_at(self.map + map + value as usize) as i32
+            };
+        }
+
+        let plies = match wdl {
+            Wdl::Win => d.flags & FLAG_WIN_PLIES != 0,
+            Wdl::Loss => d.flags & FLAG_LOSS_PLIES != 0,
+            _ => false,
+        };
+        if !plies {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    // reads past the end of the file return zeros, broken files give wrong results but no panic
+    fn u8_at(&self, at: usize) -> u8 {
+        self.bytes.get(at).copied().unwrap_or(0)
+    }
+
+    fn u16_le(&self, at: usize) -> u16 {
+        u16::from_le_bytes([self.u8_at(at), self.u8_at(at + 1)])
+    }
+
+    fn u32_le(&self, at: usize) -> u32 {
+        u32::from_le_bytes([self.u8_at(at), self.u8_at(at + 1), self.u8_at(at + 2), self.u8_at(at + 3)])
+    }
+
+    fn u32_be(&self, at: usize) -> u32 {
+        u32::from_be_bytes([self.u8_at(at), self.u8_at(at + 1), self.u8_at(at + 2), self.u8_at(at + 3)])
+    }
+
+    fn u64_be(&self, at: usize) -> u64 {
+        (self.u32_be(at) as u64) << 32 | self.u32_be(at + 4) as u64
+    }
+}
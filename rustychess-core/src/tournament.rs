@@ -0,0 +1,319 @@
+use crate::clock::{Clock, TimeControl};
+use crate::engine::{Engine, Score, SearchLimits};
+use crate::error::Error as ChessError;
+use crate::game::{Game, GameResult, Move, Termination};
+use crate::pgn::PgnGame;
+use crate::pieces::Color;
+use crate::uci_client::UciClient;
+use std::io;
+
+/// Anything that can choose moves in a match
+pub trait Player {
+    fn name(&self) -> String;
+    fn new_game(&mut self) -> io::Result<()>;
+    /// Returns the chosen move and the score from the point of view of the side to move
+    fn think(&mut self, game: &Game, limits: &SearchLimits) -> io::Result<(Option<Move>, Option<Score>)>;
+}
+
+/// The engine of this crate, searching in process
+pub struct InternalPlayer {
+    engine: Engine,
+}
+
+impl Default for InternalPlayer {
+    fn default() -> Self {
+        InternalPlayer::new()
+    }
+}
+
+impl InternalPlayer {
+    pub fn new() -> InternalPlayer {
+        InternalPlayer { engine: Engine::new() }
+    }
+}
+
+impl Player for InternalPlayer {
+    fn name(&self) -> String {
+        "rustychess".to_string()
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn think(&mut self, game: &Game, limits: &SearchLimits) -> io::Result<(Option<Move>, Option<Score>)> {
+        let result = self.engine.search(game, limits, |_| {});
+        Ok((result.best_move, Some(result.score)))
+    }
+}
+
+impl Player for UciClient {
+    fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| "engine".to_string())
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        UciClient::new_game(self)
+    }
+
+    fn think(&mut self, game: &Game, limits: &SearchLimits) -> io::Result<(Option<Move>, Option<Score>)> {
+        let result = self.go(game, limits, |_| {})?;
+        Ok((result.best_move, result.info.and_then(|i| i.score)))
+    }
+}
+
+/// When the match runner ends a game before the rules do
+#[derive(Debug, Clone, Copy)]
+pub struct Adjudication {
+    /// A player resigns once its own score stayed below -`resign_score` for `resign_moves` moves
+    pub resign_score: i32,
+    pub resign_moves: u32,
+    /// A draw is declared once both scores stayed within `draw_score` for `draw_moves` moves
+    /// each, starting with move number `draw_after`
+    pub draw_score: i32,
+    pub draw_moves: u32,
+    pub draw_after: u32,
+    /// Games reaching this move number are declared drawn
+    pub max_moves: Option<u32>,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            resign_score: 1000,
+            resign_moves: 3,
+            draw_score: 10,
+            draw_moves: 8,
+            draw_after: 40,
+            max_moves: Some(200),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MatchSettings {
+    /// Without a time control the players only get `depth`
+    pub time_control: Option<TimeControl>,
+    pub depth: Option<u32>,
+    pub adjudication: Adjudication,
+}
+
+/// A finished game of a match
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub game: Game,
+    pub result: GameResult,
+    pub termination: Termination,
+    pub white: String,
+    pub black: String,
+}
+
+impl GameRecord {
+    pub fn pgn(&self) -> PgnGame {
+        let mut r = PgnGame::from_game(&self.game);
+        r.set_tag("White", &self.white);
+        r.set_tag("Black", &self.black);
+        r.set_result(self.result.pgn());
+        r.set_tag("Termination", &self.termination.to_string());
+        r
+    }
+}
+
+fn centipawns(score: Score) -> i32 {
+    match score {
+        Score::Cp(v) => v,
+        Score::Mate(n) if n > 0 => 100_000 - n,
+        Score::Mate(n) => -100_000 - n,
+    }
+}
+
+/// Plays one game from the position `start`
+pub fn play_game(
+    white: &mut dyn Player,
+    black: &mut dyn Player,
+    start: &Game,
+    settings: &MatchSettings,
+) -> io::Result<GameRecord> {
+    let mut game = start.clone();
+    white.new_game()?;
+    black.new_game()?;
+
+    let names = (white.name(), black.name());
+    let finish = |mut game: Game, result: GameResult, termination: Termination| {
+        if let Some(clock) = game.clock_mut() {
+            clock.halt();
+        }
+        GameRecord {
+            game,
+            result,
+            termination,
+            white: names.0.clone(),
+            black: names.1.clone(),
+        }
+    };
+
+    let adjudication = settings.adjudication;
+    if let Some(tc) = &settings.time_control {
+        game.set_clock(Clock::new(tc.clone()));
+    }
+    let mut resign_count = [0u32, 0u32];
+    let mut draw_count = 0u32;
+
+    loop {
+        if let Some((result, termination)) = game.outcome() {
+            return Ok(finish(game, result, termination));
+        }
+
+        let color = game.turn();
+        let side = if color == Color::White { 0 } else { 1 };
+        let mut limits = SearchLimits {
+            depth: settings.depth,
+            ..SearchLimits::default()
+        };
+        if let Some(clock) = game.clock() {
+            limits.wtime = Some(clock.remaining(Color::White));
+            limits.btime = Some(clock.remaining(Color::Black));
+            limits.winc = Some(clock.increment(Color::White));
+            limits.binc = Some(clock.increment(Color::Black));
+            limits.movestogo = clock.moves_to_go(color);
+        }
+
+        let player: &mut dyn Player = if side == 0 { &mut *white } else { &mut *black };
+        let (m, score) = match player.think(&game, &limits) {
+            Ok(v) => v,
+            // an engine that does not even answer "stop" has lost on time
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Ok(finish(
+                    game,
+                    GameResult::win_for(color.switch()),
+                    Termination::TimeForfeit,
+                ))
+            }
+            Err(e) => return Err(e),
+        };
+
+        // the UCI client already dropped moves that are not legal here
+        let m = match m {
+            Some(v) => v,
+            None => {
+                return Ok(finish(
+                    game,
+                    GameResult::win_for(color.switch()),
+                    Termination::IllegalMove,
+                ))
+            }
+        };
+        if !game.play_timed(m) {
+            // the flag fell, outcome() reports it
+            continue;
+        }
+
+        if let Some(score) = score.map(centipawns) {
+            if score <= -adjudication.resign_score {
+                resign_count[side] += 1;
+                if resign_count[side] >= adjudication.resign_moves {
+                    return Ok(finish(
+                        game,
+                        GameResult::win_for(color.switch()),
+                        Termination::Adjudication,
+                    ));
+                }
+            } else {
+                resign_count[side] = 0;
+            }
+
+            if score.abs() <= adjudication.draw_score && game.fullmove_number() >= adjudication.draw_after {
+                draw_count += 1;
+                if draw_count >= adjudication.draw_moves * 2 {
+                    return Ok(finish(game, GameResult::Draw, Termination::Adjudication));
+                }
+            } else {
+                draw_count = 0;
+            }
+        }
+
+        if let Some(max) = adjudication.max_moves {
+            if game.fullmove_number() > max {
+                return Ok(finish(game, GameResult::Draw, Termination::Adjudication));
+            }
+        }
+    }
+}
+
+/// Wins, draws and losses from the point of view of the first player
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn add(&mut self, result: GameResult, first_is_white: bool) {
+        match (result, first_is_white) {
+            (GameResult::Draw, _) => self.draws += 1,
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Points per game between 0 and 1
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Returns the Elo difference and its 95% error margin,
+    /// `None` if either side scored all points
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let n = self.games() as f64;
+        let p = self.score();
+        if n == 0.0 || p <= 0.0 || p >= 1.0 {
+            return None;
+        }
+
+        let variance = (self.wins as f64 * (1.0 - p).powi(2)
+            + self.draws as f64 * (0.5 - p).powi(2)
+            + self.losses as f64 * p.powi(2))
+            / n;
+        let deviation = (variance / n).sqrt();
+        let low = (p - 1.96 * deviation).max(0.0001);
+        let high = (p + 1.96 * deviation).min(0.9999);
+
+        Some((elo_difference(p), (elo_difference(high) - elo_difference(low)) / 2.0))
+    }
+}
+
+/// Elo difference corresponding to the expected score `p`
+pub fn elo_difference(p: f64) -> f64 {
+    400.0 * (p / (1.0 - p)).log10()
+}
+
+/// Reads starting positions from FEN or EPD lines, only the first four fields of
+/// an EPD line are used
+pub fn openings_from_epd(text: &str) -> Result<Vec<Game>, ChessError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let is_fen = fields.len() >= 6 && fields[4].parse::<u32>().is_ok();
+            if is_fen {
+                Game::from_fen(line)
+            } else {
+                Game::from_fen(&fields.iter().take(4).cloned().collect::<Vec<&str>>().join(" "))
+            }
+        })
+        .collect()
+}
+
+/// Reads starting positions from the final positions of PGN games
+pub fn openings_from_pgn(text: &str) -> Result<Vec<Game>, ChessError> {
+    PgnGame::parse_all(text).iter().map(PgnGame::to_game).collect()
+}
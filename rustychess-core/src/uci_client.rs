@@ -0,0 +1,402 @@
+use crate::engine::{Score, SearchLimits};
+use crate::game::Game;
+use crate::game::Move;
+use crate::pieces::Color;
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the engine may take to answer "uci" and "isready"
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long an engine may search past its time before it is sent "stop", and then
+/// how long it has to answer with "bestmove"
+pub const STOP_MARGIN: Duration = Duration::from_secs(1);
+
+/// An option announced by the engine during the handshake
+#[derive(Debug, Clone, PartialEq)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: String,
+    pub default: Option<String>,
+}
+
+/// One "info" line of an engine
+#[derive(Debug, Clone, Default)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+    /// The score is only a lower (`Some(true)`) or upper (`Some(false)`) bound
+    pub lowerbound: Option<bool>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+/// The answer of an engine to "go"
+#[derive(Debug, Clone)]
+pub struct BestMove {
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+    /// The last info line carrying a score
+    pub info: Option<EngineInfo>,
+}
+
+/// An external UCI engine running as a subprocess
+pub struct UciClient {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub options: Vec<UciOption>,
+}
+
+impl UciClient {
+    /// Launches the engine and performs the "uci" handshake
+    pub fn spawn<S: AsRef<OsStr>>(program: S, args: &[&str]) -> io::Result<UciClient> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| broken("no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| broken("no stdout"))?;
+
+        // a reader thread lets us wait for lines with a timeout
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(l) => {
+                        if sender.send(l).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut client = UciClient {
+            child,
+            stdin,
+            lines,
+            name: None,
+            author: None,
+            options: Vec::new(),
+        };
+        client.handshake()?;
+        Ok(client)
+    }
+
+    fn handshake(&mut self) -> io::Result<()> {
+        self.send("uci")?;
+        loop {
+            let line = self.read_line(Some(HANDSHAKE_TIMEOUT))?;
+            let line = line.trim();
+            if line == "uciok" {
+                return Ok(());
+            } else if let Some(name) = line.strip_prefix("id name ") {
+                self.name = Some(name.to_string());
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                self.author = Some(author.to_string());
+            } else if line.starts_with("option ") {
+                if let Some(option) = parse_option(line) {
+                    self.options.push(option);
+                }
+            }
+        }
+    }
+
+    /// Sends a raw command line to the engine
+    pub fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn read_line(&mut self, timeout: Option<Duration>) -> io::Result<String> {
+        match timeout {
+            Some(t) => self.lines.recv_timeout(t).map_err(|e| match e {
+                RecvTimeoutError::Timeout => {
+                    io::Error::new(io::ErrorKind::TimedOut, "engine did not answer in time")
+                }
+                RecvTimeoutError::Disconnected => broken("engine closed its output"),
+            }),
+            None => self.lines.recv().map_err(|_| broken("engine closed its output")),
+        }
+    }
+
+    /// Waits until the engine answers "isready" with "readyok"
+    pub fn is_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        loop {
+            if self.read_line(Some(HANDSHAKE_TIMEOUT))?.trim() == "readyok" {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Sends the starting position of `game` followed by all moves played so far
+    pub fn set_position(&mut self, game: &Game) -> io::Result<()> {
+        self.send(&position_command(game))
+    }
+
+    /// Sends the position of `game` and searches it, blocking until "bestmove".
+    /// `on_info` is called for every info line. An engine searching `STOP_MARGIN`
+    /// past its movetime or the time left on its clock is sent "stop", and one that
+    /// does not answer that in time gives a `TimedOut` error. Infinite searches are
+    /// not supported as nothing would stop them
+    pub fn go<F>(&mut self, game: &Game, limits: &SearchLimits, mut on_info: F) -> io::Result<BestMove>
+    where
+        F: FnMut(&EngineInfo),
+    {
+        if limits.infinite {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "infinite searches are not supported, use a movetime",
+            ));
+        }
+
+        self.set_position(game)?;
+        self.send(&go_command(limits))?;
+
+        let mut deadline = time_budget(limits, game.turn()).map(|t| Instant::now() + t + STOP_MARGIN);
+        let mut stopped = false;
+        let mut last = None;
+        loop {
+            let timeout = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let line = match self.read_line(timeout) {
+                Err(e) if e.kind() == io::ErrorKind::TimedOut && !stopped => {
+                    self.send("stop")?;
+                    stopped = true;
+                    deadline = Some(Instant::now() + STOP_MARGIN);
+                    continue;
+                }
+                line => line?,
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("info") => {
+                    let info = parse_info(&line, game);
+                    on_info(&info);
+                    if info.score.is_some() {
+                        last = Some(info);
+                    }
+                }
+                Some("bestmove") => {
+                    let (best_move, ponder) = parse_bestmove(&line, game);
+                    return Ok(BestMove {
+                        best_move,
+                        ponder,
+                        info: last,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks the engine to quit and waits for it to exit
+    pub fn quit(mut self) -> io::Result<()> {
+        let _ = self.send("quit");
+        self.child.wait()?;
+        Ok(())
+    }
+}
+
+impl Drop for UciClient {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = writeln!(self.stdin, "quit");
+            let _ = self.stdin.flush();
+            thread::sleep(Duration::from_millis(20));
+            if let Ok(None) = self.child.try_wait() {
+                let _ = self.child.kill();
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// The longest a search with `limits` may take for `turn`, `None` without a time limit
+fn time_budget(limits: &SearchLimits, turn: Color) -> Option<Duration> {
+    let clock = if turn == Color::White { limits.wtime } else { limits.btime };
+    match (limits.movetime, clock) {
+        (Some(movetime), Some(clock)) => Some(movetime.min(clock)),
+        (movetime, clock) => movetime.or(clock),
+    }
+}
+
+fn broken(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::BrokenPipe, msg.to_string())
+}
+
+/// Returns "position startpos moves ..." or "position fen ... moves ..." for `game`
+pub fn position_command(game: &Game) -> String {
+    let start = game.starting_fen();
+    let mut r = if start == crate::game::STARTING_FEN {
+        "position startpos".to_string()
+    } else {
+        format!("position fen {}", start)
+    };
+
+    if !game.moves().is_empty() {
+        r.push_str(" moves");
+        for m in game.moves().iter() {
+            r.push(' ');
+            r.push_str(&m.uci());
+        }
+    }
+    r
+}
+
+pub fn go_command(limits: &SearchLimits) -> String {
+    let mut r = "go".to_string();
+    let millis = |d: &Duration| d.as_millis().to_string();
+    let mut push = |key: &str, value: Option<String>| {
+        if let Some(v) = value {
+            r.push_str(&format!(" {} {}", key, v));
+        }
+    };
+
+    push("depth", limits.depth.map(|v| v.to_string()));
+    push("nodes", limits.nodes.map(|v| v.to_string()));
+    push("movetime", limits.movetime.as_ref().map(millis));
+    push("wtime", limits.wtime.as_ref().map(millis));
+    push("btime", limits.btime.as_ref().map(millis));
+    push("winc", limits.winc.as_ref().map(millis));
+    push("binc", limits.binc.as_ref().map(millis));
+    push("movestogo", limits.movestogo.map(|v| v.to_string()));
+    if limits.infinite {
+        r.push_str(" infinite");
+    }
+    r
+}
+
+/// Parses "option name <id> type <t> [default <x>] [min <x>] [max <x>] [var <x>]*"
+pub fn parse_option(line: &str) -> Option<UciOption> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let keyword = |k: &str| tokens.iter().position(|t| *t == k);
+    let name_at = keyword("name")?;
+    let type_at = keyword("type")?;
+    if type_at <= name_at {
+        return None;
+    }
+
+    let default = keyword("default").map(|at| {
+        tokens[at + 1..]
+            .iter()
+            .take_while(|t| !["min", "max", "var"].contains(t))
+            .cloned()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    });
+
+    Some(UciOption {
+        name: tokens[name_at + 1..type_at].join(" "),
+        kind: tokens.get(type_at + 1)?.to_string(),
+        default,
+    })
+}
+
+/// Parses an "info" line, moves of the PV are resolved against `game`
+pub fn parse_info(line: &str, game: &Game) -> EngineInfo {
+    let mut info = EngineInfo::default();
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+
+    let mut i = 1;
+    while i < tokens.len() {
+        let value = tokens.get(i + 1);
+        match tokens[i] {
+            "depth" => info.depth = value.and_then(|v| v.parse().ok()),
+            "seldepth" => info.seldepth = value.and_then(|v| v.parse().ok()),
+            "multipv" => info.multipv = value.and_then(|v| v.parse().ok()),
+            "nodes" => info.nodes = value.and_then(|v| v.parse().ok()),
+            "nps" => info.nps = value.and_then(|v| v.parse().ok()),
+            "time" => info.time = value.and_then(|v| v.parse().ok()).map(Duration::from_millis),
+            "score" => {
+                let number = tokens.get(i + 2).and_then(|v| v.parse::<i32>().ok());
+                info.score = match (value, number) {
+                    (Some(&"cp"), Some(n)) => Some(Score::Cp(n)),
+                    (Some(&"mate"), Some(n)) => Some(Score::Mate(n)),
+                    _ => None,
+                };
+                i += 1;
+            }
+            "lowerbound" => {
+                info.lowerbound = Some(true);
+                i += 1;
+                continue;
+            }
+            "upperbound" => {
+                info.lowerbound = Some(false);
+                i += 1;
+                continue;
+            }
+            "pv" => {
+                info.pv = parse_moves(&tokens[i + 1..], game);
+                break;
+            }
+            "string" => {
+                info.string = Some(tokens[i + 1..].join(" "));
+                break;
+            }
+            _ => {}
+        }
+        i += 2;
+    }
+
+    info
+}
+
+/// Parses "bestmove <move> [ponder <move>]"
+pub fn parse_bestmove(line: &str, game: &Game) -> (Option<Move>, Option<Move>) {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let mut moves = Vec::new();
+    if let Some(best) = tokens.get(1) {
+        moves.push(*best);
+        if tokens.get(2) == Some(&"ponder") {
+            if let Some(ponder) = tokens.get(3) {
+                moves.push(*ponder);
+            }
+        }
+    }
+
+    let mut parsed = parse_moves(&moves, game).into_iter();
+    (parsed.next(), parsed.next())
+}
+
+/// Replays UCI moves from the position of `game`, stopping at the first illegal one
+pub fn parse_moves(moves: &[&str], game: &Game) -> Vec<Move> {
+    let mut game = game.clone();
+    let mut r = Vec::new();
+    for notation in moves {
+        match game.uci_move(notation) {
+            Ok(m) => {
+                game.play(m);
+                r.push(m);
+            }
+            Err(_) => break,
+        }
+    }
+    r
+}
@@ -0,0 +1,470 @@
+use crate::chessboard::{CastlingRights, Chessboard2};
+use crate::fairy::{ARCHBISHOP, CHANCELLOR};
+use crate::file::File;
+use crate::game::{Game, GameResult, Move, Termination, STARTING_FEN};
+use crate::pieces::{Color, Kind};
+use crate::rank::Rank;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// The rules a game is played by: its starting position, which moves are legal
+/// and when it ends. The default methods are those of standard chess
+pub trait Rules: Debug + Send + Sync {
+    /// Name as written in the PGN Variant tag
+    fn name(&self) -> &'static str;
+
+    /// Name of the UCI_Variant option
+    fn uci_name(&self) -> &'static str;
+
+    fn starting_fen(&self) -> &'static str {
+        STARTING_FEN
+    }
+
+    /// Returns the legal moves of the side to move
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        game.board.legal_moves(game.turn(), game.castling(), game.en_passant())
+    }
+
+    /// Returns the result when the position ends the game, `moves` being the legal
+    /// moves of the side to move. The clock, the fifty move rule and repetitions
+    /// are left to `Game::outcome`
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        standard_result(game, moves)
+    }
+
+    /// Number of checks that win the game, written in FEN after the move numbers
+    fn check_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Returns whenever the king of `color` is in check
+    fn is_check(&self, board: &Chessboard2, color: Color) -> bool {
+        match board.find_piece(Kind::King, color) {
+            Ok((pos, _)) => board.is_square_attacked(&pos, color.switch()),
+            Err(_) => false,
+        }
+    }
+
+    /// Whenever each side has exactly one king that must be kept out of check
+    fn has_royal_king(&self) -> bool {
+        true
+    }
+
+    /// Whenever captured pieces go to a pocket and can be dropped back
+    fn has_drops(&self) -> bool {
+        false
+    }
+
+    /// Whenever the capturer keeps the captured pieces in its own pocket, see `Bughouse`
+    fn captures_to_pocket(&self) -> bool {
+        self.has_drops()
+    }
+}
+
+/// Checkmate, stalemate and insufficient material
+pub fn standard_result(game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+    if moves.is_empty() {
+        return decided_by_moves(game, moves);
+    }
+
+    if game.is_insufficient_material() {
+        Some((GameResult::Draw, Termination::InsufficientMaterial))
+    } else {
+        None
+    }
+}
+
+/// Checkmate and stalemate only
+pub fn decided_by_moves(game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+    if !moves.is_empty() {
+        None
+    } else if game.is_check() {
+        Some((GameResult::win_for(game.turn().switch()), Termination::Checkmate))
+    } else {
+        Some((GameResult::Draw, Termination::Stalemate))
+    }
+}
+
+/// Every variant, the standard rules first
+pub fn all_variants() -> Vec<Arc<dyn Rules>> {
+    vec![
+        Arc::new(Standard),
+        Arc::new(KingOfTheHill),
+        Arc::new(ThreeCheck),
+        Arc::new(RacingKings),
+        Arc::new(Antichess),
+        Arc::new(Crazyhouse),
+        Arc::new(Atomic),
+        Arc::new(Capablanca),
+        Arc::new(Bughouse),
+    ]
+}
+
+/// Finds a variant by its PGN or UCI name, ignoring case, spaces and dashes
+pub fn variant_by_name(name: &str) -> Option<Arc<dyn Rules>> {
+    let simplify = |s: &str| s.to_lowercase().replace([' ', '-'], "");
+    let name = simplify(name);
+    // Chess960 is standard chess with other starting positions, see `Game::is_chess960`
+    let name = match name.as_str() {
+        "normal" | "chess960" | "fischerandom" | "fischerrandom" => "standard".to_string(),
+        "koth" => "kingofthehill".to_string(),
+        "giveaway" | "suicide" => "antichess".to_string(),
+        _ => name,
+    };
+    all_variants()
+        .into_iter()
+        .find(|v| simplify(v.name()) == name || simplify(v.uci_name()) == name)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Standard;
+
+impl Rules for Standard {
+    fn name(&self) -> &'static str {
+        "Standard"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "chess"
+    }
+}
+
+/// Bringing the king to one of the four center squares wins
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KingOfTheHill;
+
+impl Rules for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "kingofthehill"
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        let mover = game.turn().switch();
+        let on_hill = game.board.find_pieces(Kind::King, mover).iter().any(|(pos, _)| {
+            matches!(pos.rank, Rank::D | Rank::E) && matches!(pos.file, File::Fourth | File::Fifth)
+        });
+        if on_hill {
+            return Some((GameResult::win_for(mover), Termination::Variant("king of the hill")));
+        }
+        // a lone king can still walk to the center
+        decided_by_moves(game, moves)
+    }
+}
+
+/// Giving check for the third time wins
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreeCheck;
+
+impl Rules for ThreeCheck {
+    fn name(&self) -> &'static str {
+        "Three-check"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "3check"
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        let mover = game.turn().switch();
+        if game.checks_given(mover) >= 3 {
+            return Some((GameResult::win_for(mover), Termination::Variant("three checks")));
+        }
+        // any piece can give check
+        decided_by_moves(game, moves)
+    }
+
+    fn check_limit(&self) -> Option<u32> {
+        Some(3)
+    }
+}
+
+/// Both sides race their king to the eighth row, giving check is not allowed.
+/// When white gets there first black may still draw by arriving right after
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RacingKings;
+
+impl RacingKings {
+    fn arrived(game: &Game, color: Color) -> bool {
+        game.board
+            .find_pieces(Kind::King, color)
+            .iter()
+            .any(|(pos, _)| pos.file == File::Eighth)
+    }
+}
+
+impl Rules for RacingKings {
+    fn name(&self) -> &'static str {
+        "Racing Kings"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "racingkings"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1"
+    }
+
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        let opponent = game.turn().switch();
+        let mut board = game.board.clone();
+        Standard
+            .legal_moves(game)
+            .into_iter()
+            .filter(|m| {
+                board.make(m);
+                let check = board.is_checked(opponent);
+                board.unmake(m);
+                !check
+            })
+            .collect()
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        let race = Termination::Variant("king reached the eighth row");
+        match (Self::arrived(game, Color::White), Self::arrived(game, Color::Black)) {
+            (true, true) => return Some((GameResult::Draw, race)),
+            (false, true) => return Some((GameResult::BlackWins, race)),
+            (true, false) => {
+                let catches_up = game.turn() == Color::Black
+                    && moves
+                        .iter()
+                        .any(|m| m.piece.kind == Kind::King && m.to.file == File::Eighth);
+                if !catches_up {
+                    return Some((GameResult::WhiteWins, race));
+                }
+            }
+            _ => {}
+        }
+
+        if moves.is_empty() {
+            Some((GameResult::Draw, Termination::Stalemate))
+        } else {
+            None
+        }
+    }
+}
+
+/// Captures are compulsory and losing all pieces, or having no move, wins.
+/// The king is an ordinary piece, pawns may also promote to one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Antichess;
+
+impl Rules for Antichess {
+    fn name(&self) -> &'static str {
+        "Antichess"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "antichess"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+    }
+
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        let mut moves = game
+            .board
+            .pseudo_legal_moves(game.turn(), CastlingRights::none(), game.en_passant());
+        let kings: Vec<Move> = moves
+            .iter()
+            .filter(|m| m.promotion == Some(Kind::Queen))
+            .map(|m| Move {
+                promotion: Some(Kind::King),
+                ..*m
+            })
+            .collect();
+        moves.extend(kings);
+
+        if moves.iter().any(|m| m.capture.is_some()) {
+            moves.retain(|m| m.capture.is_some());
+        }
+        moves
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        if moves.is_empty() {
+            Some((GameResult::win_for(game.turn()), Termination::Variant("no moves left")))
+        } else {
+            None
+        }
+    }
+
+    // the king is an ordinary piece
+    fn is_check(&self, _: &Chessboard2, _: Color) -> bool {
+        false
+    }
+
+    fn has_royal_king(&self) -> bool {
+        false
+    }
+}
+
+/// Captured pieces change sides and can be dropped back onto the board instead of moving
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crazyhouse;
+
+impl Rules for Crazyhouse {
+    fn name(&self) -> &'static str {
+        "Crazyhouse"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "crazyhouse"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        // captured material is never lost
+        decided_by_moves(game, moves)
+    }
+
+    fn has_drops(&self) -> bool {
+        true
+    }
+}
+
+/// Captures blow up the capturing and the captured piece along with every piece
+/// but pawns around them. Blowing up the enemy king wins, kings never capture
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Atomic;
+
+impl Atomic {
+    fn has_king(board: &Chessboard2, color: Color) -> bool {
+        board.find_piece(Kind::King, color).is_ok()
+    }
+}
+
+impl Rules for Atomic {
+    fn name(&self) -> &'static str {
+        "Atomic"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "atomic"
+    }
+
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        let color = game.turn();
+        let mut board = game.board.clone();
+        if !Atomic::has_king(&board, color) {
+            return Vec::new();
+        }
+
+        game.board
+            .pseudo_legal_moves(color, game.castling(), game.en_passant())
+            .into_iter()
+            .filter(|m| m.capture.is_none() || m.piece.kind != Kind::King)
+            .map(|mut m| {
+                if m.capture.is_some() {
+                    m.explosion = Some(game.board.explosion(m.to));
+                }
+                m
+            })
+            .filter(|m| {
+                board.make(m);
+                // blowing up the enemy king wins even when the own king is attacked
+                let legal = Atomic::has_king(&board, color)
+                    && (!Atomic::has_king(&board, color.switch()) || !self.is_check(&board, color));
+                board.unmake(m);
+                legal
+            })
+            .collect()
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        for color in [Color::White, Color::Black] {
+            if !Atomic::has_king(&game.board, color) {
+                return Some((GameResult::win_for(color.switch()), Termination::Variant("king exploded")));
+            }
+        }
+        standard_result(game, moves)
+    }
+
+    fn is_check(&self, board: &Chessboard2, color: Color) -> bool {
+        let king = match board.find_piece(Kind::King, color) {
+            Ok((pos, _)) => pos,
+            Err(_) => return false,
+        };
+        // kings side by side can't capture each other, any capture next to them would blow up both
+        let touching = match board.find_piece(Kind::King, color.switch()) {
+            Ok((pos, _)) => pos.rank.sub(king.rank) <= 1 && pos.file.sub(king.file) <= 1,
+            Err(_) => false,
+        };
+        !touching && board.is_square_attacked(&king, color.switch())
+    }
+}
+
+/// Standard chess on a 10x8 board with an archbishop and a chancellor on each side,
+/// pawns may also promote to them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Capablanca;
+
+impl Rules for Capablanca {
+    fn name(&self) -> &'static str {
+        "Capablanca"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "capablanca"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w KQkq - 0 1"
+    }
+
+    fn legal_moves(&self, game: &Game) -> Vec<Move> {
+        let mut moves = game.board.legal_moves(game.turn(), game.castling(), game.en_passant());
+        let promotions: Vec<Move> = moves
+            .iter()
+            .filter(|m| m.promotion == Some(Kind::Queen))
+            .flat_map(|m| {
+                [ARCHBISHOP, CHANCELLOR].map(|kind| Move {
+                    promotion: Some(kind),
+                    ..*m
+                })
+            })
+            .collect();
+        moves.extend(promotions);
+        moves
+    }
+}
+
+/// One board of a `BughouseMatch`: Crazyhouse, but the captured pieces go to the
+/// partner on the other board, who drops them in their own color
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bughouse;
+
+impl Rules for Bughouse {
+    fn name(&self) -> &'static str {
+        "Bughouse"
+    }
+
+    fn uci_name(&self) -> &'static str {
+        "bughouse"
+    }
+
+    fn starting_fen(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[] w KQkq - 0 1"
+    }
+
+    fn result(&self, game: &Game, moves: &[Move]) -> Option<(GameResult, Termination)> {
+        decided_by_moves(game, moves)
+    }
+
+    fn has_drops(&self) -> bool {
+        true
+    }
+
+    fn captures_to_pocket(&self) -> bool {
+        false
+    }
+}
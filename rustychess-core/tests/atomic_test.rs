@@ -0,0 +1,61 @@
+use rustychess_core::engine::perft;
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::variant::Atomic;
+use std::sync::Arc;
+
+fn atomic(fen: &str) -> Game {
+    let mut game = Game::with_rules(Arc::new(Atomic));
+    game.set_fen(fen).unwrap();
+    game
+}
+
+#[test]
+fn test_explosion() {
+    let mut game = atomic("4k3/8/4b3/3np3/2N1P3/8/8/4K3 w - - 0 1");
+    let fen = game.fen();
+
+    let m = game.uci_move("e4d5").unwrap();
+    assert_eq!(m.explosion.unwrap().pieces(m.to).count(), 2);
+    game.play(m);
+    assert_eq!(game.fen(), "4k3/8/8/4p3/8/8/8/4K3 b - - 0 1");
+
+    game.rollback_move();
+    assert_eq!(game.fen(), fen);
+}
+
+#[test]
+fn test_exploded_king() {
+    let mut game = atomic("3qk3/8/8/8/8/8/8/3RK3 w - - 0 1");
+    game.play(game.uci_move("d1d8").unwrap());
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::WhiteWins, Termination::Variant("king exploded")))
+    );
+    assert!(game.legal_moves().is_empty());
+
+    // blowing up the own king is not allowed
+    let game = atomic("4k3/8/8/8/8/8/3p4/3QK3 w - - 0 1");
+    assert!(game.is_check());
+    assert!(game.uci_move("d1d2").is_err());
+}
+
+#[test]
+fn test_kings() {
+    // kings never capture
+    let game = atomic("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1");
+    assert!(game.legal_moves().iter().all(|m| m.capture.is_none()));
+
+    // and are never in check next to each other
+    let game = atomic("8/8/8/8/8/3k4/r2K4/8 w - - 0 1");
+    assert!(!game.is_check());
+    assert!(game.uci_move("d2e2").is_ok());
+    assert!(atomic("8/8/8/8/8/3k4/r4K2/8 w - - 0 1").is_check());
+}
+
+#[test]
+fn test_perft() {
+    let mut game = Game::with_rules(Arc::new(Atomic));
+    assert_eq!(perft(&mut game, 1), 20);
+    assert_eq!(perft(&mut game, 3), 8902);
+    assert_eq!(perft(&mut game, 4), 197326);
+}
@@ -0,0 +1,141 @@
+use rustychess_core::board_builder::BoardBuilder;
+use rustychess_core::chessboard::CastlingRights;
+use rustychess_core::error::Error;
+use rustychess_core::pgn::Position;
+use rustychess_core::game::Game;
+use rustychess_core::pieces::{Color, Kind};
+use rustychess_core::variant::{Antichess, Atomic, Crazyhouse};
+use std::sync::Arc;
+
+fn sq(s: &str) -> Position {
+    Position::from_algebraic(s).unwrap()
+}
+
+fn kings() -> BoardBuilder {
+    BoardBuilder::new()
+        .piece(sq("e1"), Kind::King, Color::White)
+        .piece(sq("e8"), Kind::King, Color::Black)
+}
+
+fn reason(builder: &BoardBuilder) -> String {
+    match builder.build() {
+        Err(Error::InvalidBoard { reason }) => reason,
+        Err(e) => panic!("unexpected error {}", e),
+        Ok(game) => panic!("accepted {}", game.fen()),
+    }
+}
+
+#[test]
+fn test_build() {
+    let game = kings()
+        .piece(sq("a1"), Kind::Rook, Color::White)
+        .piece(sq("d5"), Kind::Pawn, Color::Black)
+        .piece(sq("e5"), Kind::Pawn, Color::White)
+        .side_to_move(Color::White)
+        .castling(CastlingRights {
+            white_king_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+            ..CastlingRights::all()
+        })
+        .en_passant(Some(sq("d6")))
+        .move_numbers(0, 12)
+        .build()
+        .unwrap();
+    assert_eq!(game.fen(), "4k3/8/8/3pP3/8/8/8/R3K3 w Q d6 0 12");
+    assert!(game.uci_move("e5d6").is_ok());
+
+    let again = BoardBuilder::from_game(&game).remove(sq("a1")).castling(CastlingRights::none());
+    assert_eq!(again.build().unwrap().fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 12");
+}
+
+#[test]
+fn test_kings() {
+    assert_eq!(reason(&BoardBuilder::new()), "white has no king");
+    let two = kings().piece(sq("a8"), Kind::King, Color::Black);
+    assert_eq!(reason(&two), "black has 2 kings");
+}
+
+#[test]
+fn test_antichess_kings() {
+    // the king is an ordinary piece: none, several, or attacked on the wrong turn
+    let antichess = |builder: BoardBuilder| builder.rules(Arc::new(Antichess)).build();
+    let no_kings = BoardBuilder::new()
+        .piece(sq("a1"), Kind::Rook, Color::White)
+        .piece(sq("h8"), Kind::Knight, Color::Black);
+    assert!(antichess(no_kings).is_ok());
+    let three = kings().piece(sq("a8"), Kind::King, Color::Black).piece(sq("a1"), Kind::King, Color::White);
+    assert!(antichess(three).is_ok());
+    let attacked = kings().piece(sq("e4"), Kind::Rook, Color::White);
+    assert!(antichess(attacked).is_ok());
+
+    let mut game = Game::with_rules(Arc::new(Antichess));
+    game.set_fen("8/8/8/3k4/8/8/8/R7 b - - 0 1").unwrap();
+    let built = BoardBuilder::from_game(&game).build().unwrap();
+    assert_eq!(built.rules().name(), "Antichess");
+    // the pawn rows still hold
+    assert_eq!(
+        reason(&BoardBuilder::from_game(&game).piece(sq("a1"), Kind::Pawn, Color::Black)),
+        "black pawn on a1"
+    );
+}
+
+#[test]
+fn test_pawns_and_check() {
+    let pawn = kings().piece(sq("c8"), Kind::Pawn, Color::White);
+    assert_eq!(reason(&pawn), "white pawn on c8");
+
+    let check = kings().piece(sq("e4"), Kind::Rook, Color::White);
+    assert_eq!(reason(&check), "black is in check with white to move");
+    assert!(check.side_to_move(Color::Black).build().unwrap().is_check());
+}
+
+#[test]
+fn test_castling_and_en_passant() {
+    let castling = kings()
+        .piece(sq("h1"), Kind::Rook, Color::White)
+        .castling(CastlingRights::all());
+    assert_eq!(reason(&castling), "white castling queen side needs a rook on a1");
+
+    let moved = BoardBuilder::new()
+        .piece(sq("f1"), Kind::King, Color::White)
+        .piece(sq("e8"), Kind::King, Color::Black)
+        .piece(sq("h8"), Kind::Rook, Color::Black)
+        .castling(CastlingRights {
+            white_king_side: true,
+            ..CastlingRights::none()
+        });
+    assert_eq!(reason(&moved), "white castling king side needs a rook on h1");
+
+    let ep = kings().piece(sq("d4"), Kind::Pawn, Color::White).en_passant(Some(sq("d3")));
+    assert_eq!(
+        reason(&ep),
+        "en passant square d3 does not lie behind a pawn that just moved two squares"
+    );
+    assert!(ep.side_to_move(Color::Black).build().is_ok());
+}
+
+#[test]
+fn test_variants() {
+    // the pockets and the variant survive the round trip
+    let mut crazyhouse = Game::with_rules(Arc::new(Crazyhouse));
+    crazyhouse
+        .set_fen("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR[Nn] w KQkq - 0 3")
+        .unwrap();
+    let built = BoardBuilder::from_game(&crazyhouse).build().unwrap();
+    assert_eq!(built.rules().name(), "Crazyhouse");
+    assert_eq!(built.fen(), crazyhouse.fen());
+    assert!(built.legal_moves().iter().any(|m| m.is_drop));
+
+    let mut atomic = Game::with_rules(Arc::new(Atomic));
+    atomic.set_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+    let built = BoardBuilder::from_game(&atomic).build().unwrap();
+    assert_eq!(built.rules().name(), "Atomic");
+    assert_eq!(built.fen(), atomic.fen());
+
+    // a builder given the rules gets empty pockets
+    let built = kings().rules(Arc::new(Crazyhouse)).build().unwrap();
+    assert_eq!(built.rules().name(), "Crazyhouse");
+    assert!(built.board.pockets().is_some());
+    assert_eq!(kings().build().unwrap().rules().name(), "Standard");
+}
@@ -0,0 +1,172 @@
+use rustychess_core::book::{decode_move, encode_move, Book, BookBuilder, BookEntry, MoveStats};
+use rustychess_core::game::{Game, Move};
+use rustychess_core::pgn::PgnGame;
+
+fn uci(m: Option<Move>) -> Option<String> {
+    m.map(|m| m.uci())
+}
+
+fn play(game: &mut Game, moves: &[&str]) {
+    for m in moves {
+        let m = game.uci_move(m).unwrap();
+        game.play(m);
+    }
+}
+
+#[test]
+fn test_polyglot_keys() {
+    // the test vectors of the Polyglot book format specification
+    let vectors: [(&[&str], u64); 9] = [
+        (&[], 0x463b96181691fc9c),
+        (&["e2e4"], 0x823c9b50fd114196),
+        (&["e2e4", "d7d5"], 0x0756b94461c50fb0),
+        (&["e2e4", "d7d5", "e4e5"], 0x662fafb965db29d4),
+        (&["e2e4", "d7d5", "e4e5", "f7f5"], 0x22a48b5a8e47ff78),
+        (&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2"], 0x652a607ca3f242c1),
+        (&["e2e4", "d7d5", "e4e5", "f7f5", "e1e2", "e8f7"], 0x00fdd303c946bdd9),
+        (&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4"], 0x3c8123ea7b067637),
+        (&["a2a4", "b7b5", "h2h4", "b5b4", "c2c4", "b4c3", "a1a3"], 0x5c3f9b829b279560),
+    ];
+
+    for (moves, key) in vectors.iter() {
+        let mut game = Game::new();
+        play(&mut game, moves);
+        assert_eq!(game.polyglot_key(), *key, "after {:?}", moves);
+    }
+}
+
+#[test]
+fn test_entry_bytes() {
+    let entry = BookEntry {
+        key: 0x463b96181691fc9c,
+        raw_move: 0x031c,
+        weight: 7,
+        learn: 1,
+    };
+    let bytes = entry.to_bytes();
+    assert_eq!(&bytes[..8], &[0x46, 0x3b, 0x96, 0x18, 0x16, 0x91, 0xfc, 0x9c]);
+    assert_eq!(BookEntry::from_bytes(&bytes), entry);
+}
+
+#[test]
+fn test_castling_encoding() {
+    let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+    let castle = game.uci_move("e1g1").unwrap();
+    // king takes its own rook on h1
+    assert_eq!(encode_move(&castle), 7 | 4 << 6);
+    assert_eq!(uci(decode_move(&game, 7 | 4 << 6)), Some(castle.uci()));
+    assert_eq!(uci(decode_move(&game, 4 << 6)), Some("e1c1".to_string()));
+
+    let game = Game::from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1").unwrap();
+    let promotion = game.uci_move("e7e8n").unwrap();
+    assert_eq!(uci(decode_move(&game, encode_move(&promotion))), Some(promotion.uci()));
+}
+
+#[test]
+fn test_book_lookup() {
+    let game = Game::new();
+    let key = game.polyglot_key();
+    let e4 = game.uci_move("e2e4").unwrap();
+    let d4 = game.uci_move("d2d4").unwrap();
+    let entry = |raw_move, weight| BookEntry {
+        key,
+        raw_move,
+        weight,
+        learn: 0,
+    };
+
+    let mut bytes = Vec::new();
+    for e in [
+        BookEntry { key: 1, raw_move: 0, weight: 1, learn: 0 },
+        entry(encode_move(&d4), 1),
+        entry(encode_move(&e4), 3),
+        // a move that is not legal here is skipped
+        entry(0, 9),
+        BookEntry { key: u64::MAX, raw_move: 0, weight: 1, learn: 0 },
+    ]
+    .iter()
+    {
+        bytes.extend_from_slice(&e.to_bytes());
+    }
+
+    let book = Book::from_bytes(&bytes);
+    assert_eq!(book.len(), 5);
+    assert_eq!(book.entries(key).len(), 3);
+    let moves: Vec<(String, u16)> = book.moves(&game).iter().map(|(m, w)| (m.uci(), *w)).collect();
+    assert_eq!(moves, vec![(e4.uci(), 3), (d4.uci(), 1)]);
+    assert_eq!(uci(book.best_move(&game)), Some(e4.uci()));
+    assert_eq!(uci(book.weighted_move(&game, 2)), Some(e4.uci()));
+    assert_eq!(uci(book.weighted_move(&game, 3)), Some(d4.uci()));
+    assert!(book.random_move(&game).is_some());
+
+    let mut after = game.clone();
+    after.play(e4);
+    assert!(book.moves(&after).is_empty());
+}
+
+#[test]
+fn test_book_builder() {
+    let pgn = r#"[White "A"]
+[Black "B"]
+[WhiteElo "2400"]
+[BlackElo "1800"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[WhiteElo "2300"]
+[BlackElo "2300"]
+[Result "1/2-1/2"]
+
+1. e4 c5 1/2-1/2
+
+[WhiteElo "2500"]
+[BlackElo "2500"]
+[Result "0-1"]
+
+1. d4 d5 0-1
+"#;
+    let mut builder = BookBuilder::new();
+    builder.max_ply = 2;
+    for game in PgnGame::parse_all(pgn).iter() {
+        builder.add_game(game).unwrap();
+    }
+    assert_eq!(builder.games(), 3);
+
+    let start = Game::new();
+    let e4 = start.uci_move("e2e4").unwrap();
+    let stats = builder.stats(start.polyglot_key(), encode_move(&e4)).unwrap();
+    assert_eq!(stats, MoveStats { games: 2, wins: 1, draws: 1, losses: 0 });
+
+    // d4 and e5 only lost, Nf3 is beyond the ply limit
+    let book = builder.build();
+    let moves: Vec<(String, u16)> = book.moves(&start).iter().map(|(m, w)| (m.uci(), *w)).collect();
+    assert_eq!(moves, vec![("e2e4".to_string(), 3)]);
+    let mut after = start.clone();
+    after.play(e4);
+    let moves: Vec<String> = book.moves(&after).iter().map(|(m, _)| m.uci()).collect();
+    assert_eq!(moves, vec!["c7c5".to_string()]);
+    after.play(after.uci_move("e7e5").unwrap());
+    assert!(book.moves(&after).is_empty());
+
+    let mut bytes = Vec::new();
+    book.write(&mut bytes).unwrap();
+    assert_eq!(Book::from_bytes(&bytes).len(), book.len());
+
+    // with a minimum rating the moves of the 1800 player are not recorded
+    let mut builder = BookBuilder::new();
+    builder.min_elo = Some(2000);
+    for game in PgnGame::parse_all(pgn).iter() {
+        builder.add_game(game).unwrap();
+    }
+    let mut after = start.clone();
+    after.play(e4);
+    let e5 = after.uci_move("e7e5").unwrap();
+    assert_eq!(builder.stats(after.polyglot_key(), encode_move(&e5)), None);
+    assert!(builder.stats(after.polyglot_key(), encode_move(&after.uci_move("c7c5").unwrap())).is_some());
+
+    builder.min_games = 2;
+    let moves: Vec<String> = builder.build().moves(&start).iter().map(|(m, _)| m.uci()).collect();
+    assert_eq!(moves, vec!["e2e4".to_string()]);
+    assert!(builder.build().moves(&after).is_empty());
+}
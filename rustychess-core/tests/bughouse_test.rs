@@ -0,0 +1,89 @@
+use rustychess_core::bughouse::{BpgnGame, BughouseMatch};
+use rustychess_core::clock::{Clock, ManualTimeSource, TimeControl};
+use rustychess_core::game::{GameResult, Termination};
+use rustychess_core::pieces::{Color, Kind};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn play(bughouse: &mut BughouseMatch, moves: &[(usize, &str)]) {
+    for (board, san) in moves {
+        let m = bughouse.board(*board).san_move(san).unwrap();
+        assert!(bughouse.play(*board, m));
+    }
+}
+
+#[test]
+fn test_captures_go_to_partner() {
+    let mut bughouse = BughouseMatch::new();
+    play(&mut bughouse, &[(0, "e4"), (0, "d5"), (0, "exd5")]);
+
+    // white on board A took a black pawn, black on board B may drop it
+    assert_eq!(bughouse.board(0).board.pockets().unwrap().pieces(Color::White), vec![]);
+    assert_eq!(bughouse.board(1).board.pockets().unwrap().pieces(Color::Black), vec![Kind::Pawn]);
+    assert!(bughouse.board(0).fen().starts_with("rnbqkbnr/ppp1pppp/8/3P4/8/8/PPPP1PPP/RNBQKBNR[] b"));
+
+    play(&mut bughouse, &[(1, "d4"), (1, "P@d5")]);
+    assert_eq!(bughouse.board(1).board.pockets().unwrap().pieces(Color::Black), vec![]);
+    assert_eq!(bughouse.moves().len(), 5);
+
+    while bughouse.rollback().is_some() {}
+    assert_eq!(bughouse.board(1).fen(), BughouseMatch::new().board(1).fen());
+    assert_eq!(bughouse.board(0).fen(), BughouseMatch::new().board(0).fen());
+}
+
+#[test]
+fn test_any_board_ends_the_match() {
+    let mut bughouse = BughouseMatch::new();
+    play(&mut bughouse, &[(0, "e4"), (1, "f3"), (1, "e5"), (1, "g4"), (1, "Qh4#")]);
+    assert_eq!(bughouse.board(0).outcome(), None);
+    // black on board B won for the team with white on board A
+    assert_eq!(bughouse.outcome(), Some((1, GameResult::WhiteWins, Termination::Checkmate)));
+}
+
+#[test]
+fn test_clocks_per_board() {
+    let time = ManualTimeSource::new();
+    let control = TimeControl::sudden_death(Duration::from_secs(60));
+    let mut bughouse = BughouseMatch::new();
+    bughouse.set_clock(0, Clock::with_source(control.clone(), Arc::new(time.clone())));
+    bughouse.set_clock(1, Clock::with_source(control, Arc::new(time.clone())));
+
+    time.advance(Duration::from_secs(30));
+    play(&mut bughouse, &[(0, "e4")]);
+    assert_eq!(bughouse.board(0).clock_time(0), Some(Duration::from_secs(30)));
+
+    // white on board B never moved and runs out of time
+    time.advance(Duration::from_secs(31));
+    assert_eq!(bughouse.outcome(), Some((1, GameResult::WhiteWins, Termination::TimeForfeit)));
+}
+
+#[test]
+fn test_bpgn() {
+    let mut bughouse = BughouseMatch::new();
+    play(
+        &mut bughouse,
+        &[(0, "e4"), (1, "d4"), (0, "d5"), (0, "exd5"), (1, "P@e5"), (1, "dxe5"), (0, "P@e4")],
+    );
+
+    let mut bpgn = BpgnGame::from_match(&bughouse).unwrap();
+    bpgn.set_tag("WhiteA", "Alice");
+    let mut text = Vec::new();
+    bpgn.write(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.contains("[WhiteA \"Alice\"]"));
+    assert!(text.contains("1A. e4 1B. d4 1a. d5 2A. exd5 1b. P@e5 2B. dxe5 2a. P@e4 *"));
+
+    let parsed = BpgnGame::parse_all(&text);
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].tag("WhiteA"), Some("Alice"));
+    assert_eq!(parsed[0].moves, bpgn.moves);
+    let replayed = parsed[0].to_match().unwrap();
+    for board in 0..2 {
+        assert_eq!(replayed.board(board).fen(), bughouse.board(board).fen());
+    }
+
+    let compact = BpgnGame::parse_all("[Result \"*\"]\n1A.e4 {59.5} 1B.d4 1a.e5 *\n");
+    assert_eq!(compact[0].moves.len(), 3);
+    assert_eq!(compact[0].moves[0].comment.as_deref(), Some("59.5"));
+    assert_eq!(compact[0].moves[1].board, 1);
+}
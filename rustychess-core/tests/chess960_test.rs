@@ -0,0 +1,119 @@
+use rustychess_core::chessboard::{chess960_back_rank, CastlingRights, STANDARD_CHESS960_INDEX};
+use rustychess_core::engine::perft;
+use rustychess_core::game::{Game, STARTING_FEN};
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::Kind;
+
+#[test]
+fn test_starting_positions() {
+    let names = |index| {
+        chess960_back_rank(index)
+            .iter()
+            .map(|k| if *k == Kind::Pawn { "P" } else { k.pgn() })
+            .collect::<String>()
+    };
+    assert_eq!(names(0), "BBQNNRKR");
+    assert_eq!(names(STANDARD_CHESS960_INDEX), "RNBQKBNR");
+    assert_eq!(names(959), "RKRNNQBB");
+
+    // every index is a different legal setup
+    let mut seen = std::collections::HashSet::new();
+    for index in 0..960 {
+        let rank = names(index);
+        let bishops: Vec<usize> = rank.match_indices('B').map(|(i, _)| i).collect();
+        assert_ne!(bishops[0] % 2, bishops[1] % 2);
+        let (rooks, king) = (rank.match_indices('R').map(|(i, _)| i).collect::<Vec<_>>(), rank.find('K').unwrap());
+        assert!(rooks[0] < king && king < rooks[1]);
+        assert!(seen.insert(rank));
+    }
+
+    assert_eq!(Game::new_chess960(STANDARD_CHESS960_INDEX).fen(), STARTING_FEN);
+    let game = Game::new_chess960(0);
+    assert_eq!(game.fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1");
+    assert!(game.is_chess960());
+    assert_eq!(game.castling(), CastlingRights::chess960(0));
+}
+
+#[test]
+fn test_perft() {
+    let mut game = Game::from_fen("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    assert_eq!(perft(&mut game, 1), 21);
+    assert_eq!(perft(&mut game, 2), 528);
+    assert_eq!(perft(&mut game, 3), 12189);
+
+    let mut game = Game::from_fen("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9").unwrap();
+    assert_eq!(perft(&mut game, 1), 21);
+    assert_eq!(perft(&mut game, 2), 807);
+    assert_eq!(perft(&mut game, 3), 18002);
+}
+
+#[test]
+fn test_fen_castling_fields() {
+    // X-FEN letters stand for the outermost rook
+    let game = Game::from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq - 0 1").unwrap();
+    assert_eq!(game.castling().king_rook.to_str(), "g");
+    assert_eq!(game.castling().queen_rook.to_str(), "b");
+    assert!(game.is_chess960());
+    assert_eq!(game.fen(), "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1");
+    assert_eq!(Game::from_fen(&game.fen()).unwrap().castling(), game.castling());
+
+    let game = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+    assert_eq!(game.fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    assert!(!game.is_chess960());
+    assert!(Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1").is_err());
+}
+
+#[test]
+fn test_castling() {
+    // the king stays on g1 and the rook jumps over it from h1
+    let mut game = Game::from_fen("k7/8/8/8/8/8/8/5RKR w H - 0 1").unwrap();
+    assert!(game.uci_move("g1h1").is_err());
+    game.set_fen("k7/8/8/8/8/8/8/4RK1R w H - 0 1").unwrap();
+    let m = game.uci_move("f1h1").unwrap();
+    assert!(m.is_castling);
+    assert_eq!(m.uci_chess960(), "f1h1");
+    assert_eq!(m.uci(), "f1g1");
+    assert_eq!(game.san(&m), "O-O");
+    game.play(m);
+    assert_eq!(game.fen(), "k7/8/8/8/8/8/8/4RRK1 b - - 1 1");
+    game.rollback_move();
+    assert_eq!(game.fen(), "k7/8/8/8/8/8/8/4RK1R w K - 0 1");
+
+    // king and rook swap squares
+    let mut game = Game::from_fen("4k3/8/8/8/8/8/8/2RK4 w C - 0 1").unwrap();
+    let m = game.san_move("O-O-O").unwrap();
+    game.play(m);
+    assert_eq!(game.fen(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+    game.rollback_move();
+    assert_eq!(game.fen(), "4k3/8/8/8/8/8/8/2RK4 w C - 0 1");
+
+    // a piece between the rook and its target square blocks
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/RN1K4 w A - 0 1").unwrap();
+    assert!(game.san_move("O-O-O").is_err());
+    // the rook shielded the king's target square from the queen on a1
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/qR2K3 w B - 0 1").unwrap();
+    assert!(game.san_move("O-O-O").is_err());
+}
+
+#[test]
+fn test_pgn_variant() {
+    let mut game = Game::new_chess960(0);
+    for m in ["f2f4", "f7f5", "f1f2", "f8f7", "g1h1"].iter() {
+        let m = game.uci_move(m).unwrap();
+        game.play(m);
+    }
+
+    let pgn = PgnGame::from_game(&game);
+    assert_eq!(pgn.tag("Variant"), Some("Chess960"));
+    assert_eq!(pgn.tag("SetUp"), Some("1"));
+    assert_eq!(pgn.tag("FEN"), Some("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w HFhf - 0 1"));
+    assert_eq!(pgn.moves.last().unwrap().san, "O-O");
+
+    let mut text = Vec::new();
+    pgn.write(&mut text).unwrap();
+    let read = &PgnGame::parse_all(&String::from_utf8(text).unwrap())[0];
+    assert!(read.is_chess960());
+    let replayed = read.to_game().unwrap();
+    assert!(replayed.is_chess960());
+    assert_eq!(replayed.fen(), game.fen());
+}
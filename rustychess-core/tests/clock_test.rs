@@ -0,0 +1,150 @@
+use rustychess_core::clock::{format_clock, parse_clock, Bonus, Clock, ManualTimeSource, TimeControl};
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::Color;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn secs(s: u64) -> Duration {
+    Duration::from_secs(s)
+}
+
+fn manual_clock(control: TimeControl) -> (Clock, ManualTimeSource) {
+    let time = ManualTimeSource::new();
+    (Clock::with_source(control, Arc::new(time.clone())), time)
+}
+
+#[test]
+fn test_parse_time_control() {
+    let tc = TimeControl::parse("40/5400+30:1800+30").unwrap();
+    assert_eq!(tc.stages.len(), 2);
+    assert_eq!(tc.stages[0].moves, Some(40));
+    assert_eq!(tc.stages[0].time, secs(5400));
+    assert_eq!(tc.stages[0].bonus, Bonus::Fischer(secs(30)));
+    assert_eq!(tc.stages[1].moves, None);
+    assert_eq!(tc.pgn(), "40/5400+30:1800+30");
+
+    assert_eq!(TimeControl::parse("300d5").unwrap(), TimeControl::simple_delay(secs(300), secs(5)));
+    assert_eq!(TimeControl::parse("300b5").unwrap(), TimeControl::bronstein(secs(300), secs(5)));
+    assert_eq!(TimeControl::parse("60").unwrap(), TimeControl::sudden_death(secs(60)));
+    assert!(TimeControl::parse("0/60").is_none());
+    assert!(TimeControl::parse("60+x").is_none());
+}
+
+#[test]
+fn test_sudden_death_flag() {
+    let (mut clock, time) = manual_clock(TimeControl::sudden_death(secs(60)));
+    clock.start(Color::White);
+    time.advance(secs(20));
+    assert_eq!(clock.remaining(Color::White), secs(40));
+    clock.press();
+    assert_eq!(clock.running(), Some(Color::Black));
+
+    time.advance(secs(60));
+    assert_eq!(clock.flag(), Some(Color::Black));
+    assert_eq!(clock.complete_move(), Some(secs(0)));
+    assert_eq!(clock.flag(), Some(Color::Black));
+}
+
+#[test]
+fn test_fischer_and_bronstein() {
+    let (mut clock, time) = manual_clock(TimeControl::fischer(secs(60), secs(2)));
+    clock.start(Color::White);
+    time.advance(secs(5));
+    assert_eq!(clock.complete_move(), Some(secs(57)));
+    assert_eq!(clock.increment(Color::White), secs(2));
+
+    let (mut clock, time) = manual_clock(TimeControl::bronstein(secs(60), secs(5)));
+    clock.start(Color::White);
+    time.advance(secs(2));
+    assert_eq!(clock.complete_move(), Some(secs(60)));
+    clock.start(Color::White);
+    time.advance(secs(8));
+    assert_eq!(clock.complete_move(), Some(secs(57)));
+}
+
+#[test]
+fn test_simple_delay() {
+    let (mut clock, time) = manual_clock(TimeControl::simple_delay(secs(60), secs(5)));
+    clock.start(Color::White);
+    time.advance(secs(4));
+    assert_eq!(clock.remaining(Color::White), secs(60));
+    time.advance(secs(3));
+    assert_eq!(clock.remaining(Color::White), secs(58));
+    assert_eq!(clock.complete_move(), Some(secs(58)));
+}
+
+#[test]
+fn test_multi_stage() {
+    let (mut clock, time) = manual_clock(TimeControl::parse("2/100:50+10").unwrap());
+    assert_eq!(clock.moves_to_go(Color::White), Some(2));
+    for _ in 0..2 {
+        clock.start(Color::White);
+        time.advance(secs(10));
+        clock.complete_move();
+    }
+
+    // the second stage adds its time and switches to its increment
+    assert_eq!(clock.remaining(Color::White), secs(130));
+    assert_eq!(clock.moves_to_go(Color::White), None);
+    clock.start(Color::White);
+    time.advance(secs(10));
+    assert_eq!(clock.complete_move(), Some(secs(130)));
+    assert_eq!(clock.remaining(Color::Black), secs(100));
+}
+
+#[test]
+fn test_game_clock_and_pgn() {
+    let (clock, time) = manual_clock(TimeControl::fischer(secs(300), secs(2)));
+    let mut game = Game::new();
+    game.set_clock(clock);
+
+    time.advance(secs(10));
+    game.move_("e2e4").unwrap();
+    time.advance(secs(65));
+    game.move_("e7e5").unwrap();
+    assert_eq!(game.clock_time(0), Some(secs(292)));
+    assert_eq!(game.clock_time(1), Some(secs(237)));
+
+    let pgn = PgnGame::from_game(&game);
+    assert_eq!(pgn.tag("TimeControl"), Some("300+2"));
+    assert_eq!(pgn.moves[0].comment.as_deref(), Some("[%clk 0:04:52]"));
+    assert_eq!(pgn.moves[1].comment.as_deref(), Some("[%clk 0:03:57]"));
+
+    game.rollback_move();
+    assert_eq!(game.clock_time(1), None);
+}
+
+#[test]
+fn test_loss_on_time() {
+    let (clock, time) = manual_clock(TimeControl::sudden_death(secs(10)));
+    let mut game = Game::new();
+    game.set_clock(clock);
+
+    time.advance(secs(11));
+    assert!(game.move_("e2e4").is_err());
+    assert_eq!(game.moves().len(), 0);
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::BlackWins, Termination::TimeForfeit))
+    );
+}
+
+#[test]
+fn test_draw_on_time_without_mating_material() {
+    let (clock, time) = manual_clock(TimeControl::sudden_death(secs(10)));
+    let mut game = Game::from_fen("7k/8/8/8/8/8/8/KQ6 w - - 0 1").unwrap();
+    game.set_clock(clock);
+
+    time.advance(secs(11));
+    assert_eq!(game.outcome(), Some((GameResult::Draw, Termination::TimeForfeit)));
+}
+
+#[test]
+fn test_format_clock() {
+    assert_eq!(format_clock(secs(5400)), "1:30:00");
+    assert_eq!(format_clock(Duration::from_millis(59_900)), "0:00:59");
+    assert_eq!(parse_clock("1:30:00"), Some(secs(5400)));
+    assert_eq!(parse_clock("0:00:01.5"), Some(Duration::from_millis(1500)));
+    assert_eq!(parse_clock("x"), None);
+}
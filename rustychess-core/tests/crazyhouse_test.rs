@@ -0,0 +1,109 @@
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::{Color, Kind};
+use rustychess_core::variant::{Crazyhouse, Rules};
+use std::sync::Arc;
+
+fn play(game: &mut Game, moves: &[&str]) {
+    for m in moves {
+        let m = game.uci_move(m).unwrap();
+        game.play(m);
+    }
+}
+
+fn crazyhouse(fen: &str) -> Game {
+    let mut game = Game::with_rules(Arc::new(Crazyhouse));
+    game.set_fen(fen).unwrap();
+    game
+}
+
+#[test]
+fn test_captures_and_drops() {
+    let mut game = Game::with_rules(Arc::new(Crazyhouse));
+    assert_eq!(game.fen(), Crazyhouse.starting_fen());
+
+    play(&mut game, &["e2e4", "d7d5", "e4d5", "d8d5"]);
+    assert_eq!(
+        game.fen(),
+        "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3"
+    );
+
+    let drop = game.san_move("P@e6").unwrap();
+    assert!(drop.is_drop);
+    assert_eq!(drop.uci(), "P@e6");
+    assert_eq!(game.san(&drop), "P@e6");
+    game.play(drop);
+    assert_eq!(game.board.pockets().unwrap().count(Color::White, Kind::Pawn), 0);
+    assert!(game.fen().contains("[p] b"));
+
+    let before = game.fen();
+    play(&mut game, &["c8e6"]);
+    assert_eq!(game.board.pockets().unwrap().pieces(Color::Black), vec![Kind::Pawn, Kind::Pawn]);
+    game.rollback_move();
+    assert_eq!(game.fen(), before);
+    game.rollback_move();
+    assert_eq!(
+        game.fen(),
+        "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3"
+    );
+}
+
+#[test]
+fn test_promoted_pieces_demote() {
+    let mut game = crazyhouse("r3k3/2P5/8/8/8/8/8/4K3[] w - - 0 1");
+    play(&mut game, &["c7c8q"]);
+    assert_eq!(game.fen(), "r1Q~1k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+
+    play(&mut game, &["a8c8"]);
+    assert_eq!(game.fen(), "2r1k3/8/8/8/8/8/8/4K3[p] w - - 0 2");
+
+    game.rollback_move();
+    assert_eq!(game.fen(), "r1Q~1k3/8/8/8/8/8/8/4K3[] b - - 0 1");
+    let reloaded = crazyhouse(&game.fen());
+    assert!(reloaded.board.get("c", 8).unwrap().promoted);
+}
+
+#[test]
+fn test_drop_rules() {
+    // pawns never go to the first or last row
+    let game = crazyhouse("4k3/8/8/8/8/8/8/4K3[P] w - - 0 1");
+    let drops: Vec<_> = game.legal_moves().into_iter().filter(|m| m.is_drop).collect();
+    assert_eq!(drops.len(), 48);
+    assert!(game.san_move("P@e8").is_err());
+    assert!(game.uci_move("P@e4").is_ok());
+
+    // drops may block a check
+    let game = crazyhouse("4k3/8/8/8/8/8/8/r3K3[N] w - - 0 1");
+    let blocks: Vec<String> = game
+        .legal_moves()
+        .iter()
+        .filter(|m| m.is_drop)
+        .map(|m| m.uci())
+        .collect();
+    assert_eq!(blocks.len(), 3);
+    assert!(blocks.contains(&"N@d1".to_string()));
+
+    // and mate, a lone king is no draw
+    let mut game = crazyhouse("7k/8/6K1/8/8/8/8/8[Q] w - - 0 1");
+    assert_eq!(game.outcome(), None);
+    game.play(game.san_move("Q@g7").unwrap());
+    assert_eq!(game.outcome(), Some((GameResult::WhiteWins, Termination::Checkmate)));
+}
+
+#[test]
+fn test_pgn_drops() {
+    let mut game = crazyhouse("rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3");
+    play(&mut game, &["P@e6", "c8e6", "g1f3", "P@e4"]);
+
+    let pgn = PgnGame::from_game(&game);
+    assert_eq!(pgn.tag("Variant"), Some("Crazyhouse"));
+    let mut text = Vec::new();
+    pgn.write(&mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.contains("3. P@e6 Bxe6 4. Nf3 P@e4"));
+
+    let parsed = PgnGame::parse_all(&text);
+    assert_eq!(parsed[0].tag("FEN"), pgn.tag("FEN"));
+    let replayed = parsed[0].to_game().unwrap();
+    assert_eq!(replayed.fen(), game.fen());
+}
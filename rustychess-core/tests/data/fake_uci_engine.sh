@@ -0,0 +1,42 @@
+#!/bin/sh
+# Scripted stand-in for a UCI engine, used by the uci_client, tournament and shell tests
+position=""
+while read -r line; do
+    case "$line" in
+        uci)
+            echo "id name Fake Engine"
+            echo "id author rustychess tests"
+            echo "option name Hash type spin default 16 min 1 max 1024"
+            echo "option name Skill Level type combo default Full Strength var Full Strength var Weak"
+            echo "uciok"
+            ;;
+        isready)
+            echo "readyok"
+            ;;
+        position*)
+            position="$line"
+            ;;
+        "go depth 99"*)
+            # searches until told to stop
+            searching=1
+            ;;
+        "go depth 98"*)
+            # hangs, even "stop" goes unanswered
+            ;;
+        stop)
+            if [ -n "$searching" ]; then
+                searching=""
+                echo "bestmove e2e4"
+            fi
+            ;;
+        go*)
+            echo "info string $position"
+            echo "info depth 1 score cp 20 nodes 10 nps 1000 time 10 pv e2e4"
+            echo "info depth 2 seldepth 3 score mate 2 lowerbound nodes 42 time 12 pv e2e4 e7e5 zz"
+            echo "bestmove e2e4 ponder e7e5"
+            ;;
+        quit)
+            exit 0
+            ;;
+    esac
+done
@@ -0,0 +1,98 @@
+use rustychess_core::endgame::{Dtm, EndgameSolver, EndgameTable, Material};
+use rustychess_core::game::Game;
+use std::fs;
+use std::sync::OnceLock;
+
+/// Tables take a while to build, the tests share them
+fn solver() -> &'static EndgameSolver {
+    static SOLVER: OnceLock<EndgameSolver> = OnceLock::new();
+    SOLVER.get_or_init(EndgameSolver::new)
+}
+
+fn solve(solver: &EndgameSolver, fen: &str) -> (Dtm, Option<String>) {
+    let game = Game::from_fen(fen).unwrap();
+    let solution = solver.solve(&game).expect(fen);
+    (solution.dtm, solution.best_move.map(|m| m.uci()))
+}
+
+#[test]
+fn test_material() {
+    let m = Material::parse("kbnk").unwrap();
+    assert_eq!(m.to_string(), "KBNK");
+    assert_eq!(m.count(), 4);
+    assert_eq!(Material::parse("KNBK"), Some(m));
+    assert_eq!(Material::parse("KRKN").unwrap().flipped().to_string(), "KNKR");
+    assert_eq!(Material::parse("QK"), None);
+    assert_eq!(Material::parse("KXK"), None);
+}
+
+#[test]
+fn test_kqk() {
+    let solver = solver();
+    assert_eq!(solve(solver, "7k/8/6K1/8/8/8/8/1Q6 w - - 0 1").0, Dtm::Win(1));
+    assert_eq!(solve(solver, "Q6k/8/6K1/8/8/8/8/8 b - - 0 1"), (Dtm::Loss(0), None));
+    assert_eq!(solve(solver, "8/8/8/3k4/8/8/8/KQ6 b - - 0 1").0, Dtm::Loss(9));
+    let table = solver.table(&Material::parse("KQK").unwrap()).unwrap();
+    assert_eq!(table.material().to_string(), "KQK");
+
+    // the best moves lead to mate as announced
+    let mut game = Game::from_fen("8/8/8/3k4/8/8/8/KQ6 w - - 0 1").unwrap();
+    for moves in (1..=9).rev() {
+        let solution = solver.solve(&game).unwrap();
+        assert_eq!(solution.dtm, Dtm::Win(moves));
+        game.play(solution.best_move.unwrap());
+        let reply = solver.solve(&game).unwrap();
+        assert_eq!(reply.dtm, Dtm::Loss(moves - 1));
+        if let Some(m) = reply.best_move {
+            game.play(m);
+        }
+    }
+    assert!(game.is_check() && game.legal_moves().is_empty());
+
+    // the weaker side is looked up with the colors swapped
+    assert_eq!(solve(solver, "8/8/8/8/8/8/1q6/K3k3 w - - 0 1"), (Dtm::Draw, Some("a1b2".to_string())));
+    assert_eq!(solve(solver, "8/8/8/3K4/8/8/8/kq6 w - - 0 1").0, Dtm::Loss(9));
+    // positions with castling rights are not covered
+    assert!(solver.solve(&Game::from_fen("4k3/8/8/8/8/8/8/Q3K2R w K - 0 1").unwrap()).is_none());
+    assert!(solver.solve(&Game::new()).is_none());
+}
+
+#[test]
+fn test_kpk() {
+    let solver = solver();
+    // the king in front of its pawn with the opposition holds
+    assert_eq!(solve(solver, "8/8/8/8/8/4k3/4P3/4K3 w - - 0 1").0, Dtm::Draw);
+    assert_eq!(solve(solver, "4k3/4p3/4K3/8/8/8/8/8 b - - 0 1").0, Dtm::Draw);
+    // the king on the sixth rank ahead of its pawn wins whoever moves
+    assert!(matches!(solve(solver, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").0, Dtm::Win(_)));
+    assert!(matches!(solve(solver, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").0, Dtm::Loss(_)));
+
+    // a pawn outside the square of the king runs, promotions lead into the KQK table
+    let (dtm, best) = solve(solver, "7k/8/8/P7/8/8/8/7K w - - 0 1");
+    assert!(matches!(dtm, Dtm::Win(_)));
+    assert_eq!(best, Some("a5a6".to_string()));
+    // inside the square it is caught
+    assert_eq!(solve(solver, "8/8/8/8/P3k3/8/8/7K b - - 0 1").0, Dtm::Draw);
+    assert_eq!(solve(solver, "8/8/8/8/8/8/8/k1K5 w - - 0 1").0, Dtm::Draw);
+    assert_eq!(solver.table(&Material::parse("KPK").unwrap()).unwrap().material().to_string(), "KPK");
+}
+
+#[test]
+fn test_cache() {
+    let dir = std::env::temp_dir().join(format!("rustychess-endgame-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let kqk = Material::parse("KQK").unwrap();
+    // building a table writes it to the cache, creating the directory
+    EndgameSolver::with_cache_dir(&dir).table(&kqk).unwrap();
+    let loaded = EndgameTable::load(dir.join("KQK.dtm")).unwrap();
+    assert_eq!(loaded.material(), &kqk);
+
+    // the cached table is read instead of built
+    let cached = EndgameSolver::with_cache_dir(&dir);
+    let fen = "8/8/8/3k4/8/8/8/KQ6 b - - 0 1";
+    assert_eq!(solve(&cached, fen), solve(solver(), fen));
+
+    fs::write(dir.join("broken.dtm"), b"RCDT").unwrap();
+    assert!(EndgameTable::load(dir.join("broken.dtm")).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,97 @@
+use rustychess_core::engine::{perft, Engine, Score, SearchLimits};
+use rustychess_core::game::{Game, STARTING_FEN};
+use rustychess_core::BoardStatus;
+
+#[test]
+fn test_fen_round_trip() {
+    let fens = [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+    ];
+
+    for fen in fens.iter() {
+        let game = Game::from_fen(fen).unwrap();
+        assert_eq!(game.fen(), *fen);
+    }
+
+    assert!(Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+    assert!(Game::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_err());
+}
+
+#[test]
+fn test_perft_start_position() {
+    let mut game = Game::new();
+    assert_eq!(perft(&mut game, 1), 20);
+    assert_eq!(perft(&mut game, 2), 400);
+    assert_eq!(perft(&mut game, 3), 8902);
+    assert_eq!(game.fen(), STARTING_FEN);
+}
+
+#[test]
+fn test_perft_kiwipete() {
+    let mut game =
+        Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    assert_eq!(perft(&mut game, 1), 48);
+    assert_eq!(perft(&mut game, 2), 2039);
+}
+
+#[test]
+fn test_perft_en_passant_and_promotion() {
+    let mut game = Game::from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1").unwrap();
+    assert_eq!(perft(&mut game, 3), 2812);
+
+    let mut game =
+        Game::from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1")
+            .unwrap();
+    assert_eq!(perft(&mut game, 2), 264);
+}
+
+#[test]
+fn test_move_and_rollback() {
+    let mut game = Game::new();
+    for m in ["e2e4", "d7d5", "e4d5", "c7c5", "d5c6"].iter() {
+        game.move_(m).unwrap();
+    }
+    assert_eq!(
+        game.fen(),
+        "rnbqkbnr/pp2pppp/2P5/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3"
+    );
+
+    for _ in 0..5 {
+        assert!(game.rollback_move().is_some());
+    }
+    assert_eq!(game.fen(), STARTING_FEN);
+    assert!(game.move_("e2e5").is_err());
+}
+
+#[test]
+fn test_checkmate_status() {
+    let mut game = Game::new();
+    let mut status = BoardStatus::None;
+    for m in ["f2f3", "e7e5", "g2g4", "d8h4"].iter() {
+        status = game.move_(m).unwrap();
+    }
+    assert_eq!(status, BoardStatus::Checkmate);
+}
+
+#[test]
+fn test_search_finds_mate_in_one() {
+    let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    let mut engine = Engine::new();
+    let result = engine.search(&game, &SearchLimits::depth(3), |_| {});
+
+    assert_eq!(result.best_move.unwrap().uci(), "a1a8");
+    assert_eq!(result.score, Score::Mate(1));
+}
+
+#[test]
+fn test_search_wins_material() {
+    let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/3K4 w - - 0 1").unwrap();
+    let mut engine = Engine::new();
+    let result = engine.search(&game, &SearchLimits::depth(2), |_| {});
+
+    assert_eq!(result.best_move.unwrap().uci(), "d2d5");
+}
@@ -0,0 +1,96 @@
+use rustychess_core::engine::{Engine, SearchLimits};
+use rustychess_core::fairy::{
+    fairy_kind, fairy_piece, parse_betza, register_fairy_piece, FairyPiece, StepMode, ARCHBISHOP, CAMEL,
+    GRASSHOPPER,
+};
+use rustychess_core::game::Game;
+use rustychess_core::pieces::{Color, Kind};
+
+fn targets(game: &Game, from: &str) -> Vec<String> {
+    let mut r: Vec<String> = game
+        .legal_moves()
+        .iter()
+        .filter(|m| m.from.algebraic() == from)
+        .map(|m| m.to.algebraic())
+        .collect();
+    r.sort();
+    r
+}
+
+#[test]
+fn test_parse_betza() {
+    assert_eq!(parse_betza("BN").unwrap().len(), 12);
+    assert_eq!(parse_betza("NN").unwrap().iter().filter(|s| s.range == 0).count(), 8);
+
+    let hopper = parse_betza("gQ").unwrap();
+    assert_eq!(hopper.len(), 8);
+    assert!(hopper.iter().all(|s| s.mode == StepMode::Hop));
+
+    // a Berolina-like pawn: moves one step forward and captures forward diagonally
+    let steps = parse_betza("fmWfcF").unwrap();
+    assert_eq!(steps.len(), 3);
+    assert_eq!((steps[0].ranks, steps[0].files, steps[0].mode), (0, 1, StepMode::Move));
+    assert!(steps[1..].iter().all(|s| s.files == 1 && s.mode == StepMode::Capture));
+
+    assert!(parse_betza("X").is_err());
+    assert!(parse_betza("mf").is_err());
+}
+
+#[test]
+fn test_builtin_pieces() {
+    assert_eq!(fairy_kind('a'), Some(ARCHBISHOP));
+    assert_eq!(ARCHBISHOP.pgn(), "A");
+    assert_eq!(ARCHBISHOP.symbol(Color::Black), "a");
+    assert_eq!(ARCHBISHOP.to_string(), "Archbishop");
+
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/A3K3 w - - 0 1").unwrap();
+    assert_eq!(targets(&game, "a1"), ["b2", "b3", "c2", "c3", "d4", "e5", "f6", "g7", "h8"]);
+
+    let game = Game::from_fen("4k3/8/3A4/8/8/8/8/4K3 b - - 0 1").unwrap();
+    assert!(game.is_check());
+
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/L3K3 w - - 0 1").unwrap();
+    assert_eq!(targets(&game, "a1"), ["b4", "d2"]);
+    assert_eq!(fairy_piece(CAMEL).unwrap().value, 250);
+}
+
+#[test]
+fn test_grasshopper() {
+    let game = Game::from_fen("4k3/8/8/8/8/8/1p6/G3K3 w - - 0 1").unwrap();
+    assert_eq!(game.board.get("a", 1).unwrap().kind, GRASSHOPPER);
+    assert_eq!(targets(&game, "a1"), ["c3", "f1"]);
+
+    // the hurdle may be of either color
+    let game = Game::from_fen("4k3/4p3/8/8/8/8/8/4G1K1 b - - 0 1").unwrap();
+    assert!(game.is_check());
+}
+
+#[test]
+fn test_fen_and_san() {
+    let fen = "rnbakbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBAKBNR w - - 0 1";
+    let mut game = Game::from_fen(fen).unwrap();
+    assert_eq!(game.fen(), fen);
+
+    let m = game.san_move("Ac3").unwrap();
+    assert_eq!(m.piece.kind, ARCHBISHOP);
+    assert_eq!(game.san(&m), "Ac3");
+    game.play(m);
+    assert_eq!(game.fen(), "rnbakbnr/pppppppp/8/8/8/2A5/PPPPPPPP/RNB1KBNR b - - 1 1");
+
+    let mut engine = Engine::new();
+    let result = engine.search(&game, &SearchLimits::depth(2), |_| {});
+    assert!(result.best_move.is_some());
+}
+
+#[test]
+fn test_register_piece() {
+    let nightrider = FairyPiece::new("Nightrider", 's', "NN").unwrap();
+    assert_eq!(nightrider.letter, 'S');
+    let kind = register_fairy_piece(nightrider).unwrap();
+    assert!(matches!(kind, Kind::Fairy(_)));
+    assert!(register_fairy_piece(FairyPiece::new("Other", 'S', "W").unwrap()).is_err());
+    assert!(FairyPiece::new("Rook", 'R', "R").is_err());
+
+    let game = Game::from_fen("4k3/8/8/8/8/8/8/S3K3 w - - 0 1").unwrap();
+    assert_eq!(targets(&game, "a1"), ["b3", "c2", "c5", "d7", "e3", "g4"]);
+}
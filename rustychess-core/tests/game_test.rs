@@ -0,0 +1,26 @@
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::PgnGame;
+
+#[test]
+fn test_resignation_until_takeback() {
+    let mut game = Game::new();
+    game.play(game.san_move("e4").unwrap());
+    game.declare_result(GameResult::WhiteWins, Termination::Resignation);
+    assert_eq!(game.outcome(), Some((GameResult::WhiteWins, Termination::Resignation)));
+    assert_eq!(PgnGame::from_game(&game).result, "1-0");
+
+    game.rollback_move();
+    assert_eq!(game.outcome(), None);
+}
+
+#[test]
+fn test_agreed_draw() {
+    let mut game = Game::new();
+    game.declare_result(GameResult::Draw, Termination::Agreement);
+    assert_eq!(game.outcome(), Some((GameResult::Draw, Termination::Agreement)));
+    assert_eq!(PgnGame::from_game(&game).result, "1/2-1/2");
+
+    // a new game forgets the declared result
+    game.reset();
+    assert_eq!(game.outcome(), None);
+}
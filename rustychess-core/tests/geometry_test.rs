@@ -0,0 +1,89 @@
+use rustychess_core::chessboard::Chessboard2;
+use rustychess_core::engine::perft;
+use rustychess_core::fairy::{ARCHBISHOP, CHANCELLOR};
+use rustychess_core::file::File;
+use rustychess_core::game::Game;
+use rustychess_core::pgn::Position;
+use rustychess_core::rank::Rank;
+use rustychess_core::variant::{Capablanca, Rules};
+use std::sync::Arc;
+
+fn targets(game: &Game, from: &str) -> Vec<String> {
+    let mut r: Vec<String> = game
+        .legal_moves()
+        .iter()
+        .filter(|m| m.from.algebraic() == from)
+        .map(|m| m.to.algebraic())
+        .collect();
+    r.sort();
+    r
+}
+
+#[test]
+fn test_coordinates() {
+    let pos = Position::from_algebraic("j10").unwrap();
+    assert_eq!((pos.rank, pos.file), (Rank::J, File::Tenth));
+    assert_eq!(pos.algebraic(), "j10");
+    assert_eq!(Position::from_algebraic("p16").unwrap().offset(1, 0), None);
+    assert!(Position::from_algebraic("q1").is_err());
+    assert!(Position::from_algebraic("a17").is_err());
+    assert!(Position::from_algebraic("a0").is_err());
+
+    // the edge stops a step instead of wrapping around
+    assert_eq!(Rank::A.left(), Rank::A);
+    assert_eq!(File::First.down(), File::First);
+    assert_eq!(Rank::H.right(), Rank::I);
+}
+
+#[test]
+fn test_board_size() {
+    let board = Chessboard2::from_fen("4k5/10/10/10/10/10/10/10/10/4K5").unwrap();
+    assert_eq!((board.width(), board.height()), (10, 10));
+    assert!(!board.is_standard_size());
+    assert_eq!(board.fen(), "4k5/10/10/10/10/10/10/10/10/4K5");
+
+    assert!(Chessboard2::with_size(17, 8).is_err());
+    assert!(Chessboard2::from_fen("4k3/8/8/8/8/8/8/4K4").is_err());
+    assert!(Chessboard2::from_fen("17/8/8/8/8/8/8/4K3").is_err());
+}
+
+#[test]
+fn test_moves_on_larger_board() {
+    let game = Game::from_fen("4k5/10/10/10/10/10/10/10/1P8/R3K5 w - - 0 1").unwrap();
+    assert_eq!(targets(&game, "b2"), ["b3", "b4"]);
+    assert_eq!(targets(&game, "a1")[..3], ["a10", "a2", "a3"]);
+    assert_eq!(targets(&game, "a1").len(), 12);
+
+    let mut game = Game::from_fen("4k5/1P8/10/10/10/10/10/10/10/4K5 w - - 0 1").unwrap();
+    let m = game.san_move("b10=Q").unwrap();
+    assert_eq!(m.uci(), "b9b10q");
+    assert_eq!(game.uci_move("b9b10q").unwrap().to, m.to);
+    game.play(m);
+    assert_eq!(game.fen(), "1Q2k5/10/10/10/10/10/10/10/10/4K5 b - - 0 1");
+}
+
+#[test]
+fn test_capablanca() {
+    let mut game = Game::with_rules(Arc::new(Capablanca));
+    assert_eq!(game.fen(), Capablanca.starting_fen());
+    assert_eq!(perft(&mut game, 1), 28);
+    assert_eq!(perft(&mut game, 2), 784);
+
+    // the king castles to the i-file and the rook next to it
+    let mut game = Game::with_rules(Arc::new(Capablanca));
+    game.set_fen("r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1").unwrap();
+    assert_eq!(game.fen(), "r4k3r/10/10/10/10/10/10/R4K3R w KQkq - 0 1");
+    let m = game.san_move("O-O").unwrap();
+    assert_eq!(m.uci(), "f1i1");
+    assert_eq!(game.san(&m), "O-O");
+    game.play(m);
+    assert_eq!(game.fen(), "r4k3r/10/10/10/10/10/10/R6RK1 b kq - 1 1");
+    game.play(game.san_move("O-O-O").unwrap());
+    assert_eq!(game.fen(), "2kr5r/10/10/10/10/10/10/R6RK1 w - - 2 2");
+
+    let mut game = Game::with_rules(Arc::new(Capablanca));
+    game.set_fen("5k4/P9/10/10/10/10/10/5K4 w - - 0 1").unwrap();
+    let promotions: Vec<_> = game.legal_moves().iter().filter_map(|m| m.promotion).collect();
+    assert!(promotions.contains(&ARCHBISHOP) && promotions.contains(&CHANCELLOR));
+    assert_eq!(game.san_move("a8=C").unwrap().promotion, Some(CHANCELLOR));
+}
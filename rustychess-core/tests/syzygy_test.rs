@@ -0,0 +1,163 @@
+use rustychess_core::engine::{Engine, Score, SearchLimits, TB_WIN};
+use rustychess_core::game::Game;
+use rustychess_core::pieces::Kind;
+use rustychess_core::syzygy::{Tablebase, Wdl};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// piece codes of the table files
+const KING: u8 = 6;
+const QUEEN: u8 = 5;
+const BLACK: u8 = 8;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rustychess-syzygy-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A KQvK WDL table where every position has the same value for each side to move,
+/// stored as raw values: 0 loss .. 4 win
+fn kqvk_wdl(white_to_move: u8, black_to_move: u8) -> Vec<u8> {
+    let mut r = WDL_MAGIC.to_vec();
+    // split table without pawns, leading group first
+    r.extend_from_slice(&[1, 0]);
+    for piece in [KING, QUEEN, KING | BLACK].iter() {
+        r.push(piece | piece << 4);
+    }
+    r.push(0);
+    // single value subtables for white and black to move
+    r.extend_from_slice(&[0x80, white_to_move, 0x80, black_to_move]);
+    r
+}
+
+/// A KQvK DTZ table storing `value` for white to move
+fn kqvk_dtz(flags: u8, value: u8) -> Vec<u8> {
+    let mut r = DTZ_MAGIC.to_vec();
+    r.extend_from_slice(&[1, 0]);
+    for piece in [KING, QUEEN, KING | BLACK].iter() {
+        r.push(*piece);
+    }
+    r.push(0);
+    r.extend_from_slice(&[0x80 | flags, value]);
+    r
+}
+
+fn tablebase(name: &str, dtz: Option<Vec<u8>>) -> Tablebase {
+    let dir = temp_dir(name);
+    fs::write(dir.join("KQvK.rtbw"), kqvk_wdl(4, 0)).unwrap();
+    if let Some(dtz) = dtz {
+        fs::write(dir.join("KQvK.rtbz"), dtz).unwrap();
+    }
+    fs::write(dir.join("README.txt"), "not a table").unwrap();
+    Tablebase::open(dir.to_str().unwrap()).unwrap()
+}
+
+fn wdl(tb: &Tablebase, fen: &str) -> Option<Wdl> {
+    let game = Game::from_fen(fen).unwrap();
+    tb.probe_wdl(&game.board, game.turn(), game.en_passant())
+}
+
+fn dtz(tb: &Tablebase, fen: &str) -> Option<i32> {
+    let game = Game::from_fen(fen).unwrap();
+    tb.probe_dtz(&game.board, game.turn(), game.en_passant())
+}
+
+#[test]
+fn test_open_directory() {
+    let tb = tablebase("open", Some(kqvk_dtz(0, 5)));
+    assert_eq!(tb.len(), 2);
+    assert_eq!(tb.max_pieces(), 3);
+
+    let dir = temp_dir("broken");
+    fs::write(dir.join("KRvK.rtbw"), [0u8; 16]).unwrap();
+    assert!(Tablebase::open(dir.to_str().unwrap()).is_err());
+    assert!(Tablebase::open("/nonexistent/syzygy").is_err());
+}
+
+#[test]
+fn test_probe_wdl() {
+    let tb = tablebase("wdl", None);
+    assert_eq!(wdl(&tb, "4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), Some(Wdl::Win));
+    assert_eq!(wdl(&tb, "4k3/8/8/8/8/8/8/3QK3 b - - 0 1"), Some(Wdl::Loss));
+    // black has the queen, the table is read with the colors swapped
+    assert_eq!(wdl(&tb, "3qk3/8/8/8/8/8/8/4K3 w - - 0 1"), Some(Wdl::Loss));
+    assert_eq!(wdl(&tb, "3qk3/8/8/8/8/8/8/4K3 b - - 0 1"), Some(Wdl::Win));
+
+    // the table is overruled by capturing the undefended queen
+    assert_eq!(wdl(&tb, "8/8/8/8/8/8/3kQ3/7K b - - 0 1"), Some(Wdl::Draw));
+    assert_eq!(wdl(&tb, "4k3/8/8/8/8/8/8/4K3 w - - 0 1"), Some(Wdl::Draw));
+
+    // not covered by the tables
+    assert_eq!(wdl(&tb, "4k3/8/8/8/8/8/8/3RK3 w - - 0 1"), None);
+    assert_eq!(wdl(&tb, "4k3/8/8/8/8/8/8/2RQK3 w - - 0 1"), None);
+}
+
+#[test]
+fn test_probe_dtz() {
+    // stored in moves, turned into plies
+    let tb = tablebase("dtz", Some(kqvk_dtz(0, 5)));
+    assert_eq!(dtz(&tb, "4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), Some(11));
+    // black to move is not stored, it is one move further from zeroing
+    assert_eq!(dtz(&tb, "4k3/8/8/8/8/8/8/3QK3 b - - 0 1"), Some(-12));
+    assert_eq!(dtz(&tb, "8/8/8/8/8/8/3kQ3/7K b - - 0 1"), Some(0));
+
+    let tb = tablebase("dtz-plies", Some(kqvk_dtz(4, 5)));
+    assert_eq!(dtz(&tb, "4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), Some(6));
+
+    let tb = tablebase("no-dtz", None);
+    assert_eq!(dtz(&tb, "4k3/8/8/8/8/8/8/3QK3 w - - 0 1"), None);
+}
+
+#[test]
+fn test_probe_moves() {
+    let tb = tablebase("moves", Some(kqvk_dtz(0, 5)));
+    let game = Game::from_fen("7k/Q7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+    let moves = tb.probe_moves(&game).unwrap();
+    assert_eq!(moves.len(), game.legal_moves().len());
+
+    // mates come first, they zero right away
+    let first = &moves[0];
+    assert_eq!(first.wdl, Wdl::Win);
+    assert_eq!(first.dtz, Some(1));
+    let mut after = game.clone();
+    after.play(first.m);
+    assert!(after.legal_moves().is_empty() && after.is_check());
+    let king_moves = moves.iter().filter(|t| t.m.piece.kind == Kind::King);
+    assert!(king_moves.clone().count() > 0);
+    assert!(king_moves.clone().all(|t| t.dtz == Some(13)));
+
+    // too close to the fifty move rule the win is cursed
+    let game = Game::from_fen("7k/Q7/6K1/8/8/8/8/8 w - - 90 80").unwrap();
+    let moves = tb.probe_moves(&game).unwrap();
+    assert_eq!(moves[0].wdl, Wdl::Win);
+    assert_eq!(moves.last().unwrap().wdl, Wdl::CursedWin);
+
+    let castling = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+    assert!(tb.probe_moves(&castling).is_none());
+}
+
+#[test]
+fn test_engine_probes_tablebase() {
+    let tb = Arc::new(tablebase("engine", Some(kqvk_dtz(0, 5))));
+    let mut engine = Engine::new();
+    engine.set_tablebase(Some(tb));
+
+    // taking the rook enters a won table position
+    let game = Game::from_fen("7k/8/8/8/8/8/r7/Q3K3 w - - 0 1").unwrap();
+    let result = engine.search(&game, &SearchLimits::depth(2), |_| {});
+    assert_eq!(result.best_move.map(|m| m.uci()), Some("a1a2".to_string()));
+    assert_eq!(result.score, Score::Cp(TB_WIN - 1));
+
+    // at the root only the moves closest to zeroing are searched, here the mates
+    let game = Game::from_fen("7k/Q7/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+    let result = engine.search(&game, &SearchLimits::depth(1), |_| {});
+    let mut after = game.clone();
+    after.play(result.best_move.unwrap());
+    assert!(after.legal_moves().is_empty() && after.is_check());
+}
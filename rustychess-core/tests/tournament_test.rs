@@ -0,0 +1,161 @@
+use rustychess_core::clock::TimeControl;
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::tournament::{
+    openings_from_epd, openings_from_pgn, play_game, Adjudication, InternalPlayer, MatchScore,
+    MatchSettings,
+};
+use rustychess_core::uci_client::UciClient;
+
+#[test]
+fn test_match_score_elo() {
+    let even = MatchScore { wins: 10, draws: 10, losses: 10 };
+    let (elo, margin) = even.elo().unwrap();
+    assert!(elo.abs() < 1e-9);
+    assert!(margin > 0.0);
+
+    let strong = MatchScore { wins: 50, draws: 0, losses: 0 };
+    assert!(strong.elo().is_none());
+    let mut score = MatchScore::default();
+    for _ in 0..3 {
+        score.add(GameResult::WhiteWins, true);
+    }
+    score.add(GameResult::WhiteWins, false);
+    assert_eq!(score, MatchScore { wins: 3, draws: 0, losses: 1 });
+    // 75% is about 191 Elo
+    let (elo, _) = score.elo().unwrap();
+    assert!((elo - 190.85).abs() < 0.1);
+}
+
+#[test]
+fn test_outcome_rules() {
+    let game = Game::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::Draw, Termination::InsufficientMaterial))
+    );
+
+    let game = Game::from_fen("7k/8/8/8/8/8/8/KR6 w - - 100 80").unwrap();
+    assert_eq!(game.outcome(), Some((GameResult::Draw, Termination::FiftyMoveRule)));
+
+    let mut game = Game::new();
+    for m in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+        assert_eq!(game.outcome(), None);
+        let m = game.uci_move(m).unwrap();
+        game.play(m);
+    }
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::Draw, Termination::ThreefoldRepetition))
+    );
+}
+
+#[test]
+fn test_pgn_round_trip() {
+    let text = r#"[Event "Test"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2. Nf3 (2. f4 exf4) Nc6 3. Bb5 $1 a6 4. O-O 1-0
+"#;
+    let games = PgnGame::parse_all(text);
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].tag("White"), Some("A"));
+    assert_eq!(games[0].moves.len(), 7);
+    assert_eq!(games[0].moves[0].comment.as_deref(), Some("best by test"));
+
+    let game = games[0].to_game().unwrap();
+    assert_eq!(
+        game.fen(),
+        "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 1 4"
+    );
+
+    let mut out = Vec::new();
+    games[0].write(&mut out).unwrap();
+    let again = PgnGame::parse_all(&String::from_utf8(out).unwrap());
+    assert_eq!(again[0].moves, games[0].moves);
+    assert_eq!(again[0].result, "1-0");
+}
+
+#[test]
+fn test_openings() {
+    let epd = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"e4\";\n\
+               # comment\n\
+               rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1\n";
+    let openings = openings_from_epd(epd).unwrap();
+    assert_eq!(openings.len(), 2);
+    assert_eq!(
+        openings[0].fen(),
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+    );
+
+    let openings = openings_from_pgn("1. d4 d5 2. c4 *\n\n1. e4 c5 *\n").unwrap();
+    assert_eq!(openings.len(), 2);
+    assert_eq!(openings[1].moves().len(), 2);
+}
+
+#[test]
+fn test_play_game_finishes_with_mate() {
+    let start = Game::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+    let settings = MatchSettings {
+        depth: Some(2),
+        adjudication: Adjudication {
+            resign_score: 100_000,
+            ..Adjudication::default()
+        },
+        ..MatchSettings::default()
+    };
+
+    let record = play_game(
+        &mut InternalPlayer::new(),
+        &mut InternalPlayer::new(),
+        &start,
+        &settings,
+    )
+    .unwrap();
+    assert_eq!(record.result, GameResult::WhiteWins);
+    assert_eq!(record.termination, Termination::Checkmate);
+    assert_eq!(record.pgn().tag("Result"), Some("1-0"));
+}
+
+#[test]
+fn test_play_game_with_clock() {
+    let settings = MatchSettings {
+        time_control: TimeControl::parse("5+0.1"),
+        adjudication: Adjudication {
+            max_moves: Some(3),
+            ..Adjudication::default()
+        },
+        ..MatchSettings::default()
+    };
+
+    let record = play_game(
+        &mut InternalPlayer::new(),
+        &mut InternalPlayer::new(),
+        &Game::new(),
+        &settings,
+    )
+    .unwrap();
+    let pgn = record.pgn();
+    assert_eq!(record.termination, Termination::Adjudication);
+    assert_eq!(pgn.tag("TimeControl"), Some("5+0.1"));
+    assert!(pgn.moves[0].comment.as_deref().unwrap().starts_with("[%clk 0:00:0"));
+}
+
+#[test]
+fn test_hanging_engine_loses_on_time() {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fake_uci_engine.sh");
+    let mut engine = UciClient::spawn("sh", &[script]).unwrap();
+    // the fake engine never answers a search of depth 98
+    let settings = MatchSettings {
+        time_control: TimeControl::parse("1+0"),
+        depth: Some(98),
+        ..MatchSettings::default()
+    };
+
+    let record = play_game(&mut engine, &mut InternalPlayer::new(), &Game::new(), &settings).unwrap();
+    assert_eq!(record.result, GameResult::BlackWins);
+    assert_eq!(record.termination, Termination::TimeForfeit);
+    assert!(record.game.moves().is_empty());
+}
@@ -0,0 +1,120 @@
+use rustychess_core::engine::{Score, SearchLimits};
+use rustychess_core::game::Game;
+use rustychess_core::uci_client::{go_command, parse_option, position_command, UciClient, STOP_MARGIN};
+use std::io;
+use std::time::{Duration, Instant};
+
+fn fake_engine() -> UciClient {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fake_uci_engine.sh");
+    UciClient::spawn("sh", &[script]).unwrap()
+}
+
+#[test]
+fn test_handshake() {
+    let mut client = fake_engine();
+    assert_eq!(client.name.as_deref(), Some("Fake Engine"));
+    assert_eq!(client.author.as_deref(), Some("rustychess tests"));
+    assert_eq!(client.options.len(), 2);
+    assert_eq!(client.options[0].name, "Hash");
+    assert_eq!(client.options[0].default.as_deref(), Some("16"));
+    assert_eq!(client.options[1].name, "Skill Level");
+    assert_eq!(client.options[1].default.as_deref(), Some("Full Strength"));
+
+    client.set_option("Hash", "32").unwrap();
+    client.new_game().unwrap();
+    client.quit().unwrap();
+}
+
+#[test]
+fn test_go_parses_info_and_bestmove() {
+    let mut client = fake_engine();
+    let game = Game::new();
+
+    let mut infos = Vec::new();
+    let result = client
+        .go(&game, &SearchLimits::movetime(Duration::from_millis(100)), |i| {
+            infos.push(i.clone())
+        })
+        .unwrap();
+
+    assert_eq!(infos.len(), 3);
+    assert_eq!(infos[0].string.as_deref(), Some("position startpos"));
+    assert_eq!(infos[1].score, Some(Score::Cp(20)));
+    assert_eq!(infos[1].nps, Some(1000));
+
+    let info = result.info.unwrap();
+    assert_eq!(info.depth, Some(2));
+    assert_eq!(info.seldepth, Some(3));
+    assert_eq!(info.score, Some(Score::Mate(2)));
+    assert_eq!(info.lowerbound, Some(true));
+    assert_eq!(info.nodes, Some(42));
+    assert_eq!(info.time, Some(Duration::from_millis(12)));
+    // the PV stops at the first illegal move
+    assert_eq!(info.pv.iter().map(|m| m.uci()).collect::<Vec<_>>(), ["e2e4", "e7e5"]);
+
+    assert_eq!(result.best_move.unwrap().uci(), "e2e4");
+    assert_eq!(result.ponder.unwrap().uci(), "e7e5");
+}
+
+#[test]
+fn test_go_stops_late_engine() {
+    let mut client = fake_engine();
+    let game = Game::new();
+    // the fake engine searches depth 99 until it is told to stop
+    let limits = SearchLimits {
+        depth: Some(99),
+        movetime: Some(Duration::from_millis(100)),
+        ..SearchLimits::default()
+    };
+    let start = Instant::now();
+    let result = client.go(&game, &limits, |_| {}).unwrap();
+    assert_eq!(result.best_move.unwrap().uci(), "e2e4");
+    assert!(start.elapsed() >= STOP_MARGIN);
+
+    // and does not even answer "stop" at depth 98
+    let limits = SearchLimits {
+        depth: Some(98),
+        wtime: Some(Duration::from_millis(100)),
+        btime: Some(Duration::from_secs(60)),
+        ..SearchLimits::default()
+    };
+    let error = client.go(&game, &limits, |_| {}).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_position_and_go_commands() {
+    let mut game = Game::new();
+    assert_eq!(position_command(&game), "position startpos");
+
+    game.move_("e2e4").unwrap();
+    game.move_("c7c5").unwrap();
+    assert_eq!(position_command(&game), "position startpos moves e2e4 c7c5");
+
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+    let mut game = Game::from_fen(fen).unwrap();
+    game.move_("e2e4").unwrap();
+    assert_eq!(position_command(&game), format!("position fen {} moves e2e4", fen));
+
+    let limits = SearchLimits {
+        wtime: Some(Duration::from_secs(60)),
+        btime: Some(Duration::from_secs(50)),
+        winc: Some(Duration::from_secs(1)),
+        binc: Some(Duration::from_secs(1)),
+        ..SearchLimits::default()
+    };
+    assert_eq!(go_command(&limits), "go wtime 60000 btime 50000 winc 1000 binc 1000");
+    assert_eq!(go_command(&SearchLimits::depth(5)), "go depth 5");
+}
+
+#[test]
+fn test_parse_option() {
+    let option = parse_option("option name Move Overhead type spin default 10 min 0 max 5000").unwrap();
+    assert_eq!(option.name, "Move Overhead");
+    assert_eq!(option.kind, "spin");
+    assert_eq!(option.default.as_deref(), Some("10"));
+
+    let option = parse_option("option name Clear Hash type button").unwrap();
+    assert_eq!(option.kind, "button");
+    assert_eq!(option.default, None);
+}
@@ -0,0 +1,140 @@
+use rustychess_core::engine::{Engine, Score, SearchLimits};
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::Kind;
+use rustychess_core::variant::{variant_by_name, Antichess, KingOfTheHill, RacingKings, Rules, ThreeCheck};
+use std::sync::Arc;
+
+fn play(game: &mut Game, moves: &[&str]) {
+    for m in moves {
+        let m = game.uci_move(m).unwrap();
+        game.play(m);
+    }
+}
+
+fn with_fen(rules: Arc<dyn Rules>, fen: &str) -> Game {
+    let mut game = Game::with_rules(rules);
+    game.set_fen(fen).unwrap();
+    game
+}
+
+#[test]
+fn test_variant_by_name() {
+    assert_eq!(variant_by_name("King of the Hill").unwrap().name(), "King of the Hill");
+    assert_eq!(variant_by_name("3check").unwrap().name(), "Three-check");
+    assert_eq!(variant_by_name("racingkings").unwrap().name(), "Racing Kings");
+    assert_eq!(variant_by_name("giveaway").unwrap().name(), "Antichess");
+    assert_eq!(variant_by_name("Chess960").unwrap().name(), "Standard");
+    assert!(variant_by_name("shogi").is_none());
+}
+
+#[test]
+fn test_king_of_the_hill() {
+    let mut game = with_fen(Arc::new(KingOfTheHill), "8/8/8/5k2/8/2K5/8/8 w - - 0 1");
+    assert_eq!(game.outcome(), None);
+    play(&mut game, &["c3d4"]);
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::WhiteWins, Termination::Variant("king of the hill")))
+    );
+
+    // the engine walks into the center instead of waiting
+    game.rollback_move();
+    let mut engine = Engine::new();
+    let limits = SearchLimits {
+        depth: Some(2),
+        ..SearchLimits::default()
+    };
+    let result = engine.search(&game, &limits, |_| {});
+    assert_eq!(result.best_move.unwrap().uci(), "c3d4");
+    assert_eq!(result.score, Score::Mate(1));
+}
+
+#[test]
+fn test_three_check() {
+    let mut game = Game::with_rules(Arc::new(ThreeCheck));
+    assert!(game.fen().ends_with(" 0 1 +0+0"));
+    play(&mut game, &["e2e4", "e7e5", "f1c4", "g8f6", "c4f7"]);
+    assert_eq!(game.checks_given(rustychess_core::pieces::Color::White), 1);
+    assert!(game.fen().ends_with(" +1+0"));
+
+    play(&mut game, &["e8f7", "d1h5", "g7g6", "h5e5"]);
+    assert_eq!(game.outcome(), None);
+    assert!(game.fen().ends_with(" +2+0"));
+    for _ in 0..3 {
+        game.rollback_move();
+    }
+    assert!(game.fen().ends_with(" +1+0"));
+
+    let mut game = with_fen(Arc::new(ThreeCheck), "4k3/8/8/8/8/8/8/R3K3 w - - 0 1 +2+0");
+    play(&mut game, &["a1a8"]);
+    assert_eq!(game.outcome(), Some((GameResult::WhiteWins, Termination::Variant("three checks"))));
+    assert!(Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1 2+0").is_err());
+}
+
+#[test]
+fn test_racing_kings() {
+    let game = Game::with_rules(Arc::new(RacingKings));
+    assert_eq!(game.fen(), "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1");
+    assert_eq!(game.legal_moves().len(), 21);
+
+    // moves giving check are not allowed
+    let game = with_fen(Arc::new(RacingKings), "8/8/8/8/8/8/k7/6RK w - - 0 1");
+    assert!(game.uci_move("g1g2").is_err());
+    assert!(game.uci_move("g1a1").is_err());
+    assert!(game.uci_move("g1b1").is_ok());
+
+    // black arriving right after white draws
+    let mut game = with_fen(Arc::new(RacingKings), "8/K5k1/8/8/8/8/8/8 w - - 0 1");
+    play(&mut game, &["a7a8"]);
+    assert_eq!(game.outcome(), None);
+    play(&mut game, &["g7g8"]);
+    assert_eq!(game.outcome().unwrap().0, GameResult::Draw);
+
+    let mut game = with_fen(Arc::new(RacingKings), "8/K7/8/6k1/8/8/8/8 w - - 0 1");
+    play(&mut game, &["a7b8"]);
+    assert_eq!(game.outcome().unwrap().0, GameResult::WhiteWins);
+}
+
+#[test]
+fn test_antichess() {
+    let mut game = Game::with_rules(Arc::new(Antichess));
+    assert!(!game.castling().any());
+    play(&mut game, &["e2e4", "d7d5"]);
+    // captures are compulsory
+    let moves: Vec<String> = game.legal_moves().iter().map(|m| m.uci()).collect();
+    assert_eq!(moves, vec!["e4d5"]);
+
+    // the king may be captured and pawns promote to kings
+    let game = with_fen(Arc::new(Antichess), "8/P7/8/8/8/8/8/k6R w - - 0 1");
+    let moves: Vec<String> = game.legal_moves().iter().map(|m| m.uci()).collect();
+    assert_eq!(moves, vec!["h1a1"]);
+    let game = with_fen(Arc::new(Antichess), "8/P7/8/8/8/8/8/7k w - - 0 1");
+    assert!(game.legal_moves().iter().any(|m| m.promotion == Some(Kind::King)));
+    assert!(!game.is_check());
+
+    // losing all pieces wins
+    let mut game = with_fen(Arc::new(Antichess), "8/8/8/8/8/8/8/k6R b - - 0 1");
+    play(&mut game, &["a1b1", "h1b1"]);
+    assert_eq!(
+        game.outcome(),
+        Some((GameResult::BlackWins, Termination::Variant("no moves left")))
+    );
+}
+
+#[test]
+fn test_pgn_variant_tag() {
+    let mut game = Game::with_rules(Arc::new(RacingKings));
+    play(&mut game, &["h2h3", "a2a3"]);
+    let pgn = PgnGame::from_game(&game);
+    assert_eq!(pgn.tag("Variant"), Some("Racing Kings"));
+    assert_eq!(pgn.tag("FEN"), None);
+
+    let replayed = pgn.to_game().unwrap();
+    assert_eq!(replayed.rules().name(), "Racing Kings");
+    assert_eq!(replayed.fen(), game.fen());
+
+    let mut pgn = PgnGame::new();
+    pgn.set_tag("Variant", "Shogi");
+    assert!(pgn.to_game().is_err());
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::session::Session;
+
+/// Runs shell commands from a file or stdin without the prompt, see `run_script`
+pub fn run(path: Option<&Path>, keep_going: bool, mut session: Session) -> Result<(), String> {
+    let script = match path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => {
+            let mut script = String::new();
+            io::stdin().read_to_string(&mut script).map_err(|e| e.to_string())?;
+            script
+        }
+    };
+    run_script(&script, keep_going, &mut session)
+}
+
+/// Runs the lines of `script` as shell commands. The output of each command is
+/// followed by a status line, "#<line> ok" or "#<line> error: <message>", so
+/// scripts can tell the results apart. With JSON output the object of each
+/// command tells that itself and there are no status lines. Empty lines and lines
+/// starting with '#' are skipped
+pub fn run_script(script: &str, keep_going: bool, session: &mut Session) -> Result<(), String> {
+    let mut failed = 0;
+    for (i, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match session.execute(line) {
+            Ok(()) if session.json() => {}
+            Ok(()) => writeln!(session.out, "#{} ok", i + 1).map_err(|e| e.to_string())?,
+            Err(e) => {
+                if !session.json() {
+                    writeln!(session.out, "#{} error: {}", i + 1, e).map_err(|e| e.to_string())?;
+                }
+                if !keep_going {
+                    return Err(format!("stopped at line {}: {}", i + 1, line));
+                }
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} command(s) failed", failed));
+    }
+    Ok(())
+}
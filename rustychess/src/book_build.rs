@@ -0,0 +1,67 @@
+use rustychess_core::book::BookBuilder;
+use rustychess_core::pgn::PgnGame;
+use std::fs;
+
+const USAGE: &str = "usage: rustychess book build <games.pgn>... -o <book.bin>
+    [--max-ply N] [--min-games N] [--min-elo N]";
+
+/// Runs "book" subcommands, `args` are the arguments following "book"
+pub fn run(args: &[String]) -> Result<(), String> {
+    match args.split_first() {
+        Some((cmd, rest)) if cmd == "build" => build(rest),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let mut builder = BookBuilder::new();
+    let mut inputs = Vec::new();
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut number = |flag: &str| {
+            iter.next()
+                .and_then(|v| v.parse::<u32>().ok())
+                .ok_or_else(|| format!("{} expects a number\n{}", flag, USAGE))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(iter.next().ok_or_else(|| USAGE.to_string())?.clone())
+            }
+            "--max-ply" => builder.max_ply = number(arg)? as usize,
+            "--min-games" => builder.min_games = number(arg)?,
+            "--min-elo" => builder.min_elo = Some(number(arg)?),
+            _ if arg.starts_with('-') => return Err(format!("unknown argument {}\n{}", arg, USAGE)),
+            _ => inputs.push(arg.clone()),
+        }
+    }
+
+    let output = output.ok_or_else(|| format!("missing output file\n{}", USAGE))?;
+    if inputs.is_empty() {
+        return Err(format!("missing PGN files\n{}", USAGE));
+    }
+
+    let mut skipped = 0;
+    for path in inputs.iter() {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        for (i, game) in PgnGame::parse_all(&text).iter().enumerate() {
+            // the moves before an illegal one are kept
+            if let Err(e) = builder.add_game(game) {
+                eprintln!("{}: game {}: {}", path, i + 1, e);
+                skipped += 1;
+            }
+        }
+    }
+
+    let book = builder.build();
+    book.save(&output).map_err(|e| format!("{}: {}", output, e))?;
+    println!(
+        "{} games read ({} with errors), {} entries written to {}",
+        builder.games(),
+        skipped,
+        book.len(),
+        output
+    );
+    Ok(())
+}
@@ -0,0 +1,357 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rustychess_core::board_builder::BoardBuilder;
+use rustychess_core::engine::{perft, Engine, SearchLimits};
+use rustychess_core::game::Game;
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::Color;
+use rustychess_core::variant::variant_by_name;
+
+use crate::json::{self, Json};
+use crate::session::{response, CommandError};
+
+/// Yet another chess engine. Without a subcommand it opens the interactive shell,
+/// or runs the commands piped into it
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Runs shell commands from a file without the prompt
+    #[clap(long, value_name = "FILE")]
+    pub batch: Option<PathBuf>,
+
+    /// Keeps running a batch after a command failed
+    #[clap(long)]
+    pub keep_going: bool,
+
+    /// Writes the result of every command as one JSON object on a line
+    #[clap(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Opens the shell on a game, optionally against the engine
+    Play {
+        /// Starts from this position
+        #[clap(long, conflicts_with = "pgn")]
+        fen: Option<String>,
+        /// Continues the first game of this PGN file
+        #[clap(long, value_name = "FILE")]
+        pgn: Option<PathBuf>,
+        /// The side you play
+        #[clap(long, value_enum, default_value = "white")]
+        color: Side,
+        /// Lets the engine play the other side, searching this many plies
+        #[clap(long, value_name = "DEPTH")]
+        engine: Option<u32>,
+    },
+    /// Converts the games of a PGN file
+    Convert {
+        /// The PGN file to read
+        input: PathBuf,
+        /// What to write for every game
+        #[clap(long, value_enum, default_value = "pgn")]
+        to: Format,
+        /// Writes to this file instead of stdout
+        #[clap(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Checks that PGN games replay legally and positions pass the sanity checks
+    Validate {
+        /// PGN files to check
+        files: Vec<PathBuf>,
+        /// A position to check
+        #[clap(long)]
+        fen: Option<String>,
+        /// The variant the position is played in, by its PGN name
+        #[clap(long)]
+        variant: Option<String>,
+    },
+    /// Counts the leaf nodes of the move tree
+    Perft {
+        /// Counts from this position instead of the starting position
+        #[clap(long)]
+        fen: Option<String>,
+        /// The variant to play, by its PGN name
+        #[clap(long)]
+        variant: Option<String>,
+        /// Plies to look ahead
+        #[clap(long, default_value = "4")]
+        depth: u32,
+        /// Shows the count below every legal move
+        #[clap(long)]
+        divide: bool,
+    },
+    /// Searches a position for the best move
+    Analyze {
+        /// Analyzes this position
+        #[clap(long, conflicts_with = "pgn")]
+        fen: Option<String>,
+        /// Analyzes the final position of the first game of this PGN file
+        #[clap(long, value_name = "FILE")]
+        pgn: Option<PathBuf>,
+        /// Plies to search
+        #[clap(long, default_value = "6")]
+        depth: u32,
+        /// Stops after this many milliseconds
+        #[clap(long, value_name = "MS")]
+        movetime: Option<u64>,
+    },
+    /// Speaks the Universal Chess Interface over stdin/stdout
+    Uci,
+    /// Speaks the XBoard (CECP v2) protocol over stdin/stdout
+    Xboard,
+    /// Plays engines against each other, see `rustychess match` for its options
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true, disable_help_flag = true)]
+    Match {
+        args: Vec<String>,
+    },
+    /// Builds Polyglot opening books, see `rustychess book` for its options
+    #[clap(trailing_var_arg = true, allow_hyphen_values = true, disable_help_flag = true)]
+    Book {
+        args: Vec<String>,
+    },
+}
+
+impl Command {
+    /// The name the subcommand is given on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Play { .. } => "play",
+            Command::Convert { .. } => "convert",
+            Command::Validate { .. } => "validate",
+            Command::Perft { .. } => "perft",
+            Command::Analyze { .. } => "analyze",
+            Command::Uci => "uci",
+            Command::Xboard => "xboard",
+            Command::Match { .. } => "match",
+            Command::Book { .. } => "book",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Side {
+    White,
+    Black,
+}
+
+impl Side {
+    pub fn color(self) -> Color {
+        match self {
+            Side::White => Color::White,
+            Side::Black => Color::Black,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// The games in export format
+    Pgn,
+    /// The final position of every game
+    Fen,
+    /// The moves of every game in UCI notation on one line
+    Uci,
+    /// The moves of every game in SAN on one line
+    San,
+}
+
+/// Returns the game at `fen`, at the end of the first game of the PGN file `pgn`
+/// or at the starting position
+pub fn load_game(fen: Option<&str>, pgn: Option<&Path>) -> Result<Game, String> {
+    if let Some(fen) = fen {
+        return Game::from_fen(fen).map_err(|e| e.to_string());
+    }
+    match pgn {
+        Some(path) => read_pgn(path)?
+            .first()
+            .ok_or_else(|| format!("{}: no games", path.display()))?
+            .to_game()
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        None => Ok(Game::new()),
+    }
+}
+
+fn read_pgn(path: &Path) -> Result<Vec<PgnGame>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(PgnGame::parse_all(&text))
+}
+
+pub fn convert(input: &Path, to: Format, output: Option<&Path>) -> Result<(), String> {
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?),
+        None => Box::new(io::stdout()),
+    };
+
+    for (i, pgn) in read_pgn(input)?.iter().enumerate() {
+        let game = pgn.to_game().map_err(|e| format!("game {}: {}", i + 1, e))?;
+        let written = match to {
+            Format::Pgn => {
+                let mut r = PgnGame::from_game(&game);
+                for (name, value) in pgn.tags.iter() {
+                    r.set_tag(name, value);
+                }
+                r.result = pgn.result.clone();
+                r.write(&mut writer).and_then(|_| writeln!(writer))
+            }
+            Format::Fen => writeln!(writer, "{}", game.fen()),
+            Format::Uci => {
+                let moves: Vec<String> = game.moves().iter().map(|m| m.uci()).collect();
+                writeln!(writer, "{}", moves.join(" "))
+            }
+            Format::San => {
+                let moves: Vec<String> = PgnGame::from_game(&game).moves.into_iter().map(|m| m.san).collect();
+                writeln!(writer, "{}", moves.join(" "))
+            }
+        };
+        written.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Prints a line for every game and position checked, or with `json` one object
+/// for all of them. Returns whenever all of them are valid
+pub fn validate(files: &[PathBuf], fen: Option<&str>, variant: Option<&str>, json: bool) -> Result<bool, String> {
+    let mut checks: Vec<(String, Result<(), String>)> = Vec::new();
+    if let Some(fen) = fen {
+        let mut game = match variant {
+            Some(name) => Game::with_rules(variant_by_name(name).ok_or_else(|| format!("unknown variant {}", name))?),
+            None => Game::new(),
+        };
+        let checked = game
+            .set_fen(fen)
+            .map_err(|e| e.to_string())
+            .and_then(|_| BoardBuilder::from_game(&game).build().map_err(|e| e.to_string()));
+        checks.push((fen.to_string(), checked.map(|_| ())));
+    }
+
+    for path in files {
+        for (i, pgn) in read_pgn(path)?.iter().enumerate() {
+            let source = format!("{} game {}", path.display(), i + 1);
+            checks.push((source, pgn.to_game().map(|_| ()).map_err(|e| e.to_string())));
+        }
+    }
+
+    let invalid = checks.iter().filter(|(_, checked)| checked.is_err()).count();
+    let failed = Some(CommandError::Failed(format!("{} invalid", invalid))).filter(|_| invalid > 0);
+    if json {
+        let reported: Vec<Json> = checks
+            .iter()
+            .map(|(source, checked)| {
+                Json::object(vec![
+                    ("source", source.as_str().into()),
+                    ("ok", checked.is_ok().into()),
+                    ("error", checked.as_ref().err().map(String::as_str).into()),
+                ])
+            })
+            .collect();
+        println!(
+            "{}",
+            response("validate", vec![("checks", Json::Array(reported))], failed.as_ref())
+        );
+    } else {
+        for (source, checked) in checks.iter() {
+            match checked {
+                Ok(()) => println!("{}: ok", source),
+                Err(e) => println!("{}: {}", source, e),
+            }
+        }
+        if let Some(e) = failed.as_ref() {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(invalid == 0)
+}
+
+pub fn run_perft(fen: Option<&str>, variant: Option<&str>, depth: u32, divide: bool, json: bool) -> Result<(), String> {
+    let mut game = match variant {
+        Some(name) => Game::with_rules(variant_by_name(name).ok_or_else(|| format!("unknown variant {}", name))?),
+        None => Game::new(),
+    };
+    if let Some(fen) = fen {
+        game.set_fen(fen).map_err(|e| e.to_string())?;
+    }
+
+    let start = Instant::now();
+    let mut divided = Vec::new();
+    let nodes = if divide && depth > 0 {
+        let mut nodes = 0;
+        for m in game.legal_moves() {
+            game.play(m);
+            let n = perft(&mut game, depth - 1);
+            game.rollback_move();
+            if !json {
+                println!("{}: {}", m.uci(), n);
+            }
+            divided.push((m.uci(), Json::from(n)));
+            nodes += n;
+        }
+        nodes
+    } else {
+        perft(&mut game, depth)
+    };
+
+    let elapsed = start.elapsed();
+    let nps = (nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+    if json {
+        let mut fields = vec![
+            ("fen", game.fen().into()),
+            ("depth", (depth as i64).into()),
+            ("nodes", nodes.into()),
+            ("time_ms", (elapsed.as_millis() as i64).into()),
+            ("nps", nps.into()),
+        ];
+        if divide {
+            fields.push(("divide", Json::Object(divided)));
+        }
+        println!("{}", response("perft", fields, None));
+    } else {
+        println!("nodes {} time {} ms nps {}", nodes, elapsed.as_millis(), nps);
+    }
+    Ok(())
+}
+
+/// Searches `game` printing every iteration, or with `json` only the result
+pub fn analyze(game: &Game, depth: u32, movetime: Option<u64>, json: bool) -> Result<(), String> {
+    let mut limits = SearchLimits::depth(depth);
+    limits.movetime = movetime.map(Duration::from_millis);
+
+    let mut engine = Engine::new();
+    let result = engine.search(game, &limits, |info| {
+        if json {
+            return;
+        }
+        let pv: Vec<String> = info.pv.iter().map(|m| m.uci()).collect();
+        println!(
+            "depth {} score {} nodes {} nps {} pv {}",
+            info.depth,
+            info.score,
+            info.nodes,
+            info.nps(),
+            pv.join(" ")
+        );
+    });
+
+    if json {
+        let fields = vec![
+            ("fen", game.fen().into()),
+            ("best_move", result.best_move.map(|m| json::move_(&m, &game.san(&m))).into()),
+            ("score", result.score.into()),
+        ];
+        println!("{}", response("analyze", fields, None));
+        return Ok(());
+    }
+    match result.best_move {
+        Some(m) => println!("bestmove {} ({})", m.uci(), game.san(&m)),
+        None => println!("no legal moves"),
+    }
+    Ok(())
+}
@@ -1,19 +1,210 @@
-use std::sync::Arc;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use rustyline::hint::{Hint, Hinter};
-use rustyline::Context;
-use rustyline::{Completer, Helper, Highlighter, Validator};
-//use rustyline::completion::Completer;
+use chrono::Local;
+use rustychess_core::book::Book;
+use rustychess_core::chessboard::BoardStatus;
+use rustychess_core::endgame::{EndgameSolver, MAX_ENDGAME_PIECES};
+use rustychess_core::engine::{Engine, Score, SearchLimits};
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pgn::{PgnGame, Position};
+use rustychess_core::pieces::Color;
+use rustychess_core::syzygy::Tablebase;
+use rustychess_core::uci_client::UciClient;
+
+use crate::json::{self, Json};
+use crate::session::{CommandError, Opponent, OutputFormat, Session};
 
 pub trait Command: Send + Sync {
     fn name(&self) -> &str;
-    fn complete_up_to(&self) -> usize;
-    fn run(&self, args: &[&str]);
+
+    /// How much of the name the hint completes
+    fn complete_up_to(&self) -> usize {
+        self.name().len()
+    }
+
+    /// One line on what the command does, listed by `help`
+    fn synopsis(&self) -> &str;
+
+    /// The arguments the command takes, in order
+    fn args(&self) -> &[Arg] {
+        &[]
+    }
+
+    /// Shown by `help <command>` below the usage line
+    fn description(&self) -> &str {
+        self.synopsis()
+    }
+
+    fn examples(&self) -> &[&str] {
+        &[]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError>;
+
+    /// Completions of the argument following `args` in the position of `game`,
+    /// by default what the kind of that argument allows
+    fn complete(&self, game: &Game, args: &[&str]) -> Completion {
+        let arg = match self.args().get(args.len()) {
+            Some(arg) => arg,
+            None => return Completion::Nothing,
+        };
+        match arg.kind {
+            ArgKind::Square => Completion::Words(squares(game)),
+            ArgKind::Path => Completion::Paths,
+            ArgKind::Command => Completion::Commands,
+            ArgKind::Game => Completion::Games,
+            ArgKind::Choice(words) => Completion::Words(words.iter().map(|w| w.to_string()).collect()),
+            _ => Completion::Nothing,
+        }
+    }
+
+    /// The usage line generated from the arguments, eg. "book <file> [fen]"
+    fn usage(&self) -> String {
+        let mut r = self.name().to_string();
+        for arg in self.args() {
+            if arg.optional {
+                r.push_str(&format!(" [{}]", arg.name));
+            } else {
+                r.push_str(&format!(" <{}>", arg.name));
+            }
+        }
+        r
+    }
+
+    fn usage_error(&self) -> CommandError {
+        CommandError::Usage(self.usage())
+    }
 }
 
-#[derive(Completer, Helper, Highlighter, Validator)]
-pub struct DIYHinter {
-    pub commands: Vec<Arc<dyn Command>>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    /// A square of the board, eg. "e4"
+    Square,
+    /// A move in coordinates or SAN
+    Move,
+    Path,
+    /// The name of a command
+    Command,
+    /// The name of an open game
+    Game,
+    /// A name for a new game, one word
+    Name,
+    /// A FEN string, takes the rest of the line
+    Fen,
+    /// One of the given words
+    Choice(&'static [&'static str]),
+    /// A whole number
+    Number,
+}
+
+impl ArgKind {
+    /// Returns whenever the argument takes all remaining words
+    pub fn is_rest(self) -> bool {
+        matches!(self, ArgKind::Fen)
+    }
+}
+
+/// An argument of a command as shown by `help`
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+    pub help: &'static str,
+}
+
+impl Arg {
+    pub const fn required(name: &'static str, kind: ArgKind, help: &'static str) -> Arg {
+        Arg {
+            name,
+            kind,
+            optional: false,
+            help,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kind: ArgKind, help: &'static str) -> Arg {
+        Arg {
+            name,
+            kind,
+            optional: true,
+            help,
+        }
+    }
+}
+
+/// Checks the number of arguments and the squares, command and game names among
+/// them against the arguments `cmd` declares
+pub fn check_args(cmd: &dyn Command, session: &Session, args: &[&str]) -> Result<(), CommandError> {
+    let schema = cmd.args();
+    let required = schema.iter().filter(|a| !a.optional).count();
+    let rest = schema.last().is_some_and(|a| a.kind.is_rest());
+    if args.len() < required || (!rest && args.len() > schema.len()) {
+        return Err(cmd.usage_error());
+    }
+
+    for (arg, value) in schema.iter().zip(args.iter()) {
+        let valid = match arg.kind {
+            ArgKind::Square => Position::from_algebraic(value)
+                .map(|pos| session.game.board.contains(&pos))
+                .unwrap_or(false),
+            ArgKind::Command => session.commands().iter().any(|c| c.name() == *value),
+            ArgKind::Choice(words) => words.contains(value),
+            ArgKind::Number => value.parse::<u32>().is_ok(),
+            ArgKind::Game if session.game_named(value).is_none() => {
+                return Err(CommandError::Failed(format!("No game named {}", value)));
+            }
+            ArgKind::Name if session.game_named(value).is_some() => {
+                return Err(CommandError::Failed(format!("A game named {} is open already", value)));
+            }
+            _ => true,
+        };
+        if !valid {
+            return Err(cmd.usage_error());
+        }
+    }
+    Ok(())
+}
+
+/// What the argument being typed can be completed to
+pub enum Completion {
+    Nothing,
+    /// Files and directories
+    Paths,
+    /// The names of the registered commands
+    Commands,
+    /// The names of the open games
+    Games,
+    /// The argument is one of these words
+    Words(Vec<String>),
+}
+
+/// Returns the game given by the FEN in `args`, or the game of the session without one
+fn game_or_fen(session: &Session, args: &[&str]) -> Result<Game, CommandError> {
+    if args.is_empty() {
+        return Ok(session.game.clone());
+    }
+    Game::from_fen(&args.join(" ")).map_err(|e| CommandError::Failed(e.to_string()))
+}
+
+/// The names of all squares of the board of `game`
+fn squares(game: &Game) -> Vec<String> {
+    let board = &game.board;
+    board
+        .files()
+        .flat_map(|file| board.ranks().map(move |rank| Position::new_(rank, file).algebraic()))
+        .collect()
+}
+
+/// Prints the result once the game of the session is over
+pub fn report_outcome(session: &mut Session) -> Result<(), CommandError> {
+    session.report("outcome", json::outcome(&session.game));
+    if let Some((result, termination)) = session.game.outcome() {
+        writeln!(session.out, "{} ({})", result.pgn(), termination)?;
+    }
+    Ok(())
 }
 
 pub struct HelpCommand;
@@ -23,12 +214,290 @@ impl Command for HelpCommand {
         "help"
     }
 
-    fn complete_up_to(&self) -> usize {
-        self.name().len()
+    fn synopsis(&self) -> &str {
+        "Lists the commands or explains one"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::optional("command", ArgKind::Command, "the command to explain")];
+        ARGS
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["help", "help move"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let commands = session.commands().to_vec();
+        let cmd = match args {
+            [] => {
+                let width = commands.iter().map(|c| c.usage().len()).max().unwrap_or(0);
+                writeln!(session.out, "Commands:")?;
+                for cmd in commands.iter() {
+                    writeln!(session.out, "  {:<2$}  {}", cmd.usage(), cmd.synopsis(), width)?;
+                }
+                writeln!(session.out, "Type \"help <command>\" for details")?;
+                return Ok(());
+            }
+            [name] => commands
+                .iter()
+                .find(|c| c.name() == *name)
+                .ok_or_else(|| self.usage_error())?,
+            _ => return Err(self.usage_error()),
+        };
+
+        writeln!(session.out, "Usage: {}", cmd.usage())?;
+        writeln!(session.out)?;
+        writeln!(session.out, "{}", cmd.description())?;
+        if !cmd.args().is_empty() {
+            writeln!(session.out)?;
+            writeln!(session.out, "Arguments:")?;
+            let width = cmd.args().iter().map(|a| a.name.len()).max().unwrap_or(0);
+            for arg in cmd.args() {
+                writeln!(session.out, "  {:<2$}  {}", arg.name, arg.help, width)?;
+            }
+        }
+        if !cmd.examples().is_empty() {
+            writeln!(session.out)?;
+            writeln!(session.out, "Examples:")?;
+            for example in cmd.examples() {
+                writeln!(session.out, "  {}", example)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct NewCommand;
+
+impl Command for NewCommand {
+    fn name(&self) -> &str {
+        "new"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Starts a new game"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::optional(
+            "name",
+            ArgKind::Name,
+            "opens the game next to the others under this name, saved as the PGN Event",
+        )];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Starts a new game of the variant played so far. Without a name it replaces \
+         the active game, with one it is opened next to the other games and becomes \
+         the active game."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["new", "new round3"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        match args {
+            [] => {
+                // a fresh game of the same variant
+                session.game.reset();
+                writeln!(session.out, "New game started")?;
+            }
+            [name] => {
+                let mut game = session.game.clone();
+                game.reset();
+                game.insert_metadata("name".to_string(), name.to_string());
+                session.open_game(name, game)?;
+                session.switch_game(name)?;
+                writeln!(session.out, "New game {} started", name)?;
+            }
+            _ => return Err(self.usage_error()),
+        }
+        session.report_board();
+        Ok(())
+    }
+}
+
+/// Lists the open games
+pub struct GamesCommand;
+
+impl Command for GamesCommand {
+    fn name(&self) -> &str {
+        "games"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Lists the open games"
+    }
+
+    fn description(&self) -> &str {
+        "Lists the open games with whose move it is or how they ended. The active \
+         game is marked with a star."
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        let mut reported = Vec::new();
+        for name in session.game_names() {
+            let game = match session.game_named(&name) {
+                Some(game) => game,
+                None => continue,
+            };
+            let active = name == session.active_game();
+            let state = match game.outcome() {
+                Some((result, termination)) => format!("{} ({})", result.pgn(), termination),
+                None => format!("{:?} to move", game.turn()),
+            };
+            reported.push(Json::object(vec![
+                ("name", name.as_str().into()),
+                ("active", active.into()),
+                ("turn", json::color_name(game.turn()).into()),
+                ("moves", (game.moves().len() as i64).into()),
+                ("outcome", json::outcome(game)),
+            ]));
+            let marker = if active { "*" } else { " " };
+            let line = format!("{} {:<12} {:>3} moves  {}", marker, name, game.moves().len(), state);
+            writeln!(session.out, "{}", line)?;
+        }
+        session.report("games", reported);
+        Ok(())
+    }
+}
+
+pub struct SwitchCommand;
+
+impl Command for SwitchCommand {
+    fn name(&self) -> &str {
+        "switch"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Makes another open game the active one"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::required("name", ArgKind::Game, "the game to play")];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Makes another open game the active one. The commands work on the active \
+         game, an engine playing in a game keeps playing it."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["switch main"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let name = match args {
+            [name] => *name,
+            _ => return Err(self.usage_error()),
+        };
+        session.switch_game(name)?;
+        writeln!(session.out, "Switched to {}", name)?;
+        session.report_board();
+        Ok(())
+    }
+}
+
+pub struct CloseCommand;
+
+impl Command for CloseCommand {
+    fn name(&self) -> &str {
+        "close"
     }
 
-    fn run(&self, _args: &[&str]) {
-        println!("This is the help command");
+    fn synopsis(&self) -> &str {
+        "Closes an open game"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::required("name", ArgKind::Game, "the game to close")];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Closes an open game without saving it. Closing the active game switches to \
+         the first of the others, the last open game cannot be closed."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["close round3"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let name = match args {
+            [name] => *name,
+            _ => return Err(self.usage_error()),
+        };
+        session.close_game(name)?;
+        let active = session.active_game().to_string();
+        writeln!(session.out, "Closed {}, playing {}", name, active)?;
+        Ok(())
+    }
+}
+
+pub struct CopyCommand;
+
+impl Command for CopyCommand {
+    fn name(&self) -> &str {
+        "copy"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Copies an open game under a new name"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("from", ArgKind::Game, "the game to copy"),
+            Arg::required("to", ArgKind::Name, "the name of the copy"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Opens a copy of a game with its moves and metadata under a new name, eg. to \
+         try another line. The active game stays the same, the engine does not play \
+         in the copy."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["copy main sideline"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let (from, to) = match args {
+            [from, to] => (*from, *to),
+            _ => return Err(self.usage_error()),
+        };
+        let game = session
+            .game_named(from)
+            .cloned()
+            .ok_or_else(|| CommandError::Failed(format!("No game named {}", from)))?;
+        session.open_game(to, game)?;
+        writeln!(session.out, "Copied {} to {}", from, to)?;
+        Ok(())
+    }
+}
+
+pub struct PrintCommand;
+
+impl Command for PrintCommand {
+    fn name(&self) -> &str {
+        "print"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Shows the board"
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        session.game.board.write(&mut session.out)?;
+        session.report_board();
+        Ok(())
     }
 }
 
@@ -39,54 +508,809 @@ impl Command for GetCommand {
         "get"
     }
 
-    fn complete_up_to(&self) -> usize {
-        self.name().len()
+    fn synopsis(&self) -> &str {
+        "Shows the piece on a square"
     }
 
-    fn run(&self, args: &[&str]) {
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::required("square", ArgKind::Square, "eg. e4")];
+        ARGS
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["get e1"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let square = match args {
+            [square] => *square,
+            _ => return Err(self.usage_error()),
+        };
+        let pos = Position::from_algebraic(square)
+            .ok()
+            .filter(|pos| session.game.board.contains(pos))
+            .ok_or_else(|| self.usage_error())?;
+
+        let piece = session.game.board.get_with_pos(&pos).cloned();
+        match &piece {
+            Some(piece) => writeln!(session.out, "Piece: {}", piece)?,
+            None => writeln!(session.out, "empty")?,
+        }
+        session.report("square", pos.algebraic());
+        session.report("piece", piece.as_ref().map(|p| p.kind.pgn().to_string()));
+        session.report("color", piece.as_ref().map(|p| json::color_name(p.color)));
+        Ok(())
+    }
+}
+
+/// Plays a move given as two squares, "e2e4" or in SAN, asking for the piece when
+/// a pawn promotes without one
+pub struct MoveCommand;
+
+impl Command for MoveCommand {
+    fn name(&self) -> &str {
+        "move"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Plays a move"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("move", ArgKind::Move, "the move in coordinates or SAN, or the square to move from"),
+            Arg::optional("to", ArgKind::Square, "the square to move to"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Plays a move given in coordinates, in SAN or as the squares to move from and to. \
+         When a pawn promotes without a piece given, the next line names the piece."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["move e2e4", "move e2 e4", "move Nf3", "move e7e8q", "move N@f3"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let notation = match args {
+            [from, to] => format!("{}{}", from, to),
+            [notation] => notation.to_string(),
+            _ => return Err(self.usage_error()),
+        };
+        game_going_on(session)?;
+
+        let before = session.game.clone();
+        let status = match session.game.san_move(&notation) {
+            Ok(m) => {
+                if !session.game.play_timed(m) {
+                    return Err(CommandError::GameOver(format!("{:?} lost on time", session.game.turn())));
+                }
+                session.game.status()
+            }
+            Err(_) => session
+                .game
+                .move_(&notation)
+                .map_err(|e| CommandError::IllegalMove(e.to_string()))?,
+        };
+        session.report("status", json::status(status));
+
+        if status == BoardStatus::Promote {
+            writeln!(session.out, "Promote pawn, {}", session.promotion_prompt())?;
+            let options: Vec<String> = session.promotion_options().iter().map(|kind| kind.pgn().to_string()).collect();
+            session.report("options", options);
+            return Ok(());
+        }
+        if let Some(m) = session.game.moves().back() {
+            session.report("move", json::move_(m, &before.san(m)));
+        }
+        writeln!(session.out, "ok")?;
+        session.report_board();
+        report_outcome(session)?;
+        session.engine_reply()
+    }
+
+    fn complete(&self, game: &Game, args: &[&str]) -> Completion {
+        let moves = game.legal_moves();
         match args {
-            [key] => println!("Getting key: {}", key),
-            _ => println!("Usage: get <key>"),
+            [] => Completion::Words(moves.iter().flat_map(|m| vec![m.uci(), game.san(m)]).collect()),
+            [from] => Completion::Words(
+                moves
+                    .iter()
+                    .filter(|m| !m.is_drop && m.from.algebraic() == *from)
+                    .map(|m| m.to.algebraic())
+                    .collect(),
+            ),
+            _ => Completion::Nothing,
         }
     }
 }
 
-pub struct DynCommandHint {
-    display: String,
-    complete_up_to: usize,
+pub struct MoveListCommand;
+
+impl Command for MoveListCommand {
+    fn name(&self) -> &str {
+        "movelist"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Lists the moves played in SAN"
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        let pgn = PgnGame::from_game(&session.game);
+        let first_ply = if session.game.starting_fen().contains(" b ") { 1 } else { 0 };
+        for (i, m) in pgn.moves.iter().enumerate() {
+            let ply = i + first_ply;
+            match (ply % 2, i) {
+                (0, _) => write!(session.out, "{}. {}", ply / 2 + 1, m.san)?,
+                (_, 0) => write!(session.out, "{}... {}", ply / 2 + 1, m.san)?,
+                _ => write!(session.out, " {}", m.san)?,
+            }
+            if ply % 2 == 1 || i + 1 == pgn.moves.len() {
+                writeln!(session.out)?;
+            }
+        }
+
+        let moves: Vec<Json> = session
+            .game
+            .moves()
+            .iter()
+            .zip(pgn.moves.iter())
+            .map(|(m, p)| json::move_(m, &p.san))
+            .collect();
+        session.report("moves", moves);
+        Ok(())
+    }
 }
 
-impl Hint for DynCommandHint {
-    fn display(&self) -> &str {
-        &self.display
+pub struct MetadataCommand;
+
+impl Command for MetadataCommand {
+    fn name(&self) -> &str {
+        "metadata"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Shows the metadata of the game"
     }
 
-    fn completion(&self) -> Option<&str> {
-        if self.complete_up_to > 0 {
-            Some(&self.display[..self.complete_up_to])
-        } else {
-            None
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        let mut metadata: Vec<(&String, &String)> = session.game.metadata().iter().collect();
+        metadata.sort();
+        for (key, value) in metadata.iter() {
+            writeln!(session.out, "{}: {}", key, value)?;
         }
+
+        let metadata = metadata.iter().map(|(k, v)| (k.to_string(), v.as_str().into())).collect();
+        session.report("metadata", Json::Object(metadata));
+        Ok(())
     }
 }
 
-impl Hinter for DIYHinter {
-    type Hint = DynCommandHint;
+/// Saves the game as PGN, by default to a file named after the current time in
+/// the save directory
+pub struct SaveCommand;
+
+impl Command for SaveCommand {
+    fn name(&self) -> &str {
+        "save"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Saves the game as PGN"
+    }
 
-    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
-        if line.is_empty() || pos < line.len() {
-            return None;
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::optional("file", ArgKind::Path, "the PGN file to write")];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Saves the game as PGN. Without a file the game goes to a file named after \
+         the current time in the save directory."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["save", "save games/mine.pgn"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let path = match args {
+            [path] => PathBuf::from(path),
+            [] => {
+                fs::create_dir_all(&session.settings.save_directory)?;
+                let filename = format!("file_{}.pgn", Local::now().format("%Y-%m-%d_%H-%M-%S"));
+                session.settings.save_directory.join(filename)
+            }
+            _ => return Err(self.usage_error()),
+        };
+
+        let mut pgn = PgnGame::from_game(&session.game);
+        if let Some(name) = session.game.metadata().get("name") {
+            pgn.set_tag("Event", name);
         }
+        let mut file = File::create(&path)?;
+        pgn.write(&mut file)?;
+        writeln!(session.out, "Saved to {}", path.display())?;
+        session.report("path", path.display().to_string());
+        Ok(())
+    }
+}
 
-        self.commands.iter().find_map(|cmd| {
-            if cmd.name().starts_with(line) {
-                Some(DynCommandHint {
-                    display: cmd.name().to_owned(),
-                    complete_up_to: cmd.complete_up_to().saturating_sub(0),
-                })
-            } else {
-                None
+/// Loads the first game of a PGN file
+pub struct LoadCommand;
+
+impl Command for LoadCommand {
+    fn name(&self) -> &str {
+        "load"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Loads a game from PGN"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::required("file", ArgKind::Path, "the PGN file to read")];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Replaces the game with the first game of a PGN file."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["load saves/file_2024-01-01_12-00-00.pgn"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let path = match args {
+            [path] => *path,
+            _ => return Err(self.usage_error()),
+        };
+        let text = fs::read_to_string(path).map_err(|_| CommandError::Failed("File not found".to_string()))?;
+        let pgn = PgnGame::parse_all(&text)
+            .into_iter()
+            .next()
+            .ok_or_else(|| CommandError::Failed(format!("No game in {}", path)))?;
+        let mut game = pgn.to_game().map_err(|e| CommandError::Failed(e.to_string()))?;
+        if let Some(name) = pgn.tag("Event").filter(|name| *name != "?") {
+            game.insert_metadata("name".to_string(), name.to_string());
+        }
+
+        session.game = game;
+        writeln!(session.out, "Loaded {} moves", session.game.moves().len())?;
+        session.report("path", path.to_string());
+        session.report_board();
+        Ok(())
+    }
+}
+
+pub struct BookCommand;
+
+impl Command for BookCommand {
+    fn name(&self) -> &str {
+        "book"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Lists the moves of an opening book"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("file", ArgKind::Path, "a Polyglot opening book"),
+            Arg::optional("fen", ArgKind::Fen, "the position, the current one by default"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Lists the moves a Polyglot book has for the position with their weights."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["book book.bin", "book book.bin rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let (path, fen) = args
+            .split_first()
+            .ok_or_else(|| self.usage_error())?;
+        let game = game_or_fen(session, fen)?;
+        let book = Book::open(path).map_err(|e| CommandError::Failed(format!("Cannot open {}: {}", path, e)))?;
+
+        let moves = book.moves(&game);
+        let reported: Vec<Json> = moves
+            .iter()
+            .map(|(m, weight)| {
+                Json::object(vec![
+                    ("uci", m.uci().into()),
+                    ("san", game.san(m).into()),
+                    ("weight", (*weight as i64).into()),
+                ])
+            })
+            .collect();
+        session.report("moves", reported);
+        if moves.is_empty() {
+            writeln!(session.out, "No book moves for this position")?;
+            return Ok(());
+        }
+
+        let total: u32 = moves.iter().map(|(_, w)| *w as u32).sum();
+        for (m, weight) in moves.iter() {
+            let share = if total > 0 { *weight as f64 * 100.0 / total as f64 } else { 0.0 };
+            writeln!(session.out, "{:<8} {:<6} {:>6} {:>5.1}%", game.san(m), m.uci(), weight, share)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct TbCommand;
+
+impl Command for TbCommand {
+    fn name(&self) -> &str {
+        "tb"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Probes Syzygy tablebases"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("directory", ArgKind::Path, "the directory of the tablebase files"),
+            Arg::optional("fen", ArgKind::Fen, "the position, the current one by default"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Shows win/draw/loss and the distance to zeroing of the position and every legal move."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["tb syzygy 8/8/8/8/8/2k5/2p5/2K5 w - - 0 1"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let (path, fen) = args
+            .split_first()
+            .ok_or_else(|| self.usage_error())?;
+        let game = game_or_fen(session, fen)?;
+
+        let tb = match Tablebase::open(path) {
+            Ok(tb) if tb.is_empty() => {
+                return Err(CommandError::Failed(format!("No tablebase files in {}", path)));
+            }
+            Ok(tb) => tb,
+            Err(e) => return Err(CommandError::Failed(format!("Cannot open {}: {}", path, e))),
+        };
+
+        let moves = tb
+            .probe_moves(&game)
+            .ok_or_else(|| CommandError::Failed("Position not in the tablebases".to_string()))?;
+
+        let reported: Vec<Json> = moves
+            .iter()
+            .map(|t| {
+                Json::object(vec![
+                    ("uci", t.m.uci().into()),
+                    ("san", game.san(&t.m).into()),
+                    ("wdl", t.wdl.to_string().into()),
+                    ("dtz", t.dtz.map(|d| d as i64).into()),
+                ])
+            })
+            .collect();
+        session.report("moves", reported);
+
+        let (board, turn, ep) = (&game.board, game.turn(), game.en_passant());
+        if let Some(wdl) = tb.probe_wdl(board, turn, ep) {
+            session.report("wdl", wdl.to_string());
+            session.report("dtz", tb.probe_dtz(board, turn, ep).map(|d| d as i64));
+            match tb.probe_dtz(board, turn, ep) {
+                Some(dtz) => writeln!(session.out, "Position: {}, DTZ {}", wdl, dtz)?,
+                None => writeln!(session.out, "Position: {}", wdl)?,
+            }
+        }
+        for t in moves.iter() {
+            let dtz = t.dtz.map_or("-".to_string(), |d| d.to_string());
+            writeln!(session.out, "{:<8} {:<6} {:<13} DTZ {}", game.san(&t.m), t.m.uci(), t.wdl, dtz)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tables built by the solver are kept for the session and cached in the temp directory
+pub struct SolveCommand {
+    solver: EndgameSolver,
+}
+
+impl SolveCommand {
+    pub fn new() -> SolveCommand {
+        SolveCommand {
+            solver: EndgameSolver::with_cache_dir(std::env::temp_dir().join("rustychess-endgames")),
+        }
+    }
+}
+
+impl Default for SolveCommand {
+    fn default() -> SolveCommand {
+        SolveCommand::new()
+    }
+}
+
+impl Command for SolveCommand {
+    fn name(&self) -> &str {
+        "solve"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Solves an endgame of few pieces"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[Arg::optional("fen", ArgKind::Fen, "the position, the current one by default")];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Prints the distance to mate and the best move of an endgame, building the \
+         tables it needs the first time."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["solve", "solve 8/8/8/8/8/2k5/8/2K1Q3 w - - 0 1"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let game = game_or_fen(session, args)?;
+
+        match self.solver.solve(&game) {
+            Some(solution) => {
+                writeln!(session.out, "{:?} to move: {}", game.turn(), solution.dtm)?;
+                session.report("dtm", solution.dtm.to_string());
+                if let Some(m) = solution.best_move {
+                    writeln!(session.out, "Best move: {} ({})", game.san(&m), m.uci())?;
+                    session.report("best_move", json::move_(&m, &game.san(&m)));
+                }
             }
-        })
+            None => writeln!(
+                session.out,
+                "Only positions of up to {} pieces without castling rights can be solved",
+                MAX_ENDGAME_PIECES
+            )?,
+        }
+        Ok(())
+    }
+}
+
+/// Analyses with an external UCI engine, started for every search
+pub struct EngineCommand;
+
+const ENGINE_MOVETIME: u64 = 1000;
+
+impl Command for EngineCommand {
+    fn name(&self) -> &str {
+        "engine"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Analyses the position with an external engine"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("program", ArgKind::Path, "the UCI engine to run"),
+            Arg::optional("movetime", ArgKind::Number, "milliseconds to search, 1000 by default"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Starts a UCI engine such as Stockfish, lets it search the current position \
+         and prints every iteration with a score and the best move."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["engine /usr/bin/stockfish", "engine ./engines/stockfish 5000"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let program = args.first().ok_or_else(|| self.usage_error())?;
+        let movetime = match args.get(1) {
+            Some(ms) => ms.parse().map_err(|_| self.usage_error())?,
+            None => ENGINE_MOVETIME,
+        };
+
+        let game = session.game.clone();
+        let mut client = UciClient::spawn(program, &[])
+            .map_err(|e| CommandError::Failed(format!("Cannot start {}: {}", program, e)))?;
+        let mut lines = Vec::new();
+        let limits = SearchLimits::movetime(Duration::from_millis(movetime));
+        let result = client
+            .go(&game, &limits, |info| {
+                if let (Some(depth), Some(score)) = (info.depth, info.score) {
+                    let pv: Vec<String> = info.pv.iter().map(|m| m.uci()).collect();
+                    lines.push(format!("depth {} score {} pv {}", depth, score, pv.join(" ")));
+                }
+            })
+            .map_err(|e| CommandError::Failed(format!("{} failed: {}", program, e)))?;
+        let name = client.name.clone().unwrap_or_else(|| program.to_string());
+        let _ = client.quit();
+
+        for line in lines.iter() {
+            writeln!(session.out, "{}", line)?;
+        }
+        match result.best_move {
+            Some(m) => writeln!(session.out, "{}: {} ({})", name, game.san(&m), m.uci())?,
+            None => writeln!(session.out, "{}: no move", name)?,
+        }
+        let info = result.info.unwrap_or_default();
+        session.report("engine", name);
+        session.report("best_move", result.best_move.map(|m| json::move_(&m, &game.san(&m))));
+        session.report("score", info.score);
+        session.report("depth", info.depth.map(|d| d as i64));
+        session.report("pv", info.pv.iter().map(|m| Json::from(m.uci())).collect::<Vec<Json>>());
+        Ok(())
+    }
+}
+
+/// Lets the engine take one side of the game and answer every move
+pub struct PlayCommand;
+
+const ENGINE_DEPTH: u32 = 4;
+
+impl Command for PlayCommand {
+    fn name(&self) -> &str {
+        "play"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Plays against the engine"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("color", ArgKind::Choice(&["white", "black", "off"]), "the side you play, off to stop"),
+            Arg::optional("depth", ArgKind::Number, "plies the engine searches, 4 by default"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Lets the engine play the other side of the current game. The engine moves \
+         right away when it is its turn and answers every move after that."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["play white", "play black 6", "play off"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        let color = match args.first() {
+            Some(&"white") => Color::White,
+            Some(&"black") => Color::Black,
+            Some(&"off") => {
+                session.opponent = None;
+                writeln!(session.out, "Engine stopped")?;
+                session.report("engine", Json::Null);
+                return Ok(());
+            }
+            _ => return Err(self.usage_error()),
+        };
+        let depth = match args.get(1) {
+            Some(depth) => depth.parse().map_err(|_| self.usage_error())?,
+            None => ENGINE_DEPTH,
+        };
+
+        session.opponent = Some(Opponent::new(color.switch(), depth));
+        writeln!(session.out, "You play {:?}, the engine searches {} plies", color, depth)?;
+        session.report(
+            "engine",
+            Json::object(vec![
+                ("color", json::color_name(color.switch()).into()),
+                ("depth", (depth as i64).into()),
+            ]),
+        );
+        session.engine_reply()
+    }
+}
+
+pub struct HintCommand;
+
+impl Command for HintCommand {
+    fn name(&self) -> &str {
+        "hint"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Suggests a move"
+    }
+
+    fn description(&self) -> &str {
+        "Shows the move the engine would play for the side to move."
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        let game = session.game.clone();
+        let result = match session.opponent.as_mut() {
+            Some(opponent) => opponent.search(&game),
+            None => Engine::new().search(&game, &SearchLimits::depth(ENGINE_DEPTH), |_| {}),
+        };
+        match result.best_move {
+            Some(m) => writeln!(session.out, "Hint: {} ({})", game.san(&m), result.score)?,
+            None => writeln!(session.out, "No legal moves")?,
+        }
+        session.report("move", result.best_move.map(|m| json::move_(&m, &game.san(&m))));
+        session.report("score", result.score);
+        Ok(())
+    }
+}
+
+pub struct TakebackCommand;
+
+impl Command for TakebackCommand {
+    fn name(&self) -> &str {
+        "takeback"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Takes back the last move"
+    }
+
+    fn description(&self) -> &str {
+        "Takes back the last move. Playing against the engine, your last move is taken \
+         back with its answer so it is your move again. A resigned or agreed game stays over."
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        if let Some((result, termination)) = session.game.outcome() {
+            if matches!(termination, Termination::Resignation | Termination::Agreement) {
+                return Err(CommandError::GameOver(format!(
+                    "The game is over: {} ({})",
+                    result.pgn(),
+                    termination
+                )));
+            }
+        }
+
+        // once the engine answered, the user's move is the one before its answer
+        let engine = session.opponent.as_ref().map(|o| o.color);
+        let plies = if engine.is_some() && engine != Some(session.game.turn()) {
+            2
+        } else {
+            1
+        };
+        if session.game.moves().len() < plies {
+            return Err(CommandError::Failed("No moves to take back".to_string()));
+        }
+        for _ in 0..plies {
+            session.game.rollback_move();
+        }
+        writeln!(session.out, "ok")?;
+        session.report_board();
+        session.engine_reply()
+    }
+}
+
+pub struct ResignCommand;
+
+impl Command for ResignCommand {
+    fn name(&self) -> &str {
+        "resign"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Gives up the game"
+    }
+
+    fn description(&self) -> &str {
+        "Ends the game as lost for you, or for the side to move when nobody plays against the engine."
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        game_going_on(session)?;
+        let loser = match session.opponent.as_ref() {
+            Some(o) => o.color.switch(),
+            None => session.game.turn(),
+        };
+        session
+            .game
+            .declare_result(GameResult::win_for(loser.switch()), Termination::Resignation);
+        report_outcome(session)
+    }
+}
+
+/// Offers a draw, the engine accepts unless it thinks it stands better
+pub struct DrawCommand;
+
+impl Command for DrawCommand {
+    fn name(&self) -> &str {
+        "draw"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Offers a draw"
+    }
+
+    fn description(&self) -> &str {
+        "Offers the engine a draw. It accepts when its search does not see it better \
+         off. Without the engine the draw is agreed right away."
+    }
+
+    fn run(&self, session: &mut Session, _args: &[&str]) -> Result<(), CommandError> {
+        game_going_on(session)?;
+        let game = session.game.clone();
+        if let Some(opponent) = session.opponent.as_mut() {
+            let score = opponent.search(&game).score;
+            // the score is from the side to move
+            let engine_to_move = opponent.color == game.turn();
+            let better = match score {
+                Score::Cp(v) => (v > 0) == engine_to_move && v != 0,
+                Score::Mate(n) => (n > 0) == engine_to_move,
+            };
+            session.report("accepted", !better);
+            if better {
+                writeln!(session.out, "The engine declines the draw")?;
+                return Ok(());
+            }
+            writeln!(session.out, "The engine accepts the draw")?;
+        }
+        session.game.declare_result(GameResult::Draw, Termination::Agreement);
+        report_outcome(session)
+    }
+}
+
+fn game_going_on(session: &Session) -> Result<(), CommandError> {
+    match session.game.outcome() {
+        Some((result, termination)) => Err(CommandError::GameOver(format!(
+            "The game is over: {} ({})",
+            result.pgn(),
+            termination
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Changes a setting of the session
+pub struct SetCommand;
+
+impl Command for SetCommand {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn synopsis(&self) -> &str {
+        "Changes a setting"
+    }
+
+    fn args(&self) -> &[Arg] {
+        const ARGS: &[Arg] = &[
+            Arg::required("setting", ArgKind::Choice(&["output"]), "the setting to change"),
+            Arg::required("value", ArgKind::Choice(&["text", "json"]), "its new value"),
+        ];
+        ARGS
+    }
+
+    fn description(&self) -> &str {
+        "Changes a setting of the shell. With \"output json\" every command answers \
+         with one JSON object on a line, carrying the position, the move played, \
+         the lists asked for or the error."
+    }
+
+    fn examples(&self) -> &[&str] {
+        &["set output json", "set output text"]
+    }
+
+    fn run(&self, session: &mut Session, args: &[&str]) -> Result<(), CommandError> {
+        session.settings.output = match args {
+            ["output", "text"] => OutputFormat::Text,
+            ["output", "json"] => OutputFormat::Json,
+            _ => return Err(self.usage_error()),
+        };
+        writeln!(session.out, "Output is {}", args[1])?;
+        Ok(())
     }
 }
@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use colored::*;
+use rustychess_core::game::Game;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::{Hint, Hinter};
+use rustyline::Context;
+use rustyline::{Helper, Validator};
+
+use crate::command::{Command, Completion};
+
+/// Line editor helper of the shell: hints and completes command names and their
+/// arguments, and marks what cannot be completed in red. `game` is the position
+/// moves and squares are completed in and `games` the names of the open games,
+/// the shell keeps both up to date
+#[derive(Helper, Validator)]
+pub struct DIYHinter {
+    pub commands: Vec<Arc<dyn Command>>,
+    pub game: Game,
+    pub games: Vec<String>,
+    paths: FilenameCompleter,
+}
+
+impl DIYHinter {
+    pub fn new(commands: Vec<Arc<dyn Command>>) -> DIYHinter {
+        DIYHinter {
+            commands,
+            game: Game::new(),
+            games: Vec::new(),
+            paths: FilenameCompleter::new(),
+        }
+    }
+
+    fn command(&self, name: &str) -> Option<&Arc<dyn Command>> {
+        self.commands.iter().find(|c| c.name() == name)
+    }
+
+    /// Returns whenever `word` is the start of an argument `cmd` accepts after `args`
+    fn is_valid(&self, cmd: &dyn Command, args: &[&str], word: &str) -> bool {
+        match cmd.complete(&self.game, args) {
+            Completion::Words(words) => words.iter().any(|w| w.starts_with(word)),
+            Completion::Commands => self.commands.iter().any(|c| c.name().starts_with(word)),
+            Completion::Games => self.games.iter().any(|g| g.starts_with(word)),
+            _ => true,
+        }
+    }
+}
+
+/// Splits `line` into its words and the byte offset each starts at
+fn words(line: &str) -> Vec<(usize, &str)> {
+    line.split_whitespace()
+        .map(|w| (w.as_ptr() as usize - line.as_ptr() as usize, w))
+        .collect()
+}
+
+impl Completer for DIYHinter {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let typed = &line[..pos];
+        let mut words = words(typed);
+        // the word under the cursor, empty after a space
+        let (start, word) = match words.last() {
+            Some((start, w)) if start + w.len() == pos => {
+                let last = (*start, *w);
+                words.pop();
+                last
+            }
+            _ => (pos, ""),
+        };
+
+        let mut candidates: Vec<String> = match words.split_first() {
+            None => self.commands.iter().map(|c| c.name().to_string()).collect(),
+            Some(((_, name), args)) => {
+                let cmd = match self.command(name) {
+                    Some(cmd) => cmd,
+                    None => return Ok((pos, Vec::new())),
+                };
+                let args: Vec<&str> = args.iter().map(|(_, w)| *w).collect();
+                match cmd.complete(&self.game, &args) {
+                    Completion::Nothing => Vec::new(),
+                    Completion::Paths => return self.paths.complete(line, pos, ctx),
+                    Completion::Commands => self.commands.iter().map(|c| c.name().to_string()).collect(),
+                    Completion::Games => self.games.clone(),
+                    Completion::Words(words) => words,
+                }
+            }
+        };
+        candidates.retain(|c| c.starts_with(word));
+        candidates.sort();
+        candidates.dedup();
+
+        let pairs = candidates
+            .into_iter()
+            .map(|c| Pair {
+                display: c.clone(),
+                replacement: c,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Highlighter for DIYHinter {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        // the answer to the promotion prompt is not a command
+        if self.game.pending_promotion().is_some() {
+            return Cow::Borrowed(line);
+        }
+
+        let words = words(line);
+        let cmd = words.first().and_then(|(_, name)| self.command(name));
+        let mut invalid = Vec::new();
+        for (i, (start, word)) in words.iter().enumerate() {
+            let valid = match (i, cmd) {
+                (0, _) => self.commands.iter().any(|c| c.name().starts_with(word)),
+                (_, Some(cmd)) => {
+                    let args: Vec<&str> = words[1..i].iter().map(|(_, w)| *w).collect();
+                    self.is_valid(cmd.as_ref(), &args, word)
+                }
+                _ => true,
+            };
+            if !valid {
+                invalid.push((*start, *word));
+            }
+        }
+        if invalid.is_empty() {
+            return Cow::Borrowed(line);
+        }
+
+        let mut r = String::new();
+        let mut end = 0;
+        for (start, word) in invalid {
+            r.push_str(&line[end..start]);
+            r.push_str(&word.red().to_string());
+            end = start + word.len();
+        }
+        r.push_str(&line[end..]);
+        Cow::Owned(r)
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        true
+    }
+}
+
+pub struct DynCommandHint {
+    display: String,
+    complete_up_to: usize,
+}
+
+impl Hint for DynCommandHint {
+    fn display(&self) -> &str {
+        &self.display
+    }
+
+    fn completion(&self) -> Option<&str> {
+        if self.complete_up_to > 0 {
+            Some(&self.display[..self.complete_up_to])
+        } else {
+            None
+        }
+    }
+}
+
+impl Hinter for DIYHinter {
+    type Hint = DynCommandHint;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<Self::Hint> {
+        if line.is_empty() || pos < line.len() {
+            return None;
+        }
+
+        self.commands.iter().find_map(|cmd| {
+            if cmd.name().starts_with(line) {
+                Some(DynCommandHint {
+                    display: cmd.name()[pos..].to_owned(),
+                    complete_up_to: cmd.complete_up_to().saturating_sub(pos),
+                })
+            } else {
+                None
+            }
+        })
+    }
+}
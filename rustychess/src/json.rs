@@ -0,0 +1,180 @@
+use std::fmt;
+
+use rustychess_core::chessboard::BoardStatus;
+use rustychess_core::engine::Score;
+use rustychess_core::game::{Game, Move};
+use rustychess_core::pieces::Color;
+
+/// A JSON value, written compactly on one line by `Display`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    /// Keys keep the order they were added in
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Json {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(n: i64) -> Json {
+        Json::Number(n)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Json {
+        Json::Number(n as i64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(v: Option<T>) -> Json {
+        v.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(v: Vec<T>) -> Json {
+        Json::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Score> for Json {
+    fn from(score: Score) -> Json {
+        match score {
+            Score::Cp(v) => Json::object(vec![("cp", (v as i64).into())]),
+            Score::Mate(n) => Json::object(vec![("mate", (n as i64).into())]),
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+pub fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+        Color::Unknown => "none",
+    }
+}
+
+/// The position of `game` as its FEN, side to move and the pieces on the board
+pub fn board(game: &Game) -> Vec<(&'static str, Json)> {
+    let mut pieces: Vec<_> = game.board.pieces().collect();
+    pieces.sort_by_key(|(pos, _)| (pos.file.to_index(), pos.rank.to_index()));
+    let pieces = pieces
+        .into_iter()
+        .map(|(pos, piece)| {
+            Json::object(vec![
+                ("square", pos.algebraic().into()),
+                ("piece", piece.kind.pgn().into()),
+                ("color", color_name(piece.color).into()),
+            ])
+        })
+        .collect();
+
+    vec![
+        ("fen", game.fen().into()),
+        ("turn", color_name(game.turn()).into()),
+        ("pieces", Json::Array(pieces)),
+    ]
+}
+
+/// The result of a finished game, null while it goes on
+pub fn outcome(game: &Game) -> Json {
+    match game.outcome() {
+        Some((result, termination)) => Json::object(vec![
+            ("result", result.pgn().into()),
+            ("termination", termination.to_string().into()),
+        ]),
+        None => Json::Null,
+    }
+}
+
+/// A move in both notations, `san` as it was written before the move was played
+pub fn move_(m: &Move, san: &str) -> Json {
+    Json::object(vec![("uci", m.uci().into()), ("san", san.into())])
+}
+
+pub fn status(status: BoardStatus) -> Json {
+    let name = match status {
+        BoardStatus::None => "none",
+        BoardStatus::Checkmate => "checkmate",
+        BoardStatus::Stalemate => "stalemate",
+        BoardStatus::Promote => "promote",
+        BoardStatus::EnPassant => "en_passant",
+    };
+    name.into()
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+pub mod batch;
+pub mod book_build;
+pub mod cli;
+pub mod command;
+pub mod helper;
+pub mod json;
+pub mod match_runner;
+pub mod session;
+pub mod uci;
+pub mod xboard;
+
+/// The commands of the shell, in the order `help` lists them
+pub fn registered_commands() -> Vec<Arc<dyn command::Command>> {
+    vec![
+        Arc::new(command::HelpCommand),
+        Arc::new(command::NewCommand),
+        Arc::new(command::GamesCommand),
+        Arc::new(command::SwitchCommand),
+        Arc::new(command::CloseCommand),
+        Arc::new(command::CopyCommand),
+        Arc::new(command::PrintCommand),
+        Arc::new(command::GetCommand),
+        Arc::new(command::MoveCommand),
+        Arc::new(command::MoveListCommand),
+        Arc::new(command::MetadataCommand),
+        Arc::new(command::SaveCommand),
+        Arc::new(command::LoadCommand),
+        Arc::new(command::BookCommand),
+        Arc::new(command::TbCommand),
+        Arc::new(command::SolveCommand::new()),
+        Arc::new(command::EngineCommand),
+        Arc::new(command::PlayCommand),
+        Arc::new(command::HintCommand),
+        Arc::new(command::TakebackCommand),
+        Arc::new(command::ResignCommand),
+        Arc::new(command::DrawCommand),
+        Arc::new(command::SetCommand),
+        // add more commands as needed
+    ]
+}
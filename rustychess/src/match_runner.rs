@@ -0,0 +1,177 @@
+use chrono::Local;
+use rustychess_core::clock::TimeControl;
+use rustychess_core::game::Game;
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::tournament::{
+    openings_from_epd, openings_from_pgn, play_game, InternalPlayer, MatchScore, MatchSettings,
+    Player,
+};
+use rustychess_core::uci_client::UciClient;
+use std::fs::{self, OpenOptions};
+use std::io;
+
+const USAGE: &str = "usage: rustychess match --engine1 <internal|PATH> --engine2 <internal|PATH>
+    [--games N] [--tc [MOVES/]SECONDS[+INC][:...]] [--depth N] [--openings FILE]
+    [--pgn FILE] [--resign SCORE MOVES] [--draw SCORE MOVES AFTER] [--max-moves N]";
+
+struct Options {
+    engines: [String; 2],
+    games: u32,
+    openings: Option<String>,
+    pgn: Option<String>,
+    settings: MatchSettings,
+}
+
+/// Plays a match between two engines, `args` are the arguments following "match"
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = parse_args(args)?;
+    if options.settings.time_control.is_none() && options.settings.depth.is_none() {
+        return Err(format!("either --tc or --depth is required\n{}", USAGE));
+    }
+
+    let openings = match &options.openings {
+        Some(path) => load_openings(path)?,
+        None => vec![Game::new()],
+    };
+    if openings.is_empty() {
+        return Err("no positions found in the openings file".to_string());
+    }
+
+    let mut first = load_engine(&options.engines[0])?;
+    let mut second = load_engine(&options.engines[1])?;
+    let names = (first.name(), second.name());
+    let mut score = MatchScore::default();
+
+    for round in 0..options.games {
+        // every opening is played twice, with colors swapped
+        let start = &openings[(round as usize / 2) % openings.len()];
+        let first_is_white = round.is_multiple_of(2);
+        let record = if first_is_white {
+            play_game(first.as_mut(), second.as_mut(), start, &options.settings)
+        } else {
+            play_game(second.as_mut(), first.as_mut(), start, &options.settings)
+        }
+        .map_err(|e| format!("game {} aborted: {}", round + 1, e))?;
+
+        score.add(record.result, first_is_white);
+        println!(
+            "Game {} of {}: {} vs {} {} {{{}}}",
+            round + 1,
+            options.games,
+            record.white,
+            record.black,
+            record.result.pgn(),
+            record.termination
+        );
+        println!(
+            "Score of {} vs {}: {} - {} - {}  [{:.3}] {}",
+            names.0,
+            names.1,
+            score.wins,
+            score.losses,
+            score.draws,
+            score.score(),
+            score.games()
+        );
+
+        if let Some(path) = &options.pgn {
+            let mut pgn = record.pgn();
+            pgn.set_tag("Event", "rustychess match");
+            pgn.set_tag("Date", &Local::now().format("%Y.%m.%d").to_string());
+            pgn.set_tag("Round", &(round + 1).to_string());
+            append_pgn(path, &pgn).map_err(|e| format!("{}: {}", path, e))?;
+        }
+    }
+
+    match score.elo() {
+        Some((elo, margin)) => println!("Elo difference: {:.1} +/- {:.1}", elo, margin),
+        None => println!("Elo difference: not computable from a {:.0}% score", score.score() * 100.0),
+    }
+    Ok(())
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        engines: [String::new(), String::new()],
+        games: 2,
+        openings: None,
+        pgn: None,
+        settings: MatchSettings::default(),
+    };
+
+    let mut iter = args.iter();
+    let value = |iter: &mut std::slice::Iter<String>, flag: &str| {
+        iter.next()
+            .cloned()
+            .ok_or_else(|| format!("missing value for {}\n{}", flag, USAGE))
+    };
+    let number = |v: String, flag: &str| {
+        v.parse::<i64>()
+            .map_err(|_| format!("invalid value for {}: {}", flag, v))
+    };
+
+    while let Some(flag) = iter.next() {
+        let adjudication = &mut options.settings.adjudication;
+        match flag.as_str() {
+            "--engine1" => options.engines[0] = value(&mut iter, flag)?,
+            "--engine2" => options.engines[1] = value(&mut iter, flag)?,
+            "--games" => options.games = number(value(&mut iter, flag)?, flag)?.max(1) as u32,
+            "--depth" => options.settings.depth = Some(number(value(&mut iter, flag)?, flag)?.max(1) as u32),
+            "--tc" => {
+                let tc = value(&mut iter, flag)?;
+                options.settings.time_control =
+                    Some(TimeControl::parse(&tc).ok_or_else(|| format!("invalid time control: {}", tc))?);
+            }
+            "--openings" => options.openings = Some(value(&mut iter, flag)?),
+            "--pgn" => options.pgn = Some(value(&mut iter, flag)?),
+            "--resign" => {
+                adjudication.resign_score = number(value(&mut iter, flag)?, flag)? as i32;
+                adjudication.resign_moves = number(value(&mut iter, flag)?, flag)? as u32;
+            }
+            "--draw" => {
+                adjudication.draw_score = number(value(&mut iter, flag)?, flag)? as i32;
+                adjudication.draw_moves = number(value(&mut iter, flag)?, flag)? as u32;
+                adjudication.draw_after = number(value(&mut iter, flag)?, flag)? as u32;
+            }
+            "--max-moves" => {
+                let max = number(value(&mut iter, flag)?, flag)?;
+                adjudication.max_moves = if max > 0 { Some(max as u32) } else { None };
+            }
+            _ => return Err(format!("unknown argument {}\n{}", flag, USAGE)),
+        }
+    }
+
+    if options.engines.iter().any(String::is_empty) {
+        return Err(format!("two engines are required\n{}", USAGE));
+    }
+    Ok(options)
+}
+
+fn load_engine(spec: &str) -> Result<Box<dyn Player>, String> {
+    if spec == "internal" {
+        return Ok(Box::new(InternalPlayer::new()));
+    }
+
+    // "PATH ARG..." passes arguments to the engine
+    let mut words = spec.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+    let client = UciClient::spawn(program, &args).map_err(|e| format!("{}: {}", spec, e))?;
+    Ok(Box::new(client))
+}
+
+/// A .pgn file yields the final position of every game, anything else is read as FEN/EPD lines
+fn load_openings(path: &str) -> Result<Vec<Game>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let openings = if path.to_lowercase().ends_with(".pgn") {
+        openings_from_pgn(&text)
+    } else {
+        openings_from_epd(&text)
+    };
+    openings.map_err(|e| format!("{}: {}", path, e))
+}
+
+fn append_pgn(path: &str, pgn: &PgnGame) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    pgn.write(&mut file)
+}
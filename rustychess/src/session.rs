@@ -0,0 +1,387 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use rustychess_core::engine::{Engine, SearchLimits, SearchResult};
+use rustychess_core::game::Game;
+use rustychess_core::pgn::PgnGame;
+use rustychess_core::pieces::{Color, Kind, Piece};
+
+use crate::command::{check_args, report_outcome, Command};
+use crate::json::{self, Json};
+
+/// Why a command did not run through
+#[derive(Debug)]
+pub enum CommandError {
+    /// The arguments did not fit, carries the usage line of the command
+    Usage(String),
+    UnknownCommand(String),
+    IllegalMove(String),
+    /// The game ended before the command, carries why
+    GameOver(String),
+    Failed(String),
+}
+
+impl CommandError {
+    /// The kind of the error as reported in JSON output
+    pub fn code(&self) -> &'static str {
+        match self {
+            CommandError::Usage(_) => "usage",
+            CommandError::UnknownCommand(_) => "unknown_command",
+            CommandError::IllegalMove(_) => "illegal_move",
+            CommandError::GameOver(_) => "game_over",
+            CommandError::Failed(_) => "failed",
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::Usage(usage) => write!(f, "Usage: {}", usage),
+            CommandError::UnknownCommand(name) => write!(f, "Unknown command: {}", name),
+            CommandError::IllegalMove(reason) | CommandError::GameOver(reason) | CommandError::Failed(reason) => {
+                write!(f, "{}", reason)
+            }
+        }
+    }
+}
+
+/// The JSON object answering a command: its name, whether it ran through, what it
+/// reported in `fields` and the error it failed with
+pub fn response(command: &str, mut fields: Vec<(&str, Json)>, error: Option<&CommandError>) -> Json {
+    let mut r = vec![("command", command.into()), ("ok", error.is_none().into())];
+    r.append(&mut fields);
+    if let Some(e) = error {
+        r.push((
+            "error",
+            Json::object(vec![("code", e.code().into()), ("message", e.to_string().into())]),
+        ));
+    }
+    Json::object(r)
+}
+
+impl From<io::Error> for CommandError {
+    fn from(e: io::Error) -> Self {
+        CommandError::Failed(e.to_string())
+    }
+}
+
+/// How the results of commands are written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    /// One JSON object on a line for every command
+    Json,
+}
+
+pub struct Settings {
+    /// Where `save` puts games when no path is given
+    pub save_directory: PathBuf,
+    pub output: OutputFormat,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            save_directory: PathBuf::from("saves"),
+            output: OutputFormat::Text,
+        }
+    }
+}
+
+/// Collects what a command writes while its result is reported as JSON
+#[derive(Clone, Default)]
+struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The engine playing one side of the game
+pub struct Opponent {
+    pub color: Color,
+    /// Plies searched for every move
+    pub depth: u32,
+    engine: Engine,
+}
+
+impl Opponent {
+    pub fn new(color: Color, depth: u32) -> Opponent {
+        Opponent {
+            color,
+            depth,
+            engine: Engine::new(),
+        }
+    }
+
+    /// Searches the position of `game` as deep as the engine plays
+    pub fn search(&mut self, game: &Game) -> SearchResult {
+        self.engine.search(game, &SearchLimits::depth(self.depth), |_| {})
+    }
+}
+
+/// A game left open while another one is played, with the engine playing it
+struct OpenGame {
+    game: Game,
+    opponent: Option<Opponent>,
+}
+
+/// The name of the game a session starts with
+pub const FIRST_GAME: &str = "main";
+
+/// The state shared by the commands of the shell: the game being played, the
+/// settings, the engine playing against the user and where commands write their output.
+/// Several games can be open at once, `game` and `opponent` belong to the active one
+pub struct Session {
+    pub game: Game,
+    pub settings: Settings,
+    pub opponent: Option<Opponent>,
+    pub out: Box<dyn Write>,
+    commands: Vec<Arc<dyn Command>>,
+    /// What the running command reported for JSON output
+    fields: Vec<(&'static str, Json)>,
+    /// The name of the active game
+    active: String,
+    /// The other open games by name
+    games: BTreeMap<String, OpenGame>,
+}
+
+impl Session {
+    pub fn new(commands: Vec<Arc<dyn Command>>, out: Box<dyn Write>) -> Session {
+        Session {
+            game: Game::new(),
+            settings: Settings::default(),
+            opponent: None,
+            out,
+            commands,
+            fields: Vec::new(),
+            active: FIRST_GAME.to_string(),
+            games: BTreeMap::new(),
+        }
+    }
+
+    pub fn commands(&self) -> &[Arc<dyn Command>] {
+        &self.commands
+    }
+
+    pub fn active_game(&self) -> &str {
+        &self.active
+    }
+
+    /// The names of all open games in order, the active one among them
+    pub fn game_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.games.keys().cloned().collect();
+        names.push(self.active.clone());
+        names.sort();
+        names
+    }
+
+    /// The open game named `name`
+    pub fn game_named(&self, name: &str) -> Option<&Game> {
+        if name == self.active {
+            return Some(&self.game);
+        }
+        self.games.get(name).map(|open| &open.game)
+    }
+
+    /// Opens `game` under `name` next to the active game
+    pub fn open_game(&mut self, name: &str, game: Game) -> Result<(), CommandError> {
+        if self.game_named(name).is_some() {
+            return Err(CommandError::Failed(format!("A game named {} is open already", name)));
+        }
+        self.games.insert(name.to_string(), OpenGame { game, opponent: None });
+        Ok(())
+    }
+
+    /// Makes the game named `name` the active one, the engine playing it included
+    pub fn switch_game(&mut self, name: &str) -> Result<(), CommandError> {
+        if name == self.active {
+            return Ok(());
+        }
+        let open = self
+            .games
+            .remove(name)
+            .ok_or_else(|| CommandError::Failed(format!("No game named {}", name)))?;
+        let left = OpenGame {
+            game: mem::replace(&mut self.game, open.game),
+            opponent: mem::replace(&mut self.opponent, open.opponent),
+        };
+        let name = mem::replace(&mut self.active, name.to_string());
+        self.games.insert(name, left);
+        Ok(())
+    }
+
+    /// Closes the game named `name`. Closing the active game switches to the first
+    /// of the others, the last game cannot be closed
+    pub fn close_game(&mut self, name: &str) -> Result<(), CommandError> {
+        if name == self.active {
+            let next = self
+                .games
+                .keys()
+                .next()
+                .cloned()
+                .ok_or_else(|| CommandError::Failed(format!("{} is the only game open", name)))?;
+            self.switch_game(&next)?;
+        }
+        self.games
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| CommandError::Failed(format!("No game named {}", name)))
+    }
+
+    /// The prompt of the shell, the active game and whose move it is or how it ended
+    pub fn prompt(&self) -> String {
+        match self.game.outcome() {
+            Some((result, _)) => format!("{} ({})> ", self.active, result.pgn()),
+            None => format!("{} ({:?})> ", self.active, self.game.turn()),
+        }
+    }
+
+    /// Runs one line of input. While a move waits for its promotion piece, the line
+    /// is read as the letter of that piece
+    pub fn execute(&mut self, line: &str) -> Result<(), CommandError> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = match tokens.split_first() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        if self.game.pending_promotion().is_some() {
+            return self.respond("promote", |session| session.finish_promotion(name));
+        }
+
+        self.respond(name, |session| {
+            let cmd = session
+                .commands
+                .iter()
+                .find(|c| c.name() == *name)
+                .cloned()
+                .ok_or_else(|| CommandError::UnknownCommand(name.to_string()))?;
+            check_args(cmd.as_ref(), session, args).and_then(|_| cmd.run(session, args))
+        })
+    }
+
+    /// Runs `f` as the command `name`. With JSON output what it writes and reports
+    /// is written as one object instead, errors included
+    pub fn respond<F>(&mut self, name: &str, f: F) -> Result<(), CommandError>
+    where
+        F: FnOnce(&mut Session) -> Result<(), CommandError>,
+    {
+        if self.settings.output == OutputFormat::Text {
+            let result = f(self);
+            self.out.flush()?;
+            return result;
+        }
+
+        let capture = Capture::default();
+        let out = mem::replace(&mut self.out, Box::new(capture.clone()));
+        self.fields.clear();
+        let result = f(self);
+        self.out = out;
+
+        let mut fields = vec![("game", self.active.as_str().into())];
+        fields.append(&mut self.fields);
+        let text = String::from_utf8_lossy(&capture.0.borrow()).into_owned();
+        fields.push(("text", text.into()));
+        writeln!(self.out, "{}", response(name, fields, result.as_ref().err()))?;
+        self.out.flush()?;
+        result
+    }
+
+    pub fn json(&self) -> bool {
+        self.settings.output == OutputFormat::Json
+    }
+
+    /// Adds a field to the JSON object of the running command
+    pub fn report(&mut self, key: &'static str, value: impl Into<Json>) {
+        if self.json() {
+            self.fields.retain(|(k, _)| *k != key);
+            self.fields.push((key, value.into()));
+        }
+    }
+
+    /// Reports the position of the game, see `json::board`
+    pub fn report_board(&mut self) {
+        for (key, value) in json::board(&self.game) {
+            self.report(key, value);
+        }
+    }
+
+    /// Lets the engine move while it is its turn and the game goes on
+    pub fn engine_reply(&mut self) -> Result<(), CommandError> {
+        let opponent = match self.opponent.as_mut() {
+            Some(o) if o.color == self.game.turn() && self.game.outcome().is_none() => o,
+            _ => return Ok(()),
+        };
+        let result = opponent.search(&self.game);
+        if let Some(m) = result.best_move {
+            let san = self.game.san(&m);
+            self.game.play_timed(m);
+            writeln!(self.out, "Engine plays {}", san)?;
+            self.report("engine_move", json::move_(&m, &san));
+            self.report_board();
+            report_outcome(self)?;
+        }
+        Ok(())
+    }
+
+    /// The pieces the pending promotion may turn into
+    pub fn promotion_options(&self) -> Vec<Kind> {
+        let pending = match self.game.pending_promotion() {
+            Some(m) => m,
+            None => return Vec::new(),
+        };
+        self.game
+            .legal_moves()
+            .iter()
+            .filter(|m| m.from == pending.from && m.to == pending.to)
+            .filter_map(|m| m.promotion)
+            .collect()
+    }
+
+    pub fn promotion_prompt(&self) -> String {
+        let options: Vec<String> = self
+            .promotion_options()
+            .iter()
+            .map(|kind| kind.pgn().to_string())
+            .collect();
+        format!("options: {}", options.join(", "))
+    }
+
+    fn finish_promotion(&mut self, letter: &str) -> Result<(), CommandError> {
+        let kind = match letter.chars().collect::<Vec<char>>()[..] {
+            [c] => Piece::from_fen(c).ok().map(|p| p.kind),
+            _ => None,
+        };
+        match kind {
+            Some(kind) if self.promotion_options().contains(&kind) => {
+                let from = self.game.pending_promotion().map(|m| m.from);
+                if let Some(from) = from {
+                    self.game.promote(&from, kind);
+                }
+                if let (Some(m), Some(played)) = (self.game.moves().back(), PgnGame::from_game(&self.game).moves.pop()) {
+                    self.report("move", json::move_(m, &played.san));
+                }
+                writeln!(self.out, "ok")?;
+                self.report("status", json::status(self.game.status()));
+                self.report_board();
+                report_outcome(self)?;
+                self.engine_reply()
+            }
+            _ => Err(CommandError::IllegalMove(format!("not valid, {}", self.promotion_prompt()))),
+        }
+    }
+}
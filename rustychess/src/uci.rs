@@ -0,0 +1,288 @@
+use rustychess_core::book::Book;
+use rustychess_core::engine::{Engine, SearchInfo, SearchLimits};
+use rustychess_core::game::{Game, Move};
+use rustychess_core::syzygy::Tablebase;
+use rustychess_core::variant::{all_variants, variant_by_name, Rules, Standard};
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// State of an engine speaking the Universal Chess Interface over stdin/stdout
+struct Uci {
+    game: Game,
+    stop: Arc<AtomicBool>,
+    search: Option<JoinHandle<()>>,
+    move_overhead: Duration,
+    book: Option<Book>,
+    own_book: bool,
+    tablebase: Option<Arc<Tablebase>>,
+    chess960: bool,
+    rules: Arc<dyn Rules>,
+}
+
+/// Reads UCI commands from stdin until "quit" or end of input
+pub fn run() -> io::Result<()> {
+    let mut uci = Uci::new();
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        if !uci.handle(&line?) {
+            break;
+        }
+    }
+
+    uci.stop_search();
+    Ok(())
+}
+
+impl Uci {
+    fn new() -> Uci {
+        Uci {
+            game: Game::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            search: None,
+            move_overhead: Duration::from_millis(10),
+            book: None,
+            own_book: false,
+            tablebase: None,
+            chess960: false,
+            rules: Arc::new(Standard),
+        }
+    }
+
+    /// Handles one line of input, returns false when the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = match tokens.split_first() {
+            Some(v) => v,
+            None => return true,
+        };
+
+        match *cmd {
+            "uci" => {
+                println!("id name rustychess {}", env!("CARGO_PKG_VERSION"));
+                println!("id author KLIM8D");
+                println!("option name Move Overhead type spin default 10 min 0 max 5000");
+                println!("option name OwnBook type check default false");
+                println!("option name BookFile type string default <empty>");
+                println!("option name SyzygyPath type string default <empty>");
+                println!("option name UCI_Chess960 type check default false");
+                let variants: Vec<String> = all_variants().iter().map(|v| format!("var {}", v.uci_name())).collect();
+                println!("option name UCI_Variant type combo default chess {}", variants.join(" "));
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.game = self.new_game();
+            }
+            "position" => {
+                self.stop_search();
+                self.position(args);
+            }
+            "go" => {
+                self.stop_search();
+                self.go(args);
+            }
+            "stop" => self.stop_search(),
+            "setoption" => self.set_option(args),
+            "quit" => return false,
+            _ => {}
+        }
+        true
+    }
+
+    /// position [startpos | fen <fen>] [moves <move>...]
+    fn position(&mut self, args: &[&str]) {
+        let moves_at = args.iter().position(|a| *a == "moves").unwrap_or(args.len());
+        let (setup, moves) = args.split_at(moves_at);
+
+        let game = match setup.split_first() {
+            Some((&"startpos", _)) => Ok(self.new_game()),
+            Some((&"fen", fen)) => {
+                let mut game = self.new_game();
+                game.set_fen(&fen.join(" ")).map(|_| game)
+            }
+            _ => {
+                println!("info string expected startpos or fen");
+                return;
+            }
+        };
+
+        self.game = match game {
+            Ok(g) => g,
+            Err(e) => {
+                println!("info string {}", e);
+                return;
+            }
+        };
+
+        for m in moves.iter().skip(1) {
+            match self.game.uci_move(m) {
+                Ok(v) => self.game.play(v),
+                Err(e) => {
+                    println!("info string {}: {}", e, m);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// go [depth n] [nodes n] [movetime ms] [wtime ms] [btime ms] [winc ms] [binc ms]
+    /// [movestogo n] [infinite]
+    fn go(&mut self, args: &[&str]) {
+        let mut limits = SearchLimits::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().and_then(|v| v.parse::<u64>().ok());
+            match *arg {
+                "depth" => limits.depth = value().map(|v| v as u32),
+                "nodes" => limits.nodes = value(),
+                "movetime" => limits.movetime = value().map(Duration::from_millis),
+                "wtime" => limits.wtime = value().map(Duration::from_millis),
+                "btime" => limits.btime = value().map(Duration::from_millis),
+                "winc" => limits.winc = value().map(Duration::from_millis),
+                "binc" => limits.binc = value().map(Duration::from_millis),
+                "movestogo" => limits.movestogo = value().map(|v| v as u32),
+                "infinite" => limits.infinite = true,
+                _ => {}
+            }
+        }
+
+        // book moves are played instantly, unless the GUI wants to analyse
+        if self.own_book && !limits.infinite {
+            if let Some(m) = self.book.as_ref().and_then(|b| b.random_move(&self.game)) {
+                println!("info string book move");
+                println!("bestmove {}", self.notation(&m));
+                return;
+            }
+        }
+
+        let mut engine = Engine::new();
+        engine.move_overhead = self.move_overhead;
+        engine.set_tablebase(self.tablebase.clone());
+        self.stop = engine.stop_handle();
+
+        let game = self.game.clone();
+        let stop = self.stop.clone();
+        let notation = self.notation_fn();
+        self.search = Some(thread::spawn(move || {
+            let result = engine.search(&game, &limits, |info| println!("{}", info_line(info, notation)));
+
+            // in infinite mode the best move may only be sent after "stop"
+            while limits.infinite && !stop.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            match (result.best_move, result.ponder) {
+                (Some(b), Some(p)) => println!("bestmove {} ponder {}", notation(&b), notation(&p)),
+                (Some(b), None) => println!("bestmove {}", notation(&b)),
+                _ => println!("bestmove 0000"),
+            }
+        }));
+    }
+
+    /// setoption name <id> [value <x>]
+    fn set_option(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|a| *a == "value").unwrap_or(args.len());
+        let name = args[..value_at]
+            .iter()
+            .skip_while(|a| **a == "name")
+            .cloned()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let value = args.get(value_at + 1..).unwrap_or(&[]).join(" ");
+
+        match name.to_lowercase().as_str() {
+            "move overhead" => match value.parse::<u64>() {
+                Ok(v) => self.move_overhead = Duration::from_millis(v.min(5000)),
+                Err(_) => println!("info string invalid value for {}: {}", name, value),
+            },
+            "ownbook" => self.own_book = value == "true",
+            "uci_chess960" => {
+                self.chess960 = value == "true";
+                self.game.set_chess960(self.chess960);
+            }
+            "uci_variant" => match variant_by_name(&value) {
+                Some(rules) => {
+                    self.rules = rules;
+                    self.game = self.new_game();
+                }
+                None => println!("info string unknown variant {}", value),
+            },
+            "bookfile" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.book = None;
+                    return;
+                }
+                match Book::open(&value) {
+                    Ok(book) => {
+                        println!("info string loaded {} book entries", book.len());
+                        self.book = Some(book);
+                    }
+                    Err(e) => println!("info string cannot open {}: {}", value, e),
+                }
+            }
+            "syzygypath" => {
+                if value.is_empty() || value == "<empty>" {
+                    self.tablebase = None;
+                    return;
+                }
+                match Tablebase::open(&value) {
+                    Ok(tb) => {
+                        println!(
+                            "info string found {} tablebase files, up to {} pieces",
+                            tb.len(),
+                            tb.max_pieces()
+                        );
+                        self.tablebase = Some(Arc::new(tb));
+                    }
+                    Err(e) => println!("info string cannot open {}: {}", value, e),
+                }
+            }
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    /// A game from the starting position of the selected variant. With UCI_Chess960
+    /// castling is sent as king takes rook, also from the standard position
+    fn new_game(&self) -> Game {
+        let mut game = Game::with_rules(self.rules.clone());
+        game.set_chess960(self.chess960);
+        game
+    }
+
+    /// How moves are written, castling is king takes rook with UCI_Chess960
+    fn notation_fn(&self) -> fn(&Move) -> String {
+        if self.chess960 {
+            Move::uci_chess960
+        } else {
+            Move::uci
+        }
+    }
+
+    fn notation(&self, m: &Move) -> String {
+        self.notation_fn()(m)
+    }
+
+    /// Aborts the running search, if any, and waits for its "bestmove"
+    fn stop_search(&mut self) {
+        if let Some(handle) = self.search.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            let _ = handle.join();
+        }
+    }
+}
+
+fn info_line(info: &SearchInfo, notation: fn(&Move) -> String) -> String {
+    format!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        info.depth,
+        info.score,
+        info.nodes,
+        info.nps(),
+        info.time.as_millis(),
+        info.pv.iter().map(notation).collect::<Vec<String>>().join(" ")
+    )
+}
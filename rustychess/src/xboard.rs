@@ -0,0 +1,387 @@
+use rustychess_core::clock::{Bonus, Clock, Stage, TimeControl};
+use rustychess_core::engine::{Engine, Score, SearchLimits};
+use rustychess_core::game::{Game, Move};
+use rustychess_core::pieces::Color;
+use rustychess_core::syzygy::Tablebase;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Time spent on a move when the GUI set no time control, depth or time per move
+const DEFAULT_MOVETIME: Duration = Duration::from_secs(5);
+
+/// What the engine waits for: a line from the GUI or the move of a search
+enum Event {
+    Input(io::Result<String>),
+    /// The search with this number found the move, if there was any
+    Searched(u64, Option<Move>),
+    Closed,
+}
+
+/// State of an engine speaking the XBoard (CECP v2) protocol over stdin/stdout
+struct Xboard {
+    game: Game,
+    /// The color the engine plays, `None` in force mode
+    engine_color: Option<Color>,
+    /// Set by "level", the clock of the game runs on it
+    control: Option<TimeControl>,
+    moves_per_session: u32,
+    increment: Duration,
+    movetime: Option<Duration>,
+    depth: Option<u32>,
+    time: Option<Duration>,
+    otim: Option<Duration>,
+    post: bool,
+    tablebase: Option<Arc<Tablebase>>,
+    events: Sender<Event>,
+    stop: Arc<AtomicBool>,
+    /// The number of the search whose move is awaited
+    thinking: Option<u64>,
+    searches: u64,
+}
+
+/// Reads XBoard commands from stdin until "quit" or end of input. The engine
+/// searches on another thread, so commands like "?" and "force" are read while it thinks
+pub fn run() -> io::Result<()> {
+    let (events, received) = mpsc::channel();
+    let input = events.clone();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if input.send(Event::Input(line)).is_err() {
+                return;
+            }
+        }
+        let _ = input.send(Event::Closed);
+    });
+
+    let mut xboard = Xboard::new(events);
+    for event in received {
+        match event {
+            Event::Input(line) => {
+                if !xboard.handle(&line?) {
+                    break;
+                }
+            }
+            Event::Searched(id, m) => xboard.searched(id, m),
+            Event::Closed => break,
+        }
+    }
+
+    xboard.abort_search();
+    Ok(())
+}
+
+impl Xboard {
+    fn new(events: Sender<Event>) -> Xboard {
+        Xboard {
+            game: Game::new(),
+            engine_color: Some(Color::Black),
+            control: None,
+            moves_per_session: 0,
+            increment: Duration::from_secs(0),
+            movetime: None,
+            depth: None,
+            time: None,
+            otim: None,
+            post: false,
+            tablebase: None,
+            events,
+            stop: Arc::new(AtomicBool::new(false)),
+            thinking: None,
+            searches: 0,
+        }
+    }
+
+    /// Handles one line of input, returns false when the engine should quit
+    fn handle(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (cmd, args) = match tokens.split_first() {
+            Some(v) => v,
+            None => return true,
+        };
+
+        match *cmd {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" | "ics" => {}
+            // move now, the search ends with the best move found so far
+            "?" => self.stop.store(true, Ordering::SeqCst),
+            "protover" => {
+                println!(
+                    "feature myname=\"rustychess {}\" setboard=1 usermove=1 ping=1 playother=1 colors=0 sigint=0 sigterm=0 analyze=0 egt=\"syzygy\" done=1",
+                    env!("CARGO_PKG_VERSION")
+                );
+            }
+            "new" => {
+                self.abort_search();
+                self.game = Game::new();
+                self.engine_color = Some(Color::Black);
+                self.depth = None;
+                self.movetime = None;
+                self.start_clock();
+            }
+            "force" | "result" => {
+                self.abort_search();
+                self.engine_color = None;
+            }
+            "go" => {
+                self.abort_search();
+                self.engine_color = Some(self.game.turn());
+                self.think();
+            }
+            "playother" => self.engine_color = Some(self.game.turn().switch()),
+            "usermove" => match args.first() {
+                Some(m) => self.user_move(m),
+                None => println!("Error (missing move): usermove"),
+            },
+            "setboard" => {
+                self.abort_search();
+                if let Err(e) = self.game.set_fen(&args.join(" ")) {
+                    println!("tellusererror Illegal position: {}", e);
+                }
+            }
+            "undo" => {
+                self.abort_search();
+                self.game.rollback_move();
+            }
+            "remove" => {
+                self.abort_search();
+                self.game.rollback_move();
+                self.game.rollback_move();
+            }
+            "level" => self.level(args),
+            "st" => {
+                self.movetime = args
+                    .first()
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .map(Duration::from_secs_f64)
+            }
+            "sd" => self.depth = args.first().and_then(|v| v.parse::<u32>().ok()),
+            "time" => self.time = args.first().and_then(|v| centiseconds(v)),
+            "otim" => self.otim = args.first().and_then(|v| centiseconds(v)),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => println!("pong {}", args.first().unwrap_or(&"")),
+            "egtpath" => match args.split_first() {
+                Some((&"syzygy", path)) => match Tablebase::open(&path.join(" ")) {
+                    Ok(tb) => self.tablebase = Some(Arc::new(tb)),
+                    Err(e) => println!("tellusererror Cannot open tablebases: {}", e),
+                },
+                _ => println!("Error (unsupported tablebases): egtpath"),
+            },
+            "quit" => return false,
+            _ => {
+                // without usermove=1 accepted, moves arrive as plain commands
+                if self.game.uci_move(cmd).is_ok() {
+                    self.user_move(cmd);
+                } else {
+                    println!("Error (unknown command): {}", cmd);
+                }
+            }
+        }
+        true
+    }
+
+    fn user_move(&mut self, notation: &str) {
+        self.abort_search();
+        match self.game.uci_move(notation) {
+            // a flag fall is reported below
+            Ok(m) => {
+                self.play(m);
+            }
+            Err(_) => {
+                println!("Illegal move: {}", notation);
+                return;
+            }
+        }
+
+        if self.report_result() {
+            return;
+        }
+        if self.engine_color == Some(self.game.turn()) {
+            self.think();
+        }
+    }
+
+    /// level MPS BASE INC, where BASE is minutes or minutes:seconds
+    fn level(&mut self, args: &[&str]) {
+        if args.len() < 3 {
+            println!("Error (missing arguments): level");
+            return;
+        }
+
+        let base = match minutes(args[1]) {
+            Some(base) => base,
+            None => {
+                println!("Error (bad time control): level");
+                return;
+            }
+        };
+        self.moves_per_session = args[0].parse::<u32>().unwrap_or(0);
+        self.increment = args[2]
+            .parse::<f64>()
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default();
+        self.movetime = None;
+        self.control = Some(TimeControl::new(vec![Stage {
+            moves: Some(self.moves_per_session).filter(|moves| *moves > 0),
+            time: base,
+            bonus: Bonus::Fischer(self.increment),
+        }]));
+        self.start_clock();
+    }
+
+    /// Puts the game on a new clock of the time control, both sides start with BASE
+    fn start_clock(&mut self) {
+        if let Some(control) = self.control.clone() {
+            self.time = Some(control.stage(0).time);
+            self.otim = self.time;
+            self.game.set_clock(Clock::new(control));
+        }
+    }
+
+    /// Plays a move on the clock of the game and keeps "time" and "otim" up to date
+    /// with it. Returns false when the mover's flag fell instead
+    fn play(&mut self, m: Move) -> bool {
+        let mover = self.game.turn();
+        if !self.game.play_timed(m) {
+            return false;
+        }
+        if let Some(clock) = self.game.clock() {
+            let left = Some(clock.remaining(mover));
+            if self.engine_color == Some(mover) {
+                self.time = left;
+            } else {
+                self.otim = left;
+            }
+        }
+        true
+    }
+
+    fn limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth,
+            movetime: self.movetime,
+            ..SearchLimits::default()
+        };
+        if limits.movetime.is_some() {
+            return limits;
+        }
+        if self.time.is_none() {
+            if limits.depth.is_none() {
+                limits.movetime = Some(DEFAULT_MOVETIME);
+            }
+            return limits;
+        }
+
+        let (own, other) = (self.time, self.otim);
+        match self.game.turn() {
+            Color::Black => {
+                limits.btime = own;
+                limits.wtime = other;
+            }
+            _ => {
+                limits.wtime = own;
+                limits.btime = other;
+            }
+        }
+        limits.winc = Some(self.increment);
+        limits.binc = Some(self.increment);
+
+        if self.moves_per_session > 0 {
+            let played = (self.game.fullmove_number() - 1) % self.moves_per_session;
+            limits.movestogo = Some(self.moves_per_session - played);
+        }
+        limits
+    }
+
+    /// Starts searching for the engine's move on another thread, the move is played
+    /// and sent once the search reports it
+    fn think(&mut self) {
+        if self.report_result() {
+            return;
+        }
+
+        let post = self.post;
+        let mut engine = Engine::new();
+        engine.set_tablebase(self.tablebase.clone());
+        self.stop = engine.stop_handle();
+        self.searches += 1;
+        self.thinking = Some(self.searches);
+
+        let (id, game, limits, events) = (self.searches, self.game.clone(), self.limits(), self.events.clone());
+        thread::spawn(move || {
+            let result = engine.search(&game, &limits, |info| {
+                if post {
+                    println!(
+                        "{} {} {} {} {}",
+                        info.depth,
+                        xboard_score(info.score),
+                        info.time.as_millis() / 10,
+                        info.nodes,
+                        info.pv.iter().map(Move::uci).collect::<Vec<String>>().join(" ")
+                    );
+                }
+            });
+            let _ = events.send(Event::Searched(id, result.best_move));
+        });
+    }
+
+    /// Plays and sends the move of search `id`, unless that search was aborted
+    fn searched(&mut self, id: u64, m: Option<Move>) {
+        if self.thinking != Some(id) {
+            return;
+        }
+        self.thinking = None;
+        if let Some(m) = m {
+            if self.play(m) {
+                println!("move {}", m.uci());
+            }
+            self.report_result();
+        }
+    }
+
+    /// Stops the running search without playing its move
+    fn abort_search(&mut self) {
+        if self.thinking.take().is_some() {
+            self.stop.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Sends the result if the game is over, returns whenever it was
+    fn report_result(&mut self) -> bool {
+        let (result, termination) = match self.game.outcome() {
+            Some(outcome) => outcome,
+            None => return false,
+        };
+
+        println!("{} {{{}}}", result.pgn(), termination);
+        self.engine_color = None;
+        true
+    }
+}
+
+/// Parses BASE of "level", minutes or minutes:seconds
+fn minutes(s: &str) -> Option<Duration> {
+    let (minutes, seconds) = match s.split_once(':') {
+        Some((minutes, seconds)) => (minutes, seconds.parse::<u64>().ok()?),
+        None => (s, 0),
+    };
+    let minutes = minutes.parse::<f64>().ok().filter(|m| *m >= 0.0)?;
+    Some(Duration::from_secs_f64(minutes * 60.0) + Duration::from_secs(seconds))
+}
+
+fn centiseconds(s: &str) -> Option<Duration> {
+    s.parse::<u64>().ok().map(|v| Duration::from_millis(v * 10))
+}
+
+/// XBoard expects centipawns, mates are reported as 100000 + moves
+fn xboard_score(score: Score) -> i32 {
+    match score {
+        Score::Cp(v) => v,
+        Score::Mate(n) if n > 0 => 100000 + n,
+        Score::Mate(n) => -100000 + n,
+    }
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use rustychess::batch;
+use rustychess::session::OutputFormat;
+
+const SCRIPT: &str = "# opening\nmove e2e4\n\nget z9\nmove e7e5\n";
+
+/// Runs the binary with `args`, piping `script` into it
+fn run_binary(args: &[&str], script: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rustychess"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_stop_on_error() {
+    let (mut session, out) = common::session();
+    let done = batch::run_script(SCRIPT, false, &mut session);
+    assert_eq!(done, Err("stopped at line 4: get z9".to_string()));
+    // line numbers count the skipped lines
+    assert_eq!(out.take(), "ok\n#2 ok\n#4 error: Usage: get <square>\n");
+    assert_eq!(session.game.moves().len(), 1);
+}
+
+#[test]
+fn test_keep_going() {
+    let (mut session, out) = common::session();
+    let done = batch::run_script(SCRIPT, true, &mut session);
+    assert_eq!(done, Err("1 command(s) failed".to_string()));
+    assert_eq!(out.take(), "ok\n#2 ok\n#4 error: Usage: get <square>\nok\n#5 ok\n");
+    assert_eq!(session.game.moves().len(), 2);
+
+    let (mut session, _) = common::session();
+    assert_eq!(batch::run_script("move e2e4\nmove e7e5\n", false, &mut session), Ok(()));
+}
+
+#[test]
+fn test_json_without_status_lines() {
+    let (mut session, out) = common::session();
+    session.settings.output = OutputFormat::Json;
+    batch::run_script("move e2e4\nget z9\n", true, &mut session).unwrap_err();
+    let lines: Vec<String> = out.take().lines().map(str::to_string).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("{\"command\":\"move\",\"ok\":true,"));
+    assert!(lines[1].starts_with("{\"command\":\"get\",\"ok\":false,"));
+}
+
+#[test]
+fn test_exit_status() {
+    let output = run_binary(&[], "move e2e4\nmove e7e5\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n#1 ok\nok\n#2 ok\n");
+
+    let output = run_binary(&[], SCRIPT);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "stopped at line 4: get z9\n");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("#5"));
+
+    let output = run_binary(&["--keep-going"], SCRIPT);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "1 command(s) failed\n");
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("#5 ok\n"));
+}
+
+#[test]
+fn test_json_failure() {
+    // the objects of the commands are followed by one for the batch run
+    let output = run_binary(&["--json", "--keep-going"], SCRIPT);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].starts_with(r#"{"command":"get","ok":false,"#));
+    assert_eq!(
+        lines[3],
+        r#"{"command":"batch","ok":false,"error":{"code":"failed","message":"1 command(s) failed"}}"#
+    );
+    assert!(output.stderr.is_empty());
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use rustychess::session::CommandError;
+use rustychess_core::game::Game;
+use rustychess_core::pieces::{Color, Kind};
+use rustychess_core::Position;
+
+#[test]
+fn test_print_and_get() {
+    let (mut session, out) = common::session();
+    session.execute("print").unwrap();
+    let board = out.take();
+    assert!(board.starts_with("  A  B  C  D  E  F  G  H\n"));
+    assert!(board.contains("4[ ][ ][ ][ ][ ][ ][ ][ ]\n"));
+
+    session.execute("get e1").unwrap();
+    assert_eq!(out.take(), "Piece: ♔\n");
+    session.execute("get e4").unwrap();
+    assert_eq!(out.take(), "empty\n");
+}
+
+#[test]
+fn test_move() {
+    let (mut session, out) = common::session();
+    session.execute("move e2e4").unwrap();
+    session.execute("move e7 e5").unwrap();
+    session.execute("move Nf3").unwrap();
+    assert_eq!(out.take(), "ok\nok\nok\n");
+    assert_eq!(session.game.moves().len(), 3);
+
+    match session.execute("move e2e4") {
+        Err(CommandError::IllegalMove(_)) => {}
+        other => panic!("expected an illegal move, got {:?}", other),
+    }
+    assert_eq!(session.game.moves().len(), 3);
+
+    session.execute("movelist").unwrap();
+    assert_eq!(out.take(), "1. e4 e5\n2. Nf3\n");
+}
+
+#[test]
+fn test_new() {
+    let (mut session, out) = common::session();
+    session.execute("move e2e4").unwrap();
+    out.take();
+    session.execute("new").unwrap();
+    assert_eq!(out.take(), "New game started\n");
+    assert!(session.game.moves().is_empty());
+    assert_eq!(session.game.fen(), Game::new().fen());
+}
+
+#[test]
+fn test_metadata() {
+    let (mut session, out) = common::session();
+    session.game.insert_metadata("name".to_string(), "demo".to_string());
+    session.game.insert_metadata("event".to_string(), "club".to_string());
+    session.execute("metadata").unwrap();
+    // the keys in order, the creation time the game was given first
+    let metadata = out.take();
+    assert!(metadata.starts_with("created_at: "));
+    assert!(metadata.ends_with("\nevent: club\nname: demo\n"));
+}
+
+#[test]
+fn test_save_and_load() {
+    let dir = std::env::temp_dir().join(format!("rustychess-command-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("game.pgn");
+    let path = path.to_str().unwrap();
+
+    let (mut session, out) = common::session();
+    for m in ["e4", "e5", "Nf3"].iter() {
+        session.execute(&format!("move {}", m)).unwrap();
+    }
+    let fen = session.game.fen();
+    out.take();
+    session.execute(&format!("save {}", path)).unwrap();
+    assert_eq!(out.take(), format!("Saved to {}\n", path));
+
+    session.execute("new").unwrap();
+    out.take();
+    session.execute(&format!("load {}", path)).unwrap();
+    assert_eq!(out.take(), "Loaded 3 moves\n");
+    assert_eq!(session.game.fen(), fen);
+
+    match session.execute(&format!("load {}", dir.join("missing.pgn").display())) {
+        Err(CommandError::Failed(message)) => assert_eq!(message, "File not found"),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_promotion_prompt() {
+    let (mut session, out) = common::session();
+    session.game = Game::from_fen("4k3/P7/8/8/8/8/7p/4K3 w - - 0 1").unwrap();
+    session.execute("move a7a8").unwrap();
+    assert_eq!(out.take(), "Promote pawn, options: Q, R, B, N\n");
+    assert!(session.game.pending_promotion().is_some());
+
+    // anything but the letter of a piece asks again, K is the king now and no longer the knight
+    for letter in ["x", "k"].iter() {
+        match session.execute(letter) {
+            Err(CommandError::IllegalMove(message)) => assert_eq!(message, "not valid, options: Q, R, B, N"),
+            other => panic!("expected the prompt again, got {:?}", other),
+        }
+        assert!(session.game.pending_promotion().is_some());
+    }
+
+    session.execute("n").unwrap();
+    assert_eq!(out.take(), "ok\n");
+    assert!(session.game.pending_promotion().is_none());
+    let piece = session.game.board.get_with_pos(&Position::from_algebraic("a8").unwrap()).unwrap();
+    assert_eq!((piece.kind, piece.color), (Kind::Knight, Color::White));
+}
+
+#[test]
+fn test_external_engine() {
+    let script = concat!(env!("CARGO_MANIFEST_DIR"), "/../rustychess-core/tests/data/fake_uci_engine.sh");
+    let (mut session, out) = common::session();
+    session.execute(&format!("engine {} 100", script)).unwrap();
+    assert_eq!(
+        out.take(),
+        "depth 1 score cp 20 pv e2e4\ndepth 2 score mate 2 pv e2e4 e7e5\nFake Engine: e4 (e2e4)\n"
+    );
+
+    match session.execute("engine /nonexistent/engine") {
+        Err(CommandError::Failed(message)) => assert!(message.starts_with("Cannot start /nonexistent/engine: ")),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
@@ -0,0 +1,34 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use rustychess::registered_commands;
+use rustychess::session::Session;
+
+/// Keeps what a session writes for the test to read
+#[derive(Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    /// Returns what was written since the last call
+    pub fn take(&self) -> String {
+        String::from_utf8(self.0.borrow_mut().drain(..).collect()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A session with all commands registered, writing to the returned output
+pub fn session() -> (Session, Output) {
+    let out = Output::default();
+    (Session::new(registered_commands(), Box::new(out.clone())), out)
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use rustychess::session::CommandError;
+
+/// The message a command failed with
+fn failure(result: Result<(), CommandError>) -> String {
+    match result {
+        Err(CommandError::Failed(message)) => message,
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_new_and_switch() {
+    let (mut session, out) = common::session();
+    assert_eq!(session.prompt(), "main (White)> ");
+    session.execute("move e2e4").unwrap();
+    assert_eq!(session.prompt(), "main (Black)> ");
+    out.take();
+
+    session.execute("new demo").unwrap();
+    assert_eq!(out.take(), "New game demo started\n");
+    assert_eq!(session.active_game(), "demo");
+    assert_eq!(session.prompt(), "demo (White)> ");
+    assert!(session.game.moves().is_empty());
+    assert_eq!(session.game.metadata().get("name").map(String::as_str), Some("demo"));
+
+    session.execute("games").unwrap();
+    assert_eq!(
+        out.take(),
+        "* demo           0 moves  White to move\n  main           1 moves  Black to move\n"
+    );
+
+    session.execute("switch main").unwrap();
+    assert_eq!(out.take(), "Switched to main\n");
+    assert_eq!(session.game.moves().len(), 1);
+    assert_eq!(session.game_names(), vec!["demo".to_string(), "main".to_string()]);
+
+    assert_eq!(failure(session.execute("switch nope")), "No game named nope");
+    assert_eq!(failure(session.execute("new demo")), "A game named demo is open already");
+}
+
+#[test]
+fn test_copy() {
+    let (mut session, out) = common::session();
+    session.execute("move e2e4").unwrap();
+    out.take();
+    session.execute("copy main backup").unwrap();
+    assert_eq!(out.take(), "Copied main to backup\n");
+    assert_eq!(session.active_game(), "main");
+
+    // the copy goes on by itself
+    session.execute("move e7e5").unwrap();
+    assert_eq!(session.game_named("backup").unwrap().moves().len(), 1);
+    assert_eq!(session.game.moves().len(), 2);
+
+    assert_eq!(failure(session.execute("copy main backup")), "A game named backup is open already");
+    assert_eq!(failure(session.execute("copy nope other")), "No game named nope");
+}
+
+#[test]
+fn test_close() {
+    let (mut session, out) = common::session();
+    session.execute("new demo").unwrap();
+    session.execute("new other").unwrap();
+    out.take();
+
+    session.execute("close demo").unwrap();
+    assert_eq!(out.take(), "Closed demo, playing other\n");
+    assert_eq!(session.game_names(), vec!["main".to_string(), "other".to_string()]);
+
+    // closing the active game switches to another one
+    session.execute("close other").unwrap();
+    assert_eq!(out.take(), "Closed other, playing main\n");
+    assert_eq!(session.prompt(), "main (White)> ");
+
+    assert_eq!(failure(session.execute("close main")), "main is the only game open");
+}
+
+#[test]
+fn test_prompt_of_finished_game() {
+    let (mut session, _) = common::session();
+    session.execute("new demo").unwrap();
+    session.execute("resign").unwrap();
+    assert_eq!(session.prompt(), "demo (0-1)> ");
+    session.execute("switch main").unwrap();
+    assert_eq!(session.prompt(), "main (White)> ");
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use rustychess::session::CommandError;
+
+/// The usage line a command fails with
+fn usage_error(line: &str) -> String {
+    let (mut session, _) = common::session();
+    match session.execute(line) {
+        Err(e @ CommandError::Usage(_)) => e.to_string(),
+        other => panic!("expected the usage of {:?}, got {:?}", line, other),
+    }
+}
+
+#[test]
+fn test_help_lists_commands() {
+    let (mut session, out) = common::session();
+    session.execute("help").unwrap();
+    let help = out.take();
+    let lines: Vec<&str> = help.lines().collect();
+    assert_eq!(lines.first(), Some(&"Commands:"));
+    assert_eq!(lines.last(), Some(&"Type \"help <command>\" for details"));
+    // a line for every command, its usage and synopsis in columns
+    assert_eq!(lines.len(), session.commands().len() + 2);
+    for (line, cmd) in lines[1..].iter().zip(session.commands()) {
+        assert!(line.starts_with(&format!("  {} ", cmd.usage())), "{:?}", line);
+        assert!(line.ends_with(&format!("  {}", cmd.synopsis())), "{:?}", line);
+    }
+    assert!(help.contains("\n  get <square>  "));
+}
+
+#[test]
+fn test_help_command() {
+    let (mut session, out) = common::session();
+    session.execute("help get").unwrap();
+    assert_eq!(
+        out.take(),
+        "Usage: get <square>\n\
+         \n\
+         Shows the piece on a square\n\
+         \n\
+         Arguments:\n  square  eg. e4\n\
+         \n\
+         Examples:\n  get e1\n"
+    );
+}
+
+#[test]
+fn test_usage_on_bad_arguments() {
+    // missing, invalid and extra arguments
+    assert_eq!(usage_error("get"), "Usage: get <square>");
+    assert_eq!(usage_error("get z9"), "Usage: get <square>");
+    assert_eq!(usage_error("get e4 e5"), "Usage: get <square>");
+    assert_eq!(usage_error("move"), "Usage: move <move> [to]");
+    assert_eq!(usage_error("play blue"), "Usage: play <color> [depth]");
+    assert_eq!(usage_error("set output xml"), "Usage: set <setting> <value>");
+    assert_eq!(usage_error("play white deep"), "Usage: play <color> [depth]");
+    assert_eq!(usage_error("help nope"), "Usage: help [command]");
+    assert_eq!(usage_error("book"), "Usage: book <file> [fen]");
+}
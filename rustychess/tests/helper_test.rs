@@ -0,0 +1,23 @@
+use rustychess::helper::DIYHinter;
+use rustychess::registered_commands;
+use rustyline::hint::{Hint, Hinter};
+use rustyline::history::DefaultHistory;
+use rustyline::Context;
+
+/// The hint shown and the text it completes after typing `line`
+fn hint(line: &str) -> Option<(String, Option<String>)> {
+    let helper = DIYHinter::new(registered_commands());
+    let history = DefaultHistory::new();
+    let hint = helper.hint(line, line.len(), &Context::new(&history))?;
+    Some((hint.display().to_string(), hint.completion().map(str::to_string)))
+}
+
+#[test]
+fn test_hint_partial_command() {
+    // only the rest of the name is hinted and completed
+    assert_eq!(hint("mo"), Some(("ve".to_string(), Some("ve".to_string()))));
+    assert_eq!(hint("movel"), Some(("ist".to_string(), Some("ist".to_string()))));
+    assert_eq!(hint("move"), Some(("".to_string(), None)));
+    assert_eq!(hint("xyz"), None);
+    assert_eq!(hint(""), None);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use std::process::Command;
+
+use rustychess::json::Json;
+use rustychess::session::{response, CommandError, OutputFormat, Session};
+use rustychess_core::game::Game;
+
+fn json_session() -> (Session, common::Output) {
+    let (mut session, out) = common::session();
+    session.settings.output = OutputFormat::Json;
+    (session, out)
+}
+
+/// Runs `line` and returns the object it was answered with, without the pieces
+fn respond(session: &mut Session, out: &common::Output, line: &str) -> String {
+    let _ = session.execute(line);
+    let object = out.take();
+    assert!(object.ends_with('\n') && object.lines().count() == 1, "{:?} is not one line", object);
+    match (object.find(",\"pieces\":["), object.find("],\"outcome\"")) {
+        (Some(start), Some(end)) => format!("{}{}", &object[..start], &object[end + 1..]).trim_end().to_string(),
+        _ => object.trim_end().to_string(),
+    }
+}
+
+#[test]
+fn test_write() {
+    let object = Json::object(vec![
+        ("text", "\"quoted\"\n\ttab\\".into()),
+        ("n", (-3i64).into()),
+        ("none", Json::from(None::<bool>)),
+        ("list", vec![true, false].into()),
+        ("empty", Json::object(vec![])),
+    ]);
+    assert_eq!(
+        object.to_string(),
+        r#"{"text":"\"quoted\"\n\ttab\\","n":-3,"none":null,"list":[true,false],"empty":{}}"#
+    );
+    assert_eq!(Json::from("\u{1}").to_string(), r#""\u0001""#);
+}
+
+#[test]
+fn test_response() {
+    assert_eq!(
+        response("perft", vec![("nodes", 20u64.into())], None).to_string(),
+        r#"{"command":"perft","ok":true,"nodes":20}"#
+    );
+    assert_eq!(
+        response("load", vec![], Some(&CommandError::Failed("File not found".to_string()))).to_string(),
+        r#"{"command":"load","ok":false,"error":{"code":"failed","message":"File not found"}}"#
+    );
+}
+
+#[test]
+fn test_commands() {
+    let (mut session, out) = json_session();
+    assert_eq!(
+        respond(&mut session, &out, "move e2e4"),
+        r#"{"command":"move","ok":true,"game":"main","status":"none","move":{"uci":"e2e4","san":"e4"},"fen":"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1","turn":"black","outcome":null,"text":"ok\n"}"#
+    );
+    assert_eq!(
+        respond(&mut session, &out, "get e4"),
+        r#"{"command":"get","ok":true,"game":"main","square":"e4","piece":"P","color":"white","text":"Piece: ♙\n"}"#
+    );
+    assert_eq!(
+        respond(&mut session, &out, "movelist"),
+        r#"{"command":"movelist","ok":true,"game":"main","moves":[{"uci":"e2e4","san":"e4"}],"text":"1. e4\n"}"#
+    );
+    assert_eq!(
+        respond(&mut session, &out, "resign"),
+        r#"{"command":"resign","ok":true,"game":"main","outcome":{"result":"1-0","termination":"resignation"},"text":"1-0 (resignation)\n"}"#
+    );
+}
+
+#[test]
+fn test_pieces() {
+    let (mut session, out) = json_session();
+    session.game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+    session.execute("print").unwrap();
+    assert!(out.take().contains(
+        r#""pieces":[{"square":"e1","piece":"K","color":"white"},{"square":"h1","piece":"R","color":"white"},{"square":"e8","piece":"K","color":"black"}]"#
+    ));
+}
+
+#[test]
+fn test_promotion() {
+    let (mut session, out) = json_session();
+    session.game = Game::from_fen("4k3/P7/8/8/8/8/7p/4K3 w - - 0 1").unwrap();
+    assert_eq!(
+        respond(&mut session, &out, "move a7a8"),
+        r#"{"command":"move","ok":true,"game":"main","status":"promote","options":["Q","R","B","N"],"text":"Promote pawn, options: Q, R, B, N\n"}"#
+    );
+    assert!(respond(&mut session, &out, "q").starts_with(r#"{"command":"promote","ok":true,"game":"main","move":{"uci":"a7a8q","san":"a8=Q+"}"#));
+}
+
+#[test]
+fn test_error_codes() {
+    let (mut session, out) = json_session();
+    let error = |session: &mut Session, line: &str| {
+        let object = respond(session, &out, line);
+        assert!(object.contains(r#""ok":false"#), "{}", object);
+        object[object.find(r#""error":"#).unwrap()..].to_string()
+    };
+
+    assert_eq!(error(&mut session, "foo"), r#""error":{"code":"unknown_command","message":"Unknown command: foo"}}"#);
+    assert_eq!(error(&mut session, "get"), r#""error":{"code":"usage","message":"Usage: get <square>"}}"#);
+    assert!(error(&mut session, "move e2e5").starts_with(r#""error":{"code":"illegal_move","#));
+    assert_eq!(
+        error(&mut session, "load missing.pgn"),
+        r#""error":{"code":"failed","message":"File not found"}}"#
+    );
+    assert_eq!(
+        error(&mut session, "close main"),
+        r#""error":{"code":"failed","message":"main is the only game open"}}"#
+    );
+    session.execute("resign").unwrap();
+    out.take();
+    assert_eq!(
+        error(&mut session, "move e7e5"),
+        r#""error":{"code":"game_over","message":"The game is over: 0-1 (resignation)"}}"#
+    );
+}
+
+#[test]
+fn test_cli_failure() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rustychess"))
+        .args(["--json", "analyze", "--fen", "bad fen"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            r#"{"command":"analyze","ok":false,"error":{"code":"failed","message":"Invalid FEN string: bad fen"}}"#,
+            "\n"
+        )
+    );
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_validate_variant() {
+    let validate = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rustychess"))
+            .args(["--json", "validate", "--fen", "8/8/8/3k4/8/8/8/R7 b - - 0 1"])
+            .args(args)
+            .output()
+            .unwrap();
+        (output.status.code(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+    // kings are ordinary pieces in antichess
+    let (code, stdout) = validate(&["--variant", "antichess"]);
+    assert_eq!(code, Some(0));
+    assert!(stdout.starts_with(r#"{"command":"validate","ok":true,"#), "{}", stdout);
+    let (code, stdout) = validate(&[]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.contains("white has no king"), "{}", stdout);
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use rustychess::session::CommandError;
+use rustychess_core::game::{Game, GameResult, Termination};
+use rustychess_core::pieces::Color;
+
+#[test]
+fn test_engine_answers() {
+    let (mut session, out) = common::session();
+    session.execute("play white 1").unwrap();
+    assert_eq!(out.take(), "You play White, the engine searches 1 plies\n");
+    session.execute("move e2e4").unwrap();
+    assert_eq!(session.game.moves().len(), 2);
+    assert_eq!(session.game.turn(), Color::White);
+
+    // playing black the engine opens the game
+    let (mut session, _) = common::session();
+    session.execute("play black 1").unwrap();
+    assert_eq!(session.game.moves().len(), 1);
+    assert_eq!(session.game.turn(), Color::Black);
+}
+
+#[test]
+fn test_takeback_both_plies() {
+    let (mut session, out) = common::session();
+    session.execute("play white 1").unwrap();
+    session.execute("move e2e4").unwrap();
+    session.execute("move d2d4").unwrap();
+    assert_eq!(session.game.moves().len(), 4);
+    out.take();
+
+    session.execute("takeback").unwrap();
+    assert_eq!(out.take(), "ok\n");
+    assert_eq!(session.game.moves().len(), 2);
+    assert_eq!(session.game.turn(), Color::White);
+    session.execute("takeback").unwrap();
+    assert_eq!(session.game.fen(), Game::new().fen());
+
+    match session.execute("takeback") {
+        Err(CommandError::Failed(message)) => assert_eq!(message, "No moves to take back"),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_takeback_empty_history() {
+    let (mut session, _) = common::session();
+    session.execute("play white 1").unwrap();
+    match session.execute("takeback") {
+        Err(CommandError::Failed(message)) => assert_eq!(message, "No moves to take back"),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+
+    // playing black only the engine has moved
+    let (mut session, _) = common::session();
+    session.execute("play black 1").unwrap();
+    match session.execute("takeback") {
+        Err(CommandError::Failed(message)) => assert_eq!(message, "No moves to take back"),
+        other => panic!("expected a failure, got {:?}", other),
+    }
+    assert_eq!(session.game.moves().len(), 1);
+}
+
+#[test]
+fn test_takeback_leaves_user_to_move() {
+    let (mut session, _) = common::session();
+    session.execute("move e2e4").unwrap();
+    session.execute("move e7e5").unwrap();
+    session.execute("play white 1").unwrap();
+    assert_eq!(session.game.moves().len(), 2);
+
+    // black's move counts as the engine's answer and goes back with white's
+    session.execute("takeback").unwrap();
+    assert_eq!(session.game.moves().len(), 0);
+    assert_eq!(session.game.turn(), Color::White);
+
+    // after mating the engine only the mating move is taken back
+    let (mut session, _) = common::session();
+    for m in &["f2f3", "e7e5", "g2g4"] {
+        session.execute(&format!("move {}", m)).unwrap();
+    }
+    session.execute("play black 1").unwrap();
+    session.execute("move d8h4").unwrap();
+    assert_eq!(session.game.outcome(), Some((GameResult::BlackWins, Termination::Checkmate)));
+    session.execute("takeback").unwrap();
+    assert_eq!(session.game.moves().len(), 3);
+    assert_eq!(session.game.turn(), Color::Black);
+}
+
+#[test]
+fn test_takeback_without_engine() {
+    let (mut session, _) = common::session();
+    session.execute("move e2e4").unwrap();
+    session.execute("move e7e5").unwrap();
+    session.execute("takeback").unwrap();
+    assert_eq!(session.game.moves().len(), 1);
+    assert_eq!(session.game.turn(), Color::Black);
+}
+
+#[test]
+fn test_draw_declined() {
+    let (mut session, out) = common::session();
+    // the engine has a queen more
+    session.game = Game::from_fen("q3k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+    session.execute("play white 1").unwrap();
+    out.take();
+    session.execute("draw").unwrap();
+    assert_eq!(out.take(), "The engine declines the draw\n");
+    assert_eq!(session.game.outcome(), None);
+}
+
+#[test]
+fn test_draw_accepted() {
+    let (mut session, out) = common::session();
+    // the engine has a queen less
+    session.game = Game::from_fen("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1").unwrap();
+    session.execute("play white 1").unwrap();
+    out.take();
+    session.execute("draw").unwrap();
+    assert!(out.take().starts_with("The engine accepts the draw\n1/2-1/2 "));
+    assert_eq!(session.game.outcome(), Some((GameResult::Draw, Termination::Agreement)));
+
+    match session.execute("takeback") {
+        Err(CommandError::GameOver(_)) => {}
+        other => panic!("expected the draw to stand, got {:?}", other),
+    }
+
+    match session.execute("move e1e2") {
+        Err(CommandError::GameOver(_)) => {}
+        other => panic!("expected the game to be over, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_resign() {
+    let (mut session, _) = common::session();
+    session.execute("play white 1").unwrap();
+    session.execute("resign").unwrap();
+    assert_eq!(session.game.outcome(), Some((GameResult::BlackWins, Termination::Resignation)));
+
+    session.execute("move e2e4").unwrap_err();
+    match session.execute("takeback") {
+        Err(CommandError::GameOver(message)) => assert_eq!(message, "The game is over: 0-1 (resignation)"),
+        other => panic!("expected the resignation to stand, got {:?}", other),
+    }
+    assert_eq!(session.game.outcome(), Some((GameResult::BlackWins, Termination::Resignation)));
+}
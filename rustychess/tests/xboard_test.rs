@@ -0,0 +1,122 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// The engine run in xboard mode, its output read line by line
+struct Engine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Engine {
+    fn start() -> Engine {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rustychess"))
+            .arg("xboard")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                if sender.send(line.unwrap()).is_err() {
+                    return;
+                }
+            }
+        });
+        Engine { child, stdin, lines }
+    }
+
+    fn send(&mut self, line: &str) {
+        writeln!(self.stdin, "{}", line).unwrap();
+    }
+
+    /// Waits for a line starting with `prefix`, returns the lines read until then
+    /// and that line last
+    fn until(&self, prefix: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            match self.lines.recv_timeout(Duration::from_secs(10)) {
+                Ok(line) => {
+                    let found = line.starts_with(prefix);
+                    lines.push(line);
+                    if found {
+                        return lines;
+                    }
+                }
+                Err(_) => panic!("no line starting with {:?} in {:?}", prefix, lines),
+            }
+        }
+    }
+
+    fn expect(&self, prefix: &str) -> String {
+        self.until(prefix).pop().unwrap()
+    }
+
+    fn quit(mut self) {
+        self.send("quit");
+        self.child.wait().unwrap();
+    }
+}
+
+#[test]
+fn test_result_by_outcome() {
+    let mut engine = Engine::start();
+    engine.send("new");
+    engine.send("setboard k7/8/8/8/8/8/1r6/K7 w - - 0 1");
+    engine.send("usermove a1b2");
+    assert_eq!(engine.expect("1/2-1/2"), "1/2-1/2 {insufficient material}");
+
+    engine.send("new");
+    engine.send("force");
+    for m in ["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"].iter() {
+        engine.send(&format!("usermove {}", m));
+    }
+    assert_eq!(engine.expect("1/2-1/2"), "1/2-1/2 {threefold repetition}");
+    engine.quit();
+}
+
+#[test]
+fn test_move_now() {
+    // without limits the engine would think for seconds, "?" is read meanwhile
+    let mut engine = Engine::start();
+    engine.send("new");
+    engine.send("usermove e2e4");
+    engine.send("?");
+    assert_eq!(engine.expect("move ").len(), "move e7e5".len());
+    engine.quit();
+}
+
+#[test]
+fn test_force_while_thinking() {
+    let mut engine = Engine::start();
+    engine.send("new");
+    engine.send("usermove e2e4");
+    engine.send("force");
+    engine.send("ping 1");
+    engine.send("usermove e7e5");
+    engine.send("ping 2");
+    // the aborted search sends no move and the game goes on with the user's
+    let lines = engine.until("pong 2");
+    assert!(lines.iter().all(|line| !line.starts_with("move ")), "{:?}", lines);
+    engine.send("usermove e7e5");
+    assert_eq!(engine.expect("Illegal move"), "Illegal move: e7e5");
+    engine.quit();
+}
+
+#[test]
+fn test_level_clock() {
+    let mut engine = Engine::start();
+    engine.send("new");
+    engine.send("level 40 0:01 0");
+    // the user's second runs out on the engine's clock without any "time" sent
+    thread::sleep(Duration::from_millis(1200));
+    engine.send("usermove e2e4");
+    assert_eq!(engine.expect("0-1"), "0-1 {time forfeit}");
+    engine.quit();
+}